@@ -0,0 +1,191 @@
+//! Utilities for dealing with ANSI escape sequences in text.
+
+use std::{borrow::Cow, fmt};
+
+/// Which escape sequences in the output of evaluated programs reach the
+/// terminal (`program.escapes`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EscapePolicy {
+    /// All escape sequences are passed through.
+    #[default]
+    Allow,
+
+    /// Only the sequences which set colours and text styles are passed
+    /// through, along with newlines and tabs.
+    Colours,
+
+    /// All escape sequences and control characters are removed, other than
+    /// newlines and tabs.
+    Strip,
+}
+
+impl EscapePolicy {
+    /// Parse a policy from its name.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Self::Allow),
+            "colours" | "colors" => Some(Self::Colours),
+            "strip" => Some(Self::Strip),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EscapePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Allow => write!(f, "allow"),
+            Self::Colours => write!(f, "colours"),
+            Self::Strip => write!(f, "strip"),
+        }
+    }
+}
+
+/// Remove the escape sequences and control characters from the text that
+/// aren't allowed by the policy.
+pub fn sanitise(text: &str, policy: EscapePolicy) -> Cow<'_, str> {
+    if policy == EscapePolicy::Allow || !text.contains(is_unsafe_control) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(is_unsafe_control) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let len = escape_len(rest).unwrap_or(rest.len());
+        let sequence = &rest[..len];
+
+        // Select graphic rendition sequences only set colours and styles.
+        let is_style = sequence.starts_with("\x1b[")
+            && sequence.ends_with('m')
+            && sequence[2..len - 1].chars().all(|ch| ch.is_ascii_digit() || ch == ';');
+
+        if policy == EscapePolicy::Colours && is_style {
+            output.push_str(sequence);
+        }
+
+        rest = &rest[len..];
+    }
+
+    output.push_str(rest);
+    Cow::Owned(output)
+}
+
+/// Get the index of an escape sequence at the end of the text which hasn't
+/// been terminated yet, so that it can be held back until the rest of it
+/// arrives.
+pub fn incomplete_escape(text: &str) -> Option<usize> {
+    let start = text.rfind('\x1b')?;
+    escape_len(&text[start..]).is_none().then_some(start)
+}
+
+/// Check whether a character is a control character that could change the
+/// state of the terminal.
+fn is_unsafe_control(ch: char) -> bool {
+    ch.is_control() && !matches!(ch, '\n' | '\t')
+}
+
+/// Get the length of the escape sequence or control character at the start
+/// of the text, or `None` if the sequence isn't terminated.
+fn escape_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1);
+
+    if !text.starts_with('\x1b') {
+        return text.chars().next().map(char::len_utf8);
+    }
+
+    match chars.next() {
+        // Control sequences are terminated by a character in the range `@`
+        // to `~`.
+        Some((_, '[')) => chars.find(|(_, ch)| ('@'..='~').contains(ch)).map(|(i, _)| i + 1),
+        // Operating system commands are terminated by `BEL` or `ESC \`.
+        Some((_, ']')) => {
+            let body = &text[2..];
+            let end = body.find(['\x07', '\x1b'])?;
+            match &body[end..] {
+                terminator if terminator.starts_with('\x07') => Some(2 + end + 1),
+                terminator if terminator.starts_with("\x1b\\") => Some(2 + end + 2),
+                terminator if terminator.len() == 1 => None,
+                _ => Some(2 + end),
+            }
+        }
+        // Any other escape consists of a single character.
+        Some((i, ch)) => Some(i + ch.len_utf8()),
+        None => None,
+    }
+}
+
+/// Remove all ANSI escape sequences from the given text.
+pub fn strip_ansi(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\x1b' {
+            output.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            // Control sequences are terminated by a character in the range
+            // `@` to `~`.
+            Some('[') => {
+                for ch in chars.by_ref() {
+                    if ('@'..='~').contains(&ch) {
+                        break;
+                    }
+                }
+            }
+            // Operating system commands are terminated by `BEL` or `ESC \`.
+            Some(']') => {
+                while let Some(ch) = chars.next() {
+                    if ch == '\x07' || (ch == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // Any other escape consists of a single character.
+            _ => {}
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::{incomplete_escape, sanitise, EscapePolicy};
+
+    const OUTPUT: &str = "\x1b[31mred\x1b[0m\x1b[2J\x1b]0;title\x07\x1b]0;t\x1b\\\r\x07a\n\tb";
+
+    #[test]
+    fn strips_escapes_and_controls() {
+        assert_eq!(sanitise(OUTPUT, EscapePolicy::Strip), "reda\n\tb");
+        assert_eq!(sanitise("a\x1b[31", EscapePolicy::Strip), "a");
+        assert!(matches!(sanitise("plain\n", EscapePolicy::Strip), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn keeps_only_styles() {
+        assert_eq!(sanitise(OUTPUT, EscapePolicy::Colours), "\x1b[31mred\x1b[0ma\n\tb");
+        assert_eq!(sanitise("\x1b[1;4mx\x1b[?25l", EscapePolicy::Colours), "\x1b[1;4mx");
+    }
+
+    #[test]
+    fn allows_everything() {
+        assert!(matches!(sanitise(OUTPUT, EscapePolicy::Allow), Cow::Borrowed(OUTPUT)));
+    }
+
+    #[test]
+    fn finds_incomplete_escapes() {
+        assert_eq!(incomplete_escape("ab\x1b[3"), Some(2));
+        assert_eq!(incomplete_escape("ab\x1b]0;title"), Some(2));
+        assert_eq!(incomplete_escape("ab\x1b[31m"), None);
+        assert_eq!(incomplete_escape("ab"), None);
+    }
+}
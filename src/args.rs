@@ -0,0 +1,170 @@
+//! Command line arguments that are accepted by the interactive mode.
+
+use std::{path::PathBuf, time::Duration};
+
+use clap::{Parser, Subcommand};
+use clap_complete::Shell;
+
+use crate::options::parse_duration;
+
+/// The command line arguments of the interactive mode.
+#[derive(Parser, Debug)]
+#[command(name = "hashi", version = env!("EXECUTABLE_VERSION"), about = "The Hash interactive shell")]
+pub struct Arguments {
+    /// The configuration file to use, instead of the default one.
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Start the session without the standard prelude.
+    #[arg(long, conflicts_with = "prelude")]
+    pub no_prelude: bool,
+
+    /// Start the session with a custom prelude instead of the standard one.
+    #[arg(long, value_name = "FILE")]
+    pub prelude: Option<PathBuf>,
+
+    /// Use a plain prompt without line editing or escape sequences, this is
+    /// the default when the terminal is dumb or the input is piped.
+    #[arg(long)]
+    pub dumb: bool,
+
+    /// Write the status dumps that are requested with `SIGUSR1` to the given
+    /// file, instead of standard error.
+    #[arg(long, value_name = "FILE")]
+    pub status_file: Option<PathBuf>,
+
+    /// Record where time is spent across the compilations of the session,
+    /// and write it to the given file on exit, as an SVG flamegraph if the
+    /// file has the `svg` extension and as folded stacks otherwise.
+    #[arg(long, value_name = "FILE")]
+    pub flamegraph: Option<PathBuf>,
+
+    /// The locale that the messages of the REPL are printed in, e.g. `fr`.
+    #[arg(long, value_name = "LOCALE")]
+    pub locale: Option<String>,
+
+    /// Write each input along with its AST to numbered files in the given
+    /// directory.
+    #[arg(long, value_name = "DIR", value_hint = clap::ValueHint::DirPath)]
+    pub dump_dir: Option<PathBuf>,
+
+    /// Replay the inputs of the previous session, if it didn't end cleanly.
+    #[arg(long)]
+    pub recover: bool,
+
+    /// Don't let evaluated programs read from the standard input, which is
+    /// recommended when running as a daemon for untrusted clients.
+    #[arg(long)]
+    pub no_program_stdin: bool,
+
+    /// Write the events of the session (inputs, results, diagnostics,
+    /// commands and timings) to the given file as JSON lines.
+    #[arg(long, value_name = "FILE")]
+    pub event_log: Option<PathBuf>,
+
+    /// Write the output of evaluated programs to the given file, rather than
+    /// the terminal.
+    #[arg(long, value_name = "FILE")]
+    pub program_output: Option<PathBuf>,
+
+    /// Evaluate an input and exit, this is run by the daemon if one is
+    /// listening.
+    #[arg(short, long, value_name = "INPUT")]
+    pub eval: Option<String>,
+
+    /// The socket of the daemon, instead of the default one.
+    #[arg(long, value_name = "FILE", global = true)]
+    pub socket: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub mode: Option<Mode>,
+}
+
+/// The modes that the interactive mode can run in, other than the default
+/// interactive session.
+#[derive(Subcommand, Debug)]
+pub enum Mode {
+    /// Keep a session running in the background, which inputs are sent to
+    /// with `attach` or `--eval`.
+    Daemon {
+        /// Shut the daemon down after no client has connected for this long,
+        /// e.g. `30m`.
+        #[arg(long, value_name = "DURATION", value_parser = duration, default_value = "30m")]
+        idle_timeout: Duration,
+    },
+
+    /// Start an interactive prompt which runs inputs in the daemon.
+    Attach,
+
+    /// Grade a submission against the checks of a specification, printing
+    /// the score as JSON.
+    Grade {
+        /// The specification, a Hash source with check lines.
+        #[arg(long, value_name = "FILE")]
+        spec: PathBuf,
+
+        /// The submission to grade.
+        #[arg(long, value_name = "FILE")]
+        submission: PathBuf,
+
+        /// The CPU time that grading may use, e.g. `10s`.
+        #[arg(long, value_name = "DURATION", value_parser = duration, default_value = "10s")]
+        cpu_limit: Duration,
+
+        /// The memory that grading may use, in megabytes.
+        #[arg(long, value_name = "MB", default_value_t = 512)]
+        memory_limit: u64,
+
+        /// The wall-clock time that grading may take, e.g. `30s`.
+        #[arg(long, value_name = "DURATION", value_parser = duration, default_value = "30s")]
+        time_limit: Duration,
+
+        /// Grade in this process under the limits, which is how grading runs
+        /// its child process.
+        #[arg(long, hide = true)]
+        child: bool,
+    },
+
+    /// Work through the lessons of a tutorial at the prompt.
+    Tutorial {
+        /// A lesson file, or a directory of lesson files.
+        path: PathBuf,
+    },
+
+    /// Run the inputs of an event log or a script in a sandboxed session,
+    /// printing a report of their outcomes and the resources that they used
+    /// as JSON lines.
+    SandboxRun {
+        /// The event log or script to run.
+        transcript: PathBuf,
+
+        /// The CPU time that the transcript may use, e.g. `10s`.
+        #[arg(long, value_name = "DURATION", value_parser = duration, default_value = "10s")]
+        cpu_limit: Duration,
+
+        /// The memory that the transcript may use, in megabytes.
+        #[arg(long, value_name = "MB", default_value_t = 512)]
+        memory_limit: u64,
+
+        /// The wall-clock time that the transcript may take, e.g. `30s`.
+        #[arg(long, value_name = "DURATION", value_parser = duration, default_value = "30s")]
+        time_limit: Duration,
+
+        /// Run the transcript in this process under the limits, which is how
+        /// the sandbox runs its child process.
+        #[arg(long, hide = true)]
+        child: bool,
+    },
+
+    /// Print the completion script of a shell, e.g. `hashi completions bash
+    /// > /etc/bash_completion.d/hashi`.
+    Completions {
+        /// The shell to generate the completion script for.
+        shell: Shell,
+    },
+}
+
+/// Parse a duration argument.
+fn duration(text: &str) -> Result<Duration, String> {
+    parse_duration(text).ok_or_else(|| format!("invalid duration `{text}`, e.g. `500ms` or `30m`"))
+}
@@ -0,0 +1,109 @@
+//! Assertions on the results of expressions with `:assert`. When an assertion
+//! fails, the operands of the comparison or logical operators at the top
+//! level of the expression are evaluated and printed, e.g.
+//!
+//! ```text
+//! >>> :assert x + 1 == y
+//! assertion failed: x + 1 == y
+//!   x + 1 = 3
+//!   y = 4
+//! ```
+//!
+//! ##Note: the operands are evaluated again to show their values, so
+//! expressions with side effects are run more than once when an assertion
+//! fails.
+
+use crate::{
+    error::{InteractiveError, InteractiveResult},
+    session::Session,
+};
+
+/// The operators which the expression of an assertion is split on to find
+/// the operands to show. Longer operators are listed first so that they
+/// take precedence.
+const OPERATORS: &[&str] = &["==", "!=", "<=", ">=", "&&", "||", "<", ">"];
+
+/// Evaluate the assertion, returning whether it holds. If it doesn't, the
+/// values of its operands are printed.
+pub fn check_assertion(session: &mut Session, expr: &str) -> InteractiveResult<bool> {
+    match session.evaluate(expr)?.as_str() {
+        "true" => return Ok(true),
+        "false" => {}
+        _ => return Err(InteractiveError::NotBoolean(expr.to_string())),
+    }
+
+    println!("assertion failed: {expr}");
+
+    for operand in operands(expr) {
+        let operand = operand.trim_start_matches('!').trim();
+        if is_literal(operand) {
+            continue;
+        }
+
+        match session.evaluate(operand) {
+            Ok(value) => println!("  {operand} = {value}"),
+            Err(_) => println!("  {operand} = <failed>"),
+        }
+    }
+
+    Ok(false)
+}
+
+/// Split the expression into the operands of the comparison and logical
+/// operators that aren't nested within brackets or strings. If the
+/// expression has no such operators, then it is its own operand.
+fn operands(expr: &str) -> Vec<&str> {
+    let mut operands = vec![];
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    let mut index = 0;
+
+    while index < expr.len() {
+        let rest = &expr[index..];
+        let ch = rest.chars().next().unwrap_or_default();
+
+        match quote {
+            Some(_) if ch == '\\' => {
+                index += 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+                continue;
+            }
+            Some(q) if ch == q => quote = None,
+            Some(_) => {}
+            None => match ch {
+                '"' | '\'' => quote = Some(ch),
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                _ if depth == 0 => {
+                    // Arrows aren't comparisons.
+                    let is_arrow =
+                        ch == '>' && matches!(expr[..index].chars().last(), Some('-' | '='));
+                    let operator = OPERATORS.iter().find(|op| rest.starts_with(**op));
+
+                    if let Some(operator) = operator.filter(|_| !is_arrow) {
+                        operands.push(expr[start..index].trim());
+                        index += operator.len();
+                        start = index;
+                        continue;
+                    }
+                }
+                _ => {}
+            },
+        }
+
+        index += ch.len_utf8();
+    }
+
+    operands.push(expr[start..].trim());
+    operands.retain(|operand| !operand.is_empty());
+    operands
+}
+
+/// Check whether the operand is a literal, whose value is already shown by
+/// the expression.
+fn is_literal(operand: &str) -> bool {
+    matches!(operand, "true" | "false")
+        || operand.starts_with(['"', '\''])
+        || operand.parse::<f64>().is_ok()
+        || operand.replace('_', "").parse::<i128>().is_ok()
+}
@@ -0,0 +1,242 @@
+//! Capturing what is printed to standard output, so that the values that
+//! the compiler prints when evaluating an input can be inspected by the REPL.
+//! Since the compiler writes directly to the standard output, the file
+//! descriptor itself is redirected to a pipe while the operation runs. The
+//! output can either be collected, or streamed as it is written.
+
+use std::io::{self, Write};
+
+/// Run the given operation, returning its result along with everything that
+/// was written to standard output while it ran.
+pub fn capture_stdout<T>(operation: impl FnOnce() -> T) -> io::Result<(T, String)> {
+    if cfg!(not(unix)) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "output can't be captured on this platform",
+        ));
+    }
+
+    let mut output = String::new();
+    let result = stream_stdout(operation, |chunk, _| output.push_str(chunk))?;
+    Ok((result, output))
+}
+
+/// Run the given operation, passing everything that it writes to standard
+/// output to `on_output` as it is written, along with the original standard
+/// output. Since the standard output is redirected whilst the operation runs,
+/// anything that `on_output` shows must be written to the original one.
+#[cfg(unix)]
+pub fn stream_stdout<T>(
+    operation: impl FnOnce() -> T,
+    mut on_output: impl FnMut(&str, &mut dyn Write) + Send,
+) -> io::Result<T> {
+    use std::{fs::File, io::Read, os::unix::io::FromRawFd, thread};
+
+    io::stdout().flush()?;
+
+    let mut fds = [0; 2];
+
+    // ##Safety: `fds` has room for both ends of the pipe.
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let [reader, writer] = fds;
+
+    // ##Safety: the descriptors are valid, and the original standard output
+    // is restored before returning.
+    let saved = unsafe {
+        let saved = libc::dup(libc::STDOUT_FILENO);
+        libc::dup2(writer, libc::STDOUT_FILENO);
+        libc::close(writer);
+        saved
+    };
+
+    // ##Safety: the read end of the pipe is exclusively owned by the thread.
+    let mut pipe = unsafe { File::from_raw_fd(reader) };
+
+    // The drain writes to its own duplicate of the original standard output,
+    // since writing to the redirected one would feed the output back into
+    // the pipe.
+    //
+    // ##Safety: `saved` is valid until it is restored below.
+    let terminal = unsafe { libc::dup(saved) };
+    if terminal < 0 {
+        let err = io::Error::last_os_error();
+
+        // ##Safety: as above.
+        unsafe {
+            libc::dup2(saved, libc::STDOUT_FILENO);
+            libc::close(saved);
+        }
+
+        drop(pipe);
+        return Err(err);
+    }
+
+    // ##Safety: the duplicate is exclusively owned by the thread.
+    let mut terminal = unsafe { File::from_raw_fd(terminal) };
+
+    // The pipe is drained on a separate thread, which passes the output on
+    // as it arrives, and so that the operation doesn't block when it writes
+    // more than the pipe can buffer.
+    thread::scope(|scope| {
+        let drain = scope.spawn(move || {
+            let mut buffer = [0; 4096];
+            let mut pending = vec![];
+
+            loop {
+                let read = pipe.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+
+                pending.extend_from_slice(&buffer[..read]);
+
+                // A chunk might end part of the way through a character, in
+                // which case the rest of the character is waited for.
+                let valid = match std::str::from_utf8(&pending) {
+                    Ok(text) => text.len(),
+                    Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                    Err(_) => pending.len(),
+                };
+
+                on_output(&String::from_utf8_lossy(&pending[..valid]), &mut terminal);
+                pending.drain(..valid);
+            }
+
+            if !pending.is_empty() {
+                on_output(&String::from_utf8_lossy(&pending), &mut terminal);
+            }
+
+            Ok::<_, io::Error>(())
+        });
+
+        let result = operation();
+        let flushed = io::stdout().flush();
+
+        // ##Safety: `saved` is a duplicate of the original standard output.
+        // This also closes the last write end of the pipe, which ends the
+        // drain.
+        unsafe {
+            libc::dup2(saved, libc::STDOUT_FILENO);
+            libc::close(saved);
+        }
+
+        flushed?;
+        drain.join().map_err(|_| io::Error::other("failed to read captured output"))??;
+        Ok(result)
+    })
+}
+
+/// Output can't be redirected on non-unix platforms, so it is written
+/// straight to the terminal.
+#[cfg(not(unix))]
+pub fn stream_stdout<T>(
+    operation: impl FnOnce() -> T,
+    _: impl FnMut(&str, &mut dyn Write) + Send,
+) -> io::Result<T> {
+    Ok(operation())
+}
+
+/// Run the given operation with the standard input connected to the null
+/// device, so that anything that it reads sees the end of the input.
+#[cfg(unix)]
+pub fn without_stdin<T>(operation: impl FnOnce() -> T) -> T {
+    use std::{fs::File, os::unix::io::AsRawFd};
+
+    let null = File::open("/dev/null");
+
+    // ##Safety: the descriptors are valid, and the original standard input
+    // is restored before returning. If the null device can't be opened, the
+    // standard input is closed instead, so reads fail rather than reaching
+    // the terminal.
+    let saved = unsafe {
+        let saved = libc::dup(libc::STDIN_FILENO);
+        match &null {
+            Ok(null) => libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO),
+            Err(_) => libc::close(libc::STDIN_FILENO),
+        };
+        saved
+    };
+
+    let result = operation();
+
+    // ##Safety: `saved` is a duplicate of the original standard input.
+    unsafe {
+        libc::dup2(saved, libc::STDIN_FILENO);
+        libc::close(saved);
+    }
+
+    result
+}
+
+/// The standard input can't be redirected on non-unix platforms.
+#[cfg(not(unix))]
+pub fn without_stdin<T>(operation: impl FnOnce() -> T) -> T {
+    operation()
+}
+
+/// Run the given operation with the standard error redirected to wherever
+/// the standard output currently goes, e.g. so that the diagnostics of the
+/// compiler are captured along with the output of an input.
+#[cfg(unix)]
+pub fn stderr_to_stdout<T>(operation: impl FnOnce() -> T) -> T {
+    let _ = io::stderr().flush();
+
+    // ##Safety: the descriptors are valid, and the original standard error
+    // is restored before returning.
+    let saved = unsafe {
+        let saved = libc::dup(libc::STDERR_FILENO);
+        libc::dup2(libc::STDOUT_FILENO, libc::STDERR_FILENO);
+        saved
+    };
+
+    let result = operation();
+    let _ = io::stderr().flush();
+
+    // ##Safety: `saved` is a duplicate of the original standard error.
+    unsafe {
+        libc::dup2(saved, libc::STDERR_FILENO);
+        libc::close(saved);
+    }
+
+    result
+}
+
+/// The standard error can't be redirected on non-unix platforms.
+#[cfg(not(unix))]
+pub fn stderr_to_stdout<T>(operation: impl FnOnce() -> T) -> T {
+    operation()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io::{self, Write};
+
+    use super::stream_stdout;
+
+    #[test]
+    fn streams_several_lines() {
+        let mut streamed = String::new();
+
+        stream_stdout(
+            || {
+                let mut stdout = io::stdout();
+                for line in 0..100 {
+                    writeln!(stdout, "line {line}").unwrap();
+                    stdout.flush().unwrap();
+                }
+            },
+            |chunk, terminal| {
+                // Showing the output must not feed it back into the stream.
+                streamed.push_str(chunk);
+                terminal.write_all(chunk.as_bytes()).unwrap();
+            },
+        )
+        .unwrap();
+
+        let expected: String = (0..100).map(|line| format!("line {line}\n")).collect();
+        assert_eq!(streamed, expected);
+    }
+}
@@ -0,0 +1,86 @@
+//! Hidden checks of the definitions in a session, which are used to check
+//! the answers to the exercises of tutorials, and to grade submissions. Checks
+//! are written one per line, as one of:
+//!
+//! ```text
+//! :check double(2) == 4
+//! :expect double(3) => 6
+//! :type double : (i32) -> i32
+//! ```
+//!
+//! which check that a boolean expression holds, that an expression evaluates
+//! to the given value, and that an expression has the given type.
+
+use std::fmt;
+
+use crate::{capture::capture_stdout, messages::message, session::Session};
+
+/// A check of the definitions in a session.
+#[derive(Debug, Clone)]
+pub enum Check {
+    /// A boolean expression that must hold.
+    Holds(String),
+
+    /// An expression that must evaluate to the given value.
+    Equals { expr: String, value: String },
+
+    /// An expression that must have the given type.
+    HasType { expr: String, ty: String },
+}
+
+impl Check {
+    /// Parse a check from a line, returning `None` if the line isn't a check.
+    pub fn parse(line: &str) -> Option<Result<Self, String>> {
+        let (keyword, rest) = line.trim().split_once(char::is_whitespace)?;
+        let rest = rest.trim();
+
+        let check = match keyword {
+            ":check" => Ok(Check::Holds(rest.to_string())),
+            ":expect" => match rest.rsplit_once("=>") {
+                Some((expr, value)) => Ok(Check::Equals {
+                    expr: expr.trim().to_string(),
+                    value: value.trim().to_string(),
+                }),
+                None => Err(message!("invalid-check", syntax = "<expr> => <value>", found = rest)),
+            },
+            ":type" => match rest.split_once(" : ") {
+                Some((expr, ty)) => {
+                    Ok(Check::HasType { expr: expr.trim().to_string(), ty: ty.trim().to_string() })
+                }
+                None => Err(message!("invalid-check", syntax = "<expr> : <type>", found = rest)),
+            },
+            _ => return None,
+        };
+
+        Some(check)
+    }
+
+    /// Run the check in the session. Nothing is printed, since the checks
+    /// are hidden from the user.
+    pub fn passes(&self, session: &mut Session) -> bool {
+        let (expr, expected) = match self {
+            Check::Holds(expr) => (expr.clone(), "true"),
+            Check::Equals { expr, value } => (expr.clone(), value.as_str()),
+            // The expression is bound with the type as an annotation, which
+            // only type checks if the expression has the type.
+            Check::HasType { expr, ty } => (format!("{{ _check: {ty} = {expr}; true }}"), "true"),
+        };
+
+        match capture_stdout(|| session.evaluate(&expr)) {
+            Ok((Ok(output), _)) => {
+                output.lines().last().is_some_and(|line| line.trim() == expected)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Holds(expr) => write!(f, ":check {expr}"),
+            Check::Equals { expr, value } => write!(f, ":expect {expr} => {value}"),
+            Check::HasType { expr, ty } => write!(f, ":type {expr} : {ty}"),
+        }
+    }
+}
@@ -1,6 +1,9 @@
 //! Hash interactive mode commands.
 
-use crate::error::{InteractiveError, InteractiveResult};
+use crate::{
+    error::{InteractiveError, InteractiveResult},
+    export::DataFormat,
+};
 
 /// Enum representing the variants of command that can be executed in the
 /// interactive mode.
@@ -19,16 +22,156 @@ pub enum InteractiveCommand<'i> {
     /// Get the type of the expression
     Type(&'i str),
 
+    /// Get the type of the name at a position of a source, or of the last
+    /// input if no source is given
+    TypeAt { source: Option<&'i str>, line: usize, column: usize },
+
     /// Display the node tree of the expression
     Display(&'i str),
 
     /// Just prints the version of the current interactive mode
     Version,
 
+    /// Enable or disable the recording of a flamegraph of the session
+    FlameGraph(bool),
+
+    /// Re-run the last input that failed with maximum verbosity, to explain
+    /// why it failed
+    Why,
+
+    /// Bind the first error of the last input that failed to the given name,
+    /// as a tuple of its code, message and spans
+    LastError(&'i str),
+
+    /// Evaluate two expressions and show the structural differences between
+    /// their values
+    Diff(&'i str, &'i str),
+
+    /// Infer the types of two expressions and show the parts of the types
+    /// which disagree
+    CompareTypes(&'i str, &'i str),
+
+    /// Assert that a boolean expression holds
+    Assert(&'i str),
+
+    /// Evaluate an expression and write its value to a file in the given
+    /// format, which is inferred from the file if it isn't given. The piped
+    /// input is exported if the expression is empty, and the value is
+    /// printed if the file is `-`
+    Export { expr: &'i str, format: Option<DataFormat>, out: &'i str },
+
+    /// Read the data in a file into a binding of the session, the format is
+    /// inferred from the file if it isn't given
+    Read { path: &'i str, format: Option<DataFormat>, name: &'i str },
+
+    /// Show all of the output of the last evaluation, which was truncated
+    ShowAll,
+
+    /// Write all of the output of the last evaluation to a file
+    SaveOutput(&'i str),
+
+    /// Show the piped input in a pager
+    Page,
+
+    /// Search the sources of the session, or the piped input, for a pattern,
+    /// showing the given number of lines of context around each match
+    Grep { pattern: &'i str, context: usize },
+
+    /// Show the source of the definition of a name
+    Def(&'i str),
+
+    /// Open a scratch buffer, which is evaluated whenever it is saved
+    Scratch(Option<&'i str>),
+
+    /// Show an outline of the items that a file or loaded module declares
+    Outline(&'i str),
+
+    /// Rename a binding across the inputs of the session, writing the changes
+    /// to the loaded files that mention it if `write` is set
+    Rename { old: &'i str, new: &'i str, write: bool },
+
+    /// Apply the suggested fix for the last input that failed, and retry it
+    Fix,
+
+    /// Load a file, or a directory of sources, into the session
+    Load(&'i str),
+
+    /// Continue loading the modules of a `:load` that was interrupted
+    ContinueLoad,
+
+    /// Load the modules that have changed since they were loaded again
+    Reload,
+
+    /// Run each of the inputs in a script
+    Source(&'i str),
+
+    /// Discard all of the definitions of the session, and start afresh
+    Reset,
+
+    /// Apply a profile of options from the configuration, or list the
+    /// profiles if none is given
+    Profile(Option<&'i str>),
+
+    /// Show the inputs of the session, only including the ones that failed if
+    /// `errors` is set
+    Timeline { errors: bool },
+
+    /// Show the usage statistics of this session, or of all sessions
+    Stats { all: bool },
+
+    /// Re-read the configuration file, applying it to the session
+    ReloadConfig,
+
+    /// Set an option of the REPL, boolean options are toggled if no value is
+    /// given
+    Set { key: &'i str, value: Option<&'i str> },
+
     /// A string representing a statement that will be executed
     Code(&'i str),
 }
 
+impl InteractiveCommand<'_> {
+    /// Get the name of the command, this is the name that the command is
+    /// referred to by in the configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            InteractiveCommand::Quit => "quit",
+            InteractiveCommand::Clear => "clear",
+            InteractiveCommand::Type(_) | InteractiveCommand::TypeAt { .. } => "type",
+            InteractiveCommand::Display(_) => "display",
+            InteractiveCommand::Version => "version",
+            InteractiveCommand::FlameGraph(_) => "flamegraph",
+            InteractiveCommand::Why => "why",
+            InteractiveCommand::LastError(_) => "last-error",
+            InteractiveCommand::Diff(..) => "diff",
+            InteractiveCommand::CompareTypes(..) => "compare-types",
+            InteractiveCommand::Assert(_) => "assert",
+            InteractiveCommand::Export { .. } => "export",
+            InteractiveCommand::Read { .. } => "read",
+            InteractiveCommand::ShowAll => "show-all",
+            InteractiveCommand::SaveOutput(_) => "save-output",
+            InteractiveCommand::Page => "page",
+            InteractiveCommand::Grep { .. } => "grep",
+            InteractiveCommand::Def(_) => "def",
+            InteractiveCommand::Scratch(_) => "scratch",
+            InteractiveCommand::Outline(_) => "outline",
+            InteractiveCommand::Rename { .. } => "rename",
+            InteractiveCommand::Fix => "fix",
+            InteractiveCommand::Load(_)
+            | InteractiveCommand::ContinueLoad
+            | InteractiveCommand::Reload => "load",
+            InteractiveCommand::Source(_) => "source",
+            InteractiveCommand::Reset => "reset",
+            InteractiveCommand::ReloadConfig => "reload-config",
+            InteractiveCommand::Timeline { .. } => "timeline",
+            InteractiveCommand::Stats { .. } => "stats",
+            InteractiveCommand::Profile(_) => "profile",
+            InteractiveCommand::Set { .. } => "set",
+            InteractiveCommand::Code(_) => "code",
+        }
+    }
+}
+
 struct CommandDelegator<'i> {
     /// The command to execute.
     command: &'i str,
@@ -85,8 +228,307 @@ impl<'a> TryFrom<&'a str> for InteractiveCommand<'a> {
             ":c" | ":cls" | ":clear" => d.without_arg(InteractiveCommand::Clear),
             ":v" => d.without_arg(InteractiveCommand::Version),
             ":t" => d.with_arg(|arg| Ok(InteractiveCommand::Type(arg))),
+            ":type-at" => d.with_arg(|arg| {
+                let mut parts = arg.trim().rsplitn(3, ':');
+                let column = parts.next().and_then(|column| column.parse().ok());
+                let line = parts.next().and_then(|line| line.parse().ok());
+
+                match (line, column) {
+                    (Some(line), Some(column)) => {
+                        Ok(InteractiveCommand::TypeAt { source: parts.next(), line, column })
+                    }
+                    _ => Err(InteractiveError::InvalidArgument {
+                        command: command.to_string(),
+                        arg: arg.trim().to_string(),
+                    }),
+                }
+            }),
             ":d" => d.with_arg(|arg| Ok(InteractiveCommand::Display(arg))),
+            ":flamegraph" => d.with_arg(|arg| match arg.trim() {
+                "on" => Ok(InteractiveCommand::FlameGraph(true)),
+                "off" => Ok(InteractiveCommand::FlameGraph(false)),
+                arg => Err(InteractiveError::InvalidArgument {
+                    command: command.to_string(),
+                    arg: arg.to_string(),
+                }),
+            }),
+            ":why" => d.without_arg(InteractiveCommand::Why),
+            ":last-error" => d.with_arg(|arg| match arg.trim().strip_prefix("as ") {
+                Some(name) => Ok(InteractiveCommand::LastError(name.trim())),
+                None => Err(InteractiveError::MissingOperand("as".to_string())),
+            }),
+            ":diff" => d.with_arg(|arg| match split_operands(arg) {
+                Some((left, right)) => Ok(InteractiveCommand::Diff(left, right)),
+                None => Err(InteractiveError::InvalidArgument {
+                    command: command.to_string(),
+                    arg: arg.trim().to_string(),
+                }),
+            }),
+            ":compare-types" => d.with_arg(|arg| match split_operands(arg) {
+                Some((left, right)) => Ok(InteractiveCommand::CompareTypes(left, right)),
+                None => Err(InteractiveError::InvalidArgument {
+                    command: command.to_string(),
+                    arg: arg.trim().to_string(),
+                }),
+            }),
+            ":assert" => d.with_arg(|arg| Ok(InteractiveCommand::Assert(arg.trim()))),
+            ":export" => d.with_arg(|arg| {
+                let invalid = |arg: &str| InteractiveError::InvalidArgument {
+                    command: command.to_string(),
+                    arg: arg.to_string(),
+                };
+
+                // A trailing `-` is short for `--out -`.
+                let (arg, mut out) = match arg.trim() {
+                    "-" => ("", Some("-")),
+                    arg => match arg.strip_suffix(" -") {
+                        Some(rest) => (rest, Some("-")),
+                        None => (arg, None),
+                    },
+                };
+
+                let (expr, flags) =
+                    split_flags(arg, &["--format", "--out"]).ok_or_else(|| invalid(arg))?;
+                let mut format = None;
+
+                for (flag, value) in flags {
+                    match flag {
+                        "--format" => {
+                            format = Some(DataFormat::parse(value).ok_or_else(|| invalid(value))?)
+                        }
+                        "--out" => out = Some(value),
+                        flag => return Err(invalid(flag)),
+                    }
+                }
+
+                match out {
+                    None => Err(InteractiveError::MissingOperand("--out".to_string())),
+                    Some(out) => Ok(InteractiveCommand::Export { expr, format, out }),
+                }
+            }),
+            ":read" => d.with_arg(|arg| {
+                let invalid = |arg: &str| InteractiveError::InvalidArgument {
+                    command: command.to_string(),
+                    arg: arg.to_string(),
+                };
+
+                let (rest, name) = arg
+                    .trim()
+                    .rsplit_once(" as ")
+                    .ok_or_else(|| InteractiveError::MissingOperand("as".to_string()))?;
+                let (path, flags) =
+                    split_flags(rest, &["--format"]).ok_or_else(|| invalid(rest.trim()))?;
+                let mut format = None;
+
+                for (flag, value) in flags {
+                    match flag {
+                        "--format" => {
+                            format = Some(DataFormat::parse(value).ok_or_else(|| invalid(value))?)
+                        }
+                        flag => return Err(invalid(flag)),
+                    }
+                }
+
+                match path {
+                    "" => Err(InteractiveError::MissingOperand(command.to_string())),
+                    path => Ok(InteractiveCommand::Read { path, format, name: name.trim() }),
+                }
+            }),
+            ":show-all" => d.without_arg(InteractiveCommand::ShowAll),
+            ":save-output" => d.with_arg(|arg| Ok(InteractiveCommand::SaveOutput(arg.trim()))),
+            ":page" => d.without_arg(InteractiveCommand::Page),
+            ":grep" => d.with_arg(|arg| match arg.trim().strip_prefix("-C") {
+                Some(rest) => {
+                    let (count, pattern) =
+                        rest.trim_start().split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+                    match (count.parse(), pattern.trim()) {
+                        (Ok(context), pattern) if !pattern.is_empty() => {
+                            Ok(InteractiveCommand::Grep { pattern, context })
+                        }
+                        (Ok(_), _) => Err(InteractiveError::MissingOperand(command.to_string())),
+                        (Err(_), _) => Err(InteractiveError::InvalidArgument {
+                            command: command.to_string(),
+                            arg: count.to_string(),
+                        }),
+                    }
+                }
+                None => Ok(InteractiveCommand::Grep { pattern: arg.trim(), context: 0 }),
+            }),
+            ":def" => d.with_arg(|arg| Ok(InteractiveCommand::Def(arg.trim()))),
+            ":scratch" => match rest.trim() {
+                "" => Ok(InteractiveCommand::Scratch(None)),
+                name if name.chars().all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-') => {
+                    Ok(InteractiveCommand::Scratch(Some(name)))
+                }
+                name => Err(InteractiveError::InvalidArgument {
+                    command: command.to_string(),
+                    arg: name.to_string(),
+                }),
+            },
+            ":outline" => d.with_arg(|arg| Ok(InteractiveCommand::Outline(arg.trim()))),
+            ":rename" => d.with_arg(|arg| {
+                let parts: Vec<_> = arg.split_whitespace().collect();
+
+                match parts.as_slice() {
+                    [old, new] => Ok(InteractiveCommand::Rename { old, new, write: false }),
+                    [old, new, "--write"] => {
+                        Ok(InteractiveCommand::Rename { old, new, write: true })
+                    }
+                    _ => Err(InteractiveError::InvalidArgument {
+                        command: command.to_string(),
+                        arg: arg.trim().to_string(),
+                    }),
+                }
+            }),
+            ":fix" => d.without_arg(InteractiveCommand::Fix),
+            ":load" => d.with_arg(|arg| match arg.trim() {
+                "--continue" => Ok(InteractiveCommand::ContinueLoad),
+                path => Ok(InteractiveCommand::Load(path)),
+            }),
+            ":reload" => d.without_arg(InteractiveCommand::Reload),
+            ":source" => d.with_arg(|arg| Ok(InteractiveCommand::Source(arg.trim()))),
+            ":reset" => d.without_arg(InteractiveCommand::Reset),
+            ":profile" => match rest.trim() {
+                "" => Ok(InteractiveCommand::Profile(None)),
+                name => Ok(InteractiveCommand::Profile(Some(name))),
+            },
+            ":timeline" => match rest.trim() {
+                "" => Ok(InteractiveCommand::Timeline { errors: false }),
+                "--errors" => Ok(InteractiveCommand::Timeline { errors: true }),
+                arg => Err(InteractiveError::InvalidArgument {
+                    command: command.to_string(),
+                    arg: arg.to_string(),
+                }),
+            },
+            ":stats" => match rest.trim() {
+                "" => Ok(InteractiveCommand::Stats { all: false }),
+                "all" => Ok(InteractiveCommand::Stats { all: true }),
+                arg => Err(InteractiveError::InvalidArgument {
+                    command: command.to_string(),
+                    arg: arg.to_string(),
+                }),
+            },
+            ":reload-config" => d.without_arg(InteractiveCommand::ReloadConfig),
+            ":set" => d.with_arg(|arg| {
+                let mut parts = arg.split_whitespace();
+                let key = parts.next().unwrap_or_default();
+                let value = parts.next();
+
+                match parts.next() {
+                    Some(_) => Err(InteractiveError::UnexpectedArgument(command.to_string())),
+                    None => Ok(InteractiveCommand::Set { key, value }),
+                }
+            }),
             _ => Err(InteractiveError::UnrecognisedCommand(command.to_string())),
         }
     }
 }
+
+/// Split the argument of a command into two expressions. The expressions are
+/// separated by a comma which isn't nested within brackets, or by whitespace
+/// if neither of them contains any whitespace outside of brackets.
+fn split_operands(arg: &str) -> Option<(&str, &str)> {
+    let arg = arg.trim();
+    let mut depth = 0usize;
+    let mut separators = vec![];
+
+    for (index, ch) in arg.char_indices() {
+        match ch {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                let (left, right) = (arg[..index].trim(), arg[index + 1..].trim());
+                return (!left.is_empty() && !right.is_empty()).then_some((left, right));
+            }
+            ch if ch.is_whitespace() && depth == 0 => separators.push(index),
+            _ => {}
+        }
+    }
+
+    let (first, last) = (*separators.first()?, *separators.last()?);
+    let (left, right) = (&arg[..first], arg[last..].trim_start());
+
+    // Every separator must be part of the single run of whitespace between
+    // the two expressions.
+    (arg[first..last].trim().is_empty()).then_some((left, right))
+}
+
+/// Split the argument of a command into the leading operand and the
+/// `--flag value` pairs that follow it, where each flag is one of `known`.
+/// Flags are only taken from the end of the argument, so that an operand
+/// which contains `--`, such as an expression, isn't split. Returns `None`
+/// if the last flag is missing its value.
+fn split_flags<'a>(arg: &'a str, known: &[&str]) -> Option<(&'a str, Vec<(&'a str, &'a str)>)> {
+    let mut operand = arg.trim();
+    let mut pairs = vec![];
+
+    if operand.rsplit(char::is_whitespace).next().is_some_and(|last| known.contains(&last)) {
+        return None;
+    }
+
+    while let Some((rest, value)) = operand.rsplit_once(char::is_whitespace) {
+        let rest = rest.trim_end();
+        let (before, flag) = rest.rsplit_once(char::is_whitespace).unwrap_or(("", rest));
+
+        if !known.contains(&flag) {
+            break;
+        }
+
+        pairs.push((flag, value));
+        operand = before.trim_end();
+    }
+
+    pairs.reverse();
+    Some((operand, pairs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_flags, split_operands};
+
+    const FLAGS: &[&str] = &["--format", "--out"];
+
+    #[test]
+    fn splits_trailing_flags() {
+        assert_eq!(
+            split_flags("xs --format csv --out data.csv", FLAGS),
+            Some(("xs", vec![("--format", "csv"), ("--out", "data.csv")]))
+        );
+        assert_eq!(split_flags("  f(1, 2)  ", FLAGS), Some(("f(1, 2)", vec![])));
+        assert_eq!(split_flags("--format json", FLAGS), Some(("", vec![("--format", "json")])));
+    }
+
+    #[test]
+    fn keeps_flags_within_the_operand() {
+        assert_eq!(
+            split_flags("x --y --format json", FLAGS),
+            Some(("x --y", vec![("--format", "json")]))
+        );
+        assert_eq!(
+            split_flags("g(--format) --out o.json", FLAGS),
+            Some(("g(--format)", vec![("--out", "o.json")]))
+        );
+    }
+
+    #[test]
+    fn rejects_flags_without_values() {
+        assert_eq!(split_flags("xs --format", FLAGS), None);
+        assert_eq!(split_flags("xs --out o.json --format", FLAGS), None);
+    }
+
+    #[test]
+    fn splits_operands_at_commas() {
+        assert_eq!(split_operands("f(1, 2), [3, 4]"), Some(("f(1, 2)", "[3, 4]")));
+        assert_eq!(split_operands(" a + 1 , b "), Some(("a + 1", "b")));
+        assert_eq!(split_operands("a,"), None);
+    }
+
+    #[test]
+    fn splits_operands_at_whitespace() {
+        assert_eq!(split_operands("x   y"), Some(("x", "y")));
+        assert_eq!(split_operands("f(1 2) [3 4]"), Some(("f(1 2)", "[3 4]")));
+        assert_eq!(split_operands("a + 1 b"), None);
+        assert_eq!(split_operands("x"), None);
+    }
+}
@@ -0,0 +1,223 @@
+//! Configuration of the interactive mode, which is read from a TOML file
+//! at start-up. The configuration is looked up from the `--config` flag,
+//! then the `HASHI_CONFIG` environment variable, and finally from
+//! `hashi/config.toml` within the user's configuration directory.
+//!
+//! The configuration file is watched whilst the REPL is running, and is
+//! reloaded when it changes, or with `:reload-config`.
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use toml_edit::{Document, Item, Table};
+
+use crate::{
+    error::{InteractiveError, InteractiveResult},
+    keybindings::{parse_keybinding, Keybinding},
+    notify::Notify,
+    options::parse_duration,
+    policy::Policy,
+};
+
+/// The environment variable that can be used to specify the configuration
+/// file.
+pub const CONFIG_ENV_VAR: &str = "HASHI_CONFIG";
+
+/// The configuration of the interactive mode.
+#[derive(Debug, Default)]
+pub struct Config {
+    /// The policy of which commands are allowed to be run.
+    pub policy: Policy,
+
+    /// Additional abbreviations that are expanded by the line editor, mapping
+    /// from the name of the abbreviation to its expansion.
+    pub abbreviations: BTreeMap<String, String>,
+
+    /// Named bundles of options that can be applied with `:profile`, mapping
+    /// from the name of the profile to the options that it sets.
+    pub profiles: BTreeMap<String, Vec<(String, String)>>,
+
+    /// Whether usage statistics are accumulated across sessions.
+    pub stats: bool,
+
+    /// Keys that are bound to actions of the line editor or the REPL.
+    pub keybindings: Vec<Keybinding>,
+
+    /// How to notify the user when a long-running evaluation finishes.
+    pub notify: Notify,
+
+    /// The file that the configuration was read from, if any.
+    pub path: Option<PathBuf>,
+
+    /// The time that the file was last modified when it was read.
+    pub modified: Option<SystemTime>,
+}
+
+impl Config {
+    /// Get the path of the configuration file that is used when none is
+    /// specified on the command line.
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os(CONFIG_ENV_VAR)
+            .map(PathBuf::from)
+            .or_else(|| dirs_next::config_dir().map(|dir| dir.join("hashi").join("config.toml")))
+    }
+
+    /// Load the configuration from the given path, or if no path is given
+    /// from the default path. It is not an error for the default
+    /// configuration file to be missing.
+    pub fn load(path: Option<&Path>) -> InteractiveResult<Self> {
+        match path {
+            Some(path) => Self::load_from(path),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => Self::load_from(&path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    /// Check whether the file that the configuration was read from has
+    /// changed since it was read, or if there was no file, whether the
+    /// default configuration file has since been created.
+    pub fn is_stale(&self) -> bool {
+        match &self.path {
+            Some(path) => modified_time(path) != self.modified,
+            None => Self::default_path().is_some_and(|path| path.exists()),
+        }
+    }
+
+    /// Load the configuration from the given file.
+    fn load_from(path: &Path) -> InteractiveResult<Self> {
+        let modified = modified_time(path);
+        let contents = fs::read_to_string(path).map_err(|err| InteractiveError::io(path, err))?;
+
+        let mut config = Self::parse(&contents).map_err(|message| {
+            InteractiveError::InvalidConfig { path: path.display().to_string(), message }
+        })?;
+
+        config.path = Some(path.to_path_buf());
+        config.modified = modified;
+        Ok(config)
+    }
+
+    /// Parse the configuration from the contents of a configuration file.
+    fn parse(contents: &str) -> Result<Self, String> {
+        let document = contents.parse::<Document>().map_err(|err| err.to_string())?;
+        let mut config = Self::default();
+
+        if let Some(policy) = document.get("policy") {
+            let policy = as_table(policy, "policy")?;
+
+            if let Some(disabled) = policy.get("disabled-commands") {
+                config.policy = Policy::new(as_string_array(disabled, "disabled-commands")?);
+            }
+
+            if let Some(deny) = policy.get("deny-absolute-paths") {
+                config.policy.deny_absolute_paths = as_bool(deny, "deny-absolute-paths")?;
+            }
+
+            if let Some(deny) = policy.get("deny-processes") {
+                config.policy.deny_processes = as_bool(deny, "deny-processes")?;
+            }
+        }
+
+        if let Some(abbreviations) = document.get("abbreviations") {
+            for (name, expansion) in as_table(abbreviations, "abbreviations")?.iter() {
+                let expansion = expansion
+                    .as_str()
+                    .ok_or_else(|| format!("expected abbreviation `{name}` to be a string"))?;
+
+                config.abbreviations.insert(name.to_string(), expansion.to_string());
+            }
+        }
+
+        if let Some(profiles) = document.get("profiles") {
+            for (name, profile) in as_table(profiles, "profiles")?.iter() {
+                let options = as_table(profile, name)?
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = match (value.as_str(), value.as_bool(), value.as_integer()) {
+                            (Some(text), ..) => text.to_string(),
+                            (_, Some(flag), _) => if flag { "on" } else { "off" }.to_string(),
+                            (.., Some(number)) => number.to_string(),
+                            _ => return Err(format!("expected option `{key}` to be a string")),
+                        };
+
+                        Ok((key.to_string(), value))
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                config.profiles.insert(name.to_string(), options);
+            }
+        }
+
+        if let Some(stats) = document.get("stats") {
+            if let Some(enabled) = as_table(stats, "stats")?.get("enabled") {
+                config.stats = as_bool(enabled, "enabled")?;
+            }
+        }
+
+        if let Some(keybindings) = document.get("keybindings") {
+            for (key, action) in as_table(keybindings, "keybindings")?.iter() {
+                let action = action
+                    .as_str()
+                    .ok_or_else(|| format!("expected keybinding `{key}` to be a string"))?;
+
+                config.keybindings.push(parse_keybinding(key, action)?);
+            }
+        }
+
+        if let Some(notify) = document.get("notify") {
+            let notify = as_table(notify, "notify")?;
+
+            if let Some(command) = notify.get("command") {
+                let command = command
+                    .as_str()
+                    .ok_or_else(|| "expected `command` to be a string".to_string())?;
+                config.notify.command = Some(command.to_string());
+            }
+
+            if let Some(bell) = notify.get("bell") {
+                config.notify.bell = as_bool(bell, "bell")?;
+            }
+
+            if let Some(threshold) = notify.get("threshold") {
+                config.notify.threshold =
+                    threshold.as_str().and_then(parse_duration).ok_or_else(|| {
+                        "expected `threshold` to be a duration, e.g. \"10s\"".to_string()
+                    })?;
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Get the time that the file at the given path was last modified.
+pub fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Interpret the given item as a table.
+fn as_table<'a>(item: &'a Item, key: &str) -> Result<&'a Table, String> {
+    item.as_table().ok_or_else(|| format!("expected `{key}` to be a table"))
+}
+
+/// Interpret the given item as a boolean.
+fn as_bool(item: &Item, key: &str) -> Result<bool, String> {
+    item.as_bool().ok_or_else(|| format!("expected `{key}` to be a boolean"))
+}
+
+/// Interpret the given item as an array of strings.
+fn as_string_array(item: &Item, key: &str) -> Result<Vec<String>, String> {
+    let error = || format!("expected `{key}` to be an array of strings");
+
+    item.as_array()
+        .ok_or_else(error)?
+        .iter()
+        .map(|value| value.as_str().map(|value| value.to_string()).ok_or_else(error))
+        .collect()
+}
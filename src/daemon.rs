@@ -0,0 +1,294 @@
+//! Running a session as a daemon, so that short invocations from editors and
+//! scripts can use a compiler that is already warmed up rather than paying
+//! the cost of starting a session each time. The daemon listens on a unix
+//! socket, and clients are served one at a time in the same session, so the
+//! definitions of one client are visible to the next.
+//!
+//! The protocol consists of frames, which are the length of the payload in
+//! bytes on its own line, followed by the payload. The client sends each
+//! input as a frame, and the daemon responds with a frame containing
+//! everything that was printed whilst the input was evaluated, including its
+//! diagnostics. An input that quits (e.g. `:q`, or a pipeline or script that
+//! ends with it) ends the connection rather than stopping the daemon.
+//! Clients which need request identifiers, batching or structured
+//! diagnostics can instead switch to the structured protocol (see
+//! [`crate::protocol`]).
+
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use rustyline::error::ReadlineError;
+
+use crate::{input::LineSource, messages::message};
+
+/// The largest frame that is accepted, so that a malformed or malicious
+/// header can't make the reader allocate an arbitrary amount of memory.
+pub const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Get the default path of the socket that the daemon listens on. If there is
+/// no runtime directory, the socket is kept in a directory of the temporary
+/// directory which is private to the user, and which the daemon creates.
+pub fn default_socket_path() -> PathBuf {
+    match dirs_next::runtime_dir() {
+        Some(dir) => dir.join("hashi.sock"),
+        None => env::temp_dir().join(format!("hashi-{}", user_id())).join("hashi.sock"),
+    }
+}
+
+/// Get the identifier of the user that is running the process.
+#[cfg(unix)]
+fn user_id() -> u32 {
+    // ##Safety: `getuid` has no preconditions and can't fail.
+    unsafe { libc::getuid() }
+}
+
+/// Get the name of the user that is running the process.
+#[cfg(not(unix))]
+fn user_id() -> String {
+    env::var("USERNAME").unwrap_or_default()
+}
+
+/// Run a prompt which evaluates the inputs that are read in the daemon
+/// listening on the given socket.
+pub fn attach(socket: &Path, reader: &mut impl LineSource) -> io::Result<()> {
+    let mut client = Client::connect(socket)?;
+
+    loop {
+        match reader.read_input() {
+            Ok(input) if input.trim() == ":q" => return Ok(()),
+            Ok(input) => print!("{}", client.evaluate(&input)?),
+            Err(ReadlineError::Eof | ReadlineError::Interrupted) => return Ok(()),
+            Err(err) => return Err(io::Error::other(err.to_string())),
+        }
+    }
+}
+
+/// Write a single frame.
+pub fn write_frame(writer: &mut impl Write, payload: &str) -> io::Result<()> {
+    writeln!(writer, "{}", payload.len())?;
+    writer.write_all(payload.as_bytes())?;
+    writer.flush()
+}
+
+/// Read a single frame, returning `None` if the connection was closed.
+pub fn read_frame(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut header = String::new();
+    if reader.read_line(&mut header)? == 0 {
+        return Ok(None);
+    }
+
+    let len: usize = header
+        .trim()
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, message!("malformed-frame")))?;
+
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            message!("oversized-frame", len = len, limit = MAX_FRAME_LEN),
+        ));
+    }
+
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+
+    String::from_utf8(payload)
+        .map(Some)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, message!("frame-not-utf8")))
+}
+
+#[cfg(unix)]
+pub use imp::{serve, Client};
+
+#[cfg(unix)]
+mod imp {
+    use std::{
+        fs::{self, DirBuilder},
+        io::{self, BufReader},
+        os::unix::{
+            fs::{DirBuilderExt, MetadataExt},
+            net::{UnixListener, UnixStream},
+        },
+        path::Path,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::{read_frame, user_id, write_frame};
+    use crate::{
+        capture::{capture_stdout, stderr_to_stdout},
+        messages::message,
+        protocol,
+        session::Session,
+    };
+
+    /// How often the daemon checks for new connections.
+    const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+    /// Serve clients on the socket at the given path with the session, until
+    /// no client has connected for `idle_timeout`.
+    pub fn serve(mut session: Session, socket: &Path, idle_timeout: Duration) -> io::Result<()> {
+        // The directory of the socket is only accessible to the user if it
+        // is created here, e.g. the default one in the temporary directory.
+        if let Some(dir) = socket.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            if !dir.exists() {
+                DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+            }
+        }
+
+        // The socket might have been left behind by a daemon which didn't
+        // shut down cleanly, in which case it is replaced, unless it belongs
+        // to another user.
+        if socket.exists() {
+            check_owner(socket)?;
+
+            if UnixStream::connect(socket).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, message!("daemon-in-use")));
+            }
+
+            fs::remove_file(socket)?;
+        }
+
+        let listener = UnixListener::bind(socket)?;
+        listener.set_nonblocking(true)?;
+        eprintln!("{}", message!("daemon-listening", path = socket.display()));
+
+        let mut last_active = Instant::now();
+
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(err) = handle(&mut session, stream) {
+                        eprintln!("{}", message!("daemon-disconnected", message = err));
+                    }
+
+                    last_active = Instant::now();
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if last_active.elapsed() >= idle_timeout {
+                        break;
+                    }
+
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        eprintln!("{}", message!("daemon-idle", timeout = format!("{idle_timeout:?}")));
+        session.finish();
+        fs::remove_file(socket)
+    }
+
+    /// Serve a single client until it disconnects.
+    fn handle(session: &mut Session, stream: UnixStream) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+
+        while let Some(input) = read_frame(&mut reader)? {
+            if input == protocol::HANDSHAKE {
+                return protocol::serve_client(session, &mut reader, &mut writer);
+            }
+
+            // The diagnostics are written to the standard error, so they are
+            // sent to the client along with the rest of the output. Quitting
+            // disconnects the client, rather than stopping the daemon.
+            session.log_input(&input);
+            let (flow, output) =
+                capture_stdout(|| stderr_to_stdout(|| crate::execute(session, &input)))?;
+            if flow.is_break() {
+                break;
+            }
+
+            write_frame(&mut writer, &output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that the socket at the given path belongs to the current user.
+    fn check_owner(socket: &Path) -> io::Result<()> {
+        if fs::metadata(socket)?.uid() != user_id() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                message!("socket-not-owned"),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// A connection to a daemon.
+    pub struct Client {
+        reader: BufReader<UnixStream>,
+        writer: UnixStream,
+    }
+
+    impl Client {
+        /// Connect to the daemon listening on the socket at the given path.
+        /// The socket must belong to the current user, so that inputs aren't
+        /// sent to a daemon of another user which took over the path.
+        pub fn connect(socket: &Path) -> io::Result<Self> {
+            check_owner(socket)?;
+            let stream = UnixStream::connect(socket)?;
+            Ok(Self { reader: BufReader::new(stream.try_clone()?), writer: stream })
+        }
+
+        /// Evaluate an input in the session of the daemon, returning what
+        /// was printed.
+        pub fn evaluate(&mut self, input: &str) -> io::Result<String> {
+            write_frame(&mut self.writer, input)?;
+            read_frame(&mut self.reader)?.ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+        }
+    }
+}
+
+/// Daemons are not supported on non-unix platforms.
+#[cfg(not(unix))]
+pub fn serve(_: crate::session::Session, _: &Path, _: std::time::Duration) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, message!("daemon-unsupported")))
+}
+
+/// Daemons are not supported on non-unix platforms.
+#[cfg(not(unix))]
+pub struct Client;
+
+#[cfg(not(unix))]
+impl Client {
+    pub fn connect(_: &Path) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, message!("daemon-unsupported")))
+    }
+
+    pub fn evaluate(&mut self, _: &str) -> io::Result<String> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, message!("daemon-unsupported")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, ErrorKind};
+
+    use super::{read_frame, write_frame, MAX_FRAME_LEN};
+
+    #[test]
+    fn reads_written_frames() {
+        let mut buffer = vec![];
+        write_frame(&mut buffer, "x := 1;\n").unwrap();
+        write_frame(&mut buffer, "").unwrap();
+
+        let mut reader = Cursor::new(buffer);
+        assert_eq!(read_frame(&mut reader).unwrap().as_deref(), Some("x := 1;\n"));
+        assert_eq!(read_frame(&mut reader).unwrap().as_deref(), Some(""));
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_oversized_frames() {
+        let mut reader = Cursor::new(format!("{}\n", MAX_FRAME_LEN + 1));
+        assert_eq!(read_frame(&mut reader).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,110 @@
+//! Looking up the definitions of names in the sources of the session.
+
+use regex::Regex;
+
+use crate::{
+    rename::refers_elsewhere,
+    scan::{is_ident, scan, TokenKind},
+    sources::{Source, Sources},
+};
+
+/// The definition of a name.
+#[derive(Debug)]
+pub struct Definition<'s> {
+    /// The source that the definition is in.
+    pub source: &'s Source,
+
+    /// The (1-based) line that the definition starts on.
+    pub line: usize,
+
+    /// The source text of the definition.
+    pub text: &'s str,
+}
+
+/// Find the most recent top-level definition of `name` in the given sources,
+/// since later definitions shadow earlier ones. Declarations of locals with
+/// the same name, i.e. within brackets, aren't definitions of the binding.
+///
+/// @@Future: this is a textual search for declarations of the name, it
+/// should use the resolver once the compiler exposes it to the frontend.
+pub fn find_definition<'s>(sources: &'s Sources, name: &str) -> Option<Definition<'s>> {
+    let pattern =
+        Regex::new(&format!(r"(?m)^[ \t]*(?:(?:pub|priv|mut)\s+)*({})\s*:", regex::escape(name)))
+            .ok()?;
+
+    sources
+        .iter()
+        .filter_map(|source| {
+            let top_level: Vec<_> = scan(&source.contents)
+                .filter(|token| token.kind == TokenKind::Ident && token.depth == 0)
+                .filter(|token| token.text == name)
+                .map(|token| token.start)
+                .collect();
+
+            let found = pattern
+                .captures_iter(&source.contents)
+                .filter_map(|captures| captures.get(0).zip(captures.get(1)))
+                .filter(|(_, name)| top_level.contains(&name.start()))
+                .max_by_key(|(found, _)| found.start())?;
+            let start = found.0.start();
+            let text = &source.contents[start..start + declaration_len(&source.contents[start..])];
+            let line = source.contents[..start].lines().count() + 1;
+
+            Some(Definition { source, line, text: text.trim() })
+        })
+        .last()
+}
+
+/// Find the name at the given (1-based) line and column of a source, i.e.
+/// the identifier that the column is within, along with its byte offset in
+/// the source.
+pub fn name_at(source: &Source, line: usize, column: usize) -> Option<(usize, &str)> {
+    let line_start: usize =
+        source.contents.split_inclusive('\n').take(line.checked_sub(1)?).map(str::len).sum();
+    let text = source.contents.lines().nth(line - 1)?;
+    let index = text.char_indices().nth(column.checked_sub(1)?).map(|(index, _)| index)?;
+    let start = text[..index].rfind(|ch| !is_ident(ch)).map_or(0, |i| i + 1);
+    let end = text[index..].find(|ch| !is_ident(ch)).map_or(text.len(), |i| index + i);
+    let name = &text[start..end];
+
+    name.starts_with(|ch: char| ch.is_alphabetic() || ch == '_')
+        .then_some((line_start + start, name))
+}
+
+/// Find the definition of the top-level binding that the occurrence of
+/// `name` at the given byte offset of a source refers to, which is `None` if
+/// the occurrence is a field or a local, parameter or other binding which
+/// shadows the top-level one.
+pub fn binding_at<'s>(
+    sources: &'s Sources,
+    source: &Source,
+    offset: usize,
+    name: &str,
+) -> Option<Definition<'s>> {
+    if refers_elsewhere(&source.contents, offset, name) {
+        return None;
+    }
+
+    find_definition(sources, name)
+}
+
+/// Get the length of the declaration at the start of `text`, which extends
+/// up to and including the first `;` that is not nested within brackets or
+/// a literal.
+fn declaration_len(text: &str) -> usize {
+    scan(text)
+        .find(|token| token.depth == 0 && token.is_punct(';'))
+        .map_or(text.len(), |token| token.end())
+}
+
+/// Get the names that the given module source declares at its top level.
+pub fn top_level_names(contents: &str) -> Vec<String> {
+    let Ok(pattern) = Regex::new(r"(?m)^(?:pub\s+)?([\p{Alphabetic}_]\w*)\s*:") else {
+        return vec![];
+    };
+
+    pattern
+        .captures_iter(contents)
+        .filter_map(|captures| Some(captures.get(1)?.as_str().to_string()))
+        .collect()
+}
@@ -0,0 +1,93 @@
+//! A structured view of the diagnostics that are emitted by the compiler, so
+//! that they can be inspected from within the session.
+
+use hash_reporting::report::Report;
+use serde_json::{Map, Value};
+
+use crate::ansi::strip_ansi;
+
+/// The information of a single diagnostic.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// The error code of the diagnostic, if it has one.
+    pub code: Option<String>,
+
+    /// The main message of the diagnostic.
+    pub message: String,
+
+    /// The locations that the diagnostic refers to, in the form
+    /// `path:line:column`.
+    pub spans: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Extract the information of the diagnostic from the rendered report,
+    /// which has the form:
+    ///
+    /// ```text
+    /// error[0001]: message
+    ///  --> path:line:column
+    /// ...
+    /// ```
+    pub fn from_report(report: &Report) -> Self {
+        let rendered = strip_ansi(&report.to_string());
+        let mut lines = rendered.lines();
+
+        let header = lines.next().unwrap_or_default();
+        let (kind, message) = header.split_once(": ").unwrap_or(("", header));
+        let code = kind
+            .split_once('[')
+            .and_then(|(_, code)| code.strip_suffix(']'))
+            .map(|code| code.to_string());
+
+        let spans = lines
+            .filter_map(|line| line.trim_start().strip_prefix("--> "))
+            .map(|span| span.trim().to_string())
+            .collect();
+
+        Self { code, message: message.to_string(), spans }
+    }
+
+    /// Convert the diagnostic to its JSON representation, as it is written to
+    /// the event log and sent to clients of the daemon.
+    pub fn to_json(&self) -> Map<String, Value> {
+        let mut fields = Map::new();
+        fields.insert("code".to_string(), Value::from(self.code.clone()));
+        fields.insert("message".to_string(), Value::from(self.message.clone()));
+        fields.insert("spans".to_string(), Value::from(self.spans.clone()));
+        fields
+    }
+
+    /// Create a Hash declaration which binds the diagnostic to the given name
+    /// as a tuple of `(code, message, spans)`, where the spans are a list of
+    /// strings so that every diagnostic has the same type.
+    pub fn to_binding(&self, name: &str) -> String {
+        let spans: Vec<_> = self.spans.iter().map(|span| quote(span)).collect();
+
+        format!(
+            "{name} := (code = {}, message = {}, spans = [{}]);",
+            quote(self.code.as_deref().unwrap_or_default()),
+            quote(&self.message),
+            spans.join(", ")
+        )
+    }
+}
+
+/// Quote the given text as a Hash string literal.
+pub fn quote(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('"');
+
+    for ch in text.chars() {
+        match ch {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            ch => literal.push(ch),
+        }
+    }
+
+    literal.push('"');
+    literal
+}
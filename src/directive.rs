@@ -0,0 +1,53 @@
+//! Directives which can be written inline before an input to change how the
+//! REPL runs it, e.g.
+//!
+//! ```text
+//! >>> #!time #!dump(tir) fib(20)
+//! ```
+//!
+//! Directives are prefixed with `#!` so that they don't conflict with the
+//! directives and attributes of the language itself. Since they are written
+//! as part of the input, they are kept when inputs are saved to files.
+
+use crate::error::{InteractiveError, InteractiveResult};
+
+/// The prefix of a directive.
+const DIRECTIVE_PREFIX: &str = "#!";
+
+/// The directives which apply to an input.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Directives {
+    /// Report how long the input took to run (`#!time`).
+    pub time: bool,
+
+    /// Dump the AST of the input (`#!dump(ast)`).
+    pub dump_ast: bool,
+
+    /// Dump the TIR of the input (`#!dump(tir)`).
+    pub dump_tir: bool,
+}
+
+impl Directives {
+    /// Parse the directives at the start of the input, returning them along
+    /// with the rest of the input.
+    pub fn parse(input: &str) -> InteractiveResult<(Self, &str)> {
+        let mut directives = Self::default();
+        let mut rest = input.trim_start();
+
+        while let Some(directive) = rest.strip_prefix(DIRECTIVE_PREFIX) {
+            let end = directive.find(char::is_whitespace).unwrap_or(directive.len());
+            let (name, remaining) = directive.split_at(end);
+
+            match name {
+                "time" => directives.time = true,
+                "dump(ast)" => directives.dump_ast = true,
+                "dump(tir)" => directives.dump_tir = true,
+                _ => return Err(InteractiveError::UnknownDirective(name.to_string())),
+            }
+
+            rest = remaining.trim_start();
+        }
+
+        Ok((directives, rest))
+    }
+}
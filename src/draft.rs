@@ -0,0 +1,89 @@
+//! Saving the input that is being composed, so that a long multi-line input
+//! isn't lost if the process exits before it is sent. The draft is written
+//! each time a line is added to the input, and emptied once the input is
+//! sent or abandoned.
+//!
+//! Each session has its own draft (see [`crate::lockfile`]), so sessions that
+//! run at the same time don't overwrite each other's drafts. If a session
+//! exits whilst its draft holds an input, the next session offers to restore
+//! it into the line editor.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::lockfile::{create_locked, orphans};
+
+/// The extension of draft files.
+const EXTENSION: &str = "draft";
+
+/// Get the directory that the drafts of sessions are saved in.
+pub fn draft_dir() -> Option<PathBuf> {
+    dirs_next::data_dir().map(|dir| dir.join("hashi").join("drafts"))
+}
+
+/// The draft of the input that is being composed.
+#[derive(Debug)]
+pub struct Draft {
+    /// The file that the draft is saved to, which is locked whilst the
+    /// session runs.
+    file: File,
+
+    /// The draft that was left behind by a session which has exited, which
+    /// the current session holds the lock on until it is discarded.
+    previous: Option<(PathBuf, String)>,
+}
+
+impl Draft {
+    /// Start the draft of the current session in the given directory, taking
+    /// over the most recent draft that was left behind by a session which has
+    /// exited. Empty drafts which were left behind are removed.
+    pub fn start(dir: &Path) -> io::Result<Self> {
+        let mut previous = None;
+
+        for (path, mut file) in orphans(dir, EXTENSION) {
+            let mut text = String::new();
+            file.read_to_string(&mut text)?;
+
+            if previous.is_none() && !text.trim().is_empty() {
+                previous = Some((path, text));
+            } else {
+                drop(file);
+                fs::remove_file(path)?;
+            }
+        }
+
+        let (_, file) = create_locked(dir, EXTENSION)?;
+        Ok(Self { file, previous })
+    }
+
+    /// The draft that was left behind by a previous session, if any.
+    pub fn previous(&self) -> Option<String> {
+        self.previous.as_ref().map(|(_, text)| text.clone())
+    }
+
+    /// Remove the draft that was left behind by a previous session, once it
+    /// has been restored or declined.
+    pub fn discard_previous(&mut self) {
+        if let Some((path, _)) = self.previous.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// Save the input that has been composed so far.
+    pub fn save(&self, text: &str) -> io::Result<()> {
+        // The file is rewritten in place, since replacing it would lose the
+        // lock on it.
+        let mut file = &self.file;
+        file.set_len(0)?;
+        file.rewind()?;
+        file.write_all(text.as_bytes())
+    }
+
+    /// Empty the draft, since the input was sent or abandoned.
+    pub fn clear(&self) {
+        let _ = self.file.set_len(0);
+    }
+}
@@ -0,0 +1,59 @@
+//! Writing the artifacts of each input to a directory with `--dump-dir`, so
+//! that compiler developers have a complete record of an interactive
+//! debugging session. Each input is written to a numbered file, e.g.
+//! `0001.hash`, along with its AST in `0001.ast`.
+//!
+//! @@Future: also write the TIR and IR of each input once the compiler can
+//! write its dumps somewhere other than the standard output, since they are
+//! currently only produced whilst the input is evaluated.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use hash_driver::{driver::Driver, Compiler};
+use hash_pipeline::{interface::CompilerInterface, settings::CompilerStageKind};
+
+use crate::{ansi::strip_ansi, capture::capture_stdout};
+
+/// The directory that the artifacts of the inputs are written to.
+#[derive(Debug)]
+pub struct DumpDir {
+    /// The path of the directory.
+    path: PathBuf,
+
+    /// The number of inputs that have been written.
+    count: usize,
+}
+
+impl DumpDir {
+    /// Create the directory at the given path, if it doesn't already exist.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        fs::create_dir_all(path)?;
+        Ok(Self { path: path.to_path_buf(), count: 0 })
+    }
+
+    /// Write the artifacts of the input. The input is parsed on its own to
+    /// produce its AST, so this must be done before the input is evaluated.
+    pub fn record(&mut self, compiler: &mut Driver<Compiler>, input: &str) -> io::Result<()> {
+        self.count += 1;
+        let name = format!("{:04}", self.count);
+
+        fs::write(self.path.join(format!("{name}.hash")), input)?;
+
+        let settings = compiler.settings_mut();
+        settings.ast_settings_mut().dump = true;
+        settings.set_stage(CompilerStageKind::Parse);
+
+        let result = capture_stdout(|| compiler.run_interactive(input.to_string()));
+
+        let settings = compiler.settings_mut();
+        settings.ast_settings_mut().dump = false;
+        settings.set_stage(CompilerStageKind::Analysis);
+        compiler.diagnostics_mut().clear();
+
+        let ((), ast) = result?;
+        fs::write(self.path.join(format!("{name}.ast")), strip_ansi(&ast))
+    }
+}
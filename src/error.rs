@@ -1,8 +1,12 @@
 //! Represents all of the errors that occur when running within the
 //! Hash REPL.
 
+use std::{io, path::Path};
+
 use hash_reporting::report::{Report, ReportKind};
 
+use crate::messages::message;
+
 pub type InteractiveResult<T> = Result<T, InteractiveError>;
 
 /// Enum representing the variants of error that can occur when running an
@@ -18,27 +22,116 @@ pub enum InteractiveError {
     /// When a command didn't receive the correct number of arguments.
     MissingOperand(String),
 
+    /// When a command received an argument that it doesn't understand.
+    InvalidArgument { command: String, arg: String },
+
+    /// An error occurred when reading or writing a file.
+    Io { path: String, message: String },
+
+    /// A `:load` which didn't find any sources to load.
+    NoSources(String),
+
+    /// A search pattern that could not be compiled.
+    InvalidPattern { pattern: String, message: String },
+
+    /// An expression that the REPL evaluated on behalf of a command failed.
+    EvaluationFailed(String),
+
+    /// The expression of an assertion which didn't evaluate to a boolean.
+    NotBoolean(String),
+
+    /// A value which can't be represented in the requested format.
+    Unexportable { format: String, value: String },
+
+    /// A data file that could not be read into the session.
+    InvalidData { path: String, message: String },
+
+    /// A name that has no definition in the session.
+    UndefinedName(String),
+
+    /// A name that is already defined in the session, which would be shadowed
+    /// by a rename.
+    NameInUse(String),
+
+    /// Encountering an unknown inline directive.
+    UnknownDirective(String),
+
+    /// A script that could not be run.
+    InvalidScript { path: String, line: usize, message: String },
+
+    /// A script that sources itself, directly or through other scripts.
+    SourceCycle { path: String, chain: String },
+
+    /// Setting an option that doesn't exist.
+    UnknownOption(String),
+
+    /// The configuration file could not be understood.
+    InvalidConfig { path: String, message: String },
+
+    /// An action was prevented by the policy of the deployment.
+    Disallowed(String),
+
     /// An unknown error occurred.
     Internal(String),
 }
 
+impl InteractiveError {
+    /// Create an [InteractiveError::Io] from an IO error that occurred
+    /// when accessing the given `path`.
+    pub fn io(path: impl AsRef<Path>, err: io::Error) -> Self {
+        InteractiveError::Io { path: path.as_ref().display().to_string(), message: err.to_string() }
+    }
+}
+
 impl From<InteractiveError> for Report {
     fn from(error: InteractiveError) -> Self {
         let mut report = Report::new();
 
-        match error {
+        let title = match error {
             InteractiveError::UnrecognisedCommand(command) => {
-                report.kind(ReportKind::Error).title(format!("unrecognised command `{command}`"))
+                message!("unrecognised-command", command = command)
+            }
+            InteractiveError::UnexpectedArgument(arg) => message!("unexpected-argument", arg = arg),
+            InteractiveError::MissingOperand(arg) => message!("missing-operand", arg = arg),
+            InteractiveError::InvalidArgument { command, arg } => {
+                message!("invalid-argument", command = command, arg = arg)
+            }
+            InteractiveError::Io { path, message } => {
+                message!("io", path = path, message = message)
+            }
+            InteractiveError::NoSources(target) => message!("no-sources", target = target),
+            InteractiveError::InvalidPattern { pattern, message } => {
+                message!("invalid-pattern", pattern = pattern, message = message)
+            }
+            InteractiveError::EvaluationFailed(expr) => message!("evaluation-failed", expr = expr),
+            InteractiveError::NotBoolean(expr) => message!("not-boolean", expr = expr),
+            InteractiveError::Unexportable { format, value } => {
+                message!("unexportable", format = format, value = value)
+            }
+            InteractiveError::InvalidData { path, message } => {
+                message!("invalid-data", path = path, message = message)
+            }
+            InteractiveError::UndefinedName(name) => message!("undefined-name", name = name),
+            InteractiveError::NameInUse(name) => message!("name-in-use", name = name),
+            InteractiveError::UnknownDirective(name) => message!("unknown-directive", name = name),
+            InteractiveError::InvalidScript { path, line, message } => {
+                message!("invalid-script", path = path, line = line, message = message)
+            }
+            InteractiveError::SourceCycle { path, chain } => {
+                message!("source-cycle", path = path, chain = chain)
+            }
+            InteractiveError::UnknownOption(key) => message!("unknown-option", key = key),
+            InteractiveError::InvalidConfig { path, message } => {
+                message!("invalid-config", path = path, message = message)
             }
-            InteractiveError::UnexpectedArgument(arg) => report
-                .kind(ReportKind::Error)
-                .title(format!("unexpected argument `{arg}` for command")),
-            InteractiveError::MissingOperand(arg) => {
-                report.kind(ReportKind::Error).title(format!("missing operand for `{arg}`"))
+            InteractiveError::Disallowed(message) => message,
+            InteractiveError::Internal(message) => {
+                report.kind(ReportKind::Internal).title(message);
+                return report;
             }
-            InteractiveError::Internal(message) => report.kind(ReportKind::Internal).title(message),
         };
 
+        report.kind(ReportKind::Error).title(title);
         report
     }
 }
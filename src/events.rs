@@ -0,0 +1,100 @@
+//! The structured log of the events of a session, which is written with
+//! `--event-log <file>` so that external tools such as dashboards and grading
+//! systems can follow the session as it happens. Each line of the log is a
+//! JSON object with the kind of the event in `event`, the time of the event
+//! in milliseconds since the Unix epoch in `time`, and the fields of the
+//! event, e.g.
+//!
+//! ```text
+//! {"event":"input","input":"x := 1 + 2;","time":1700000000000}
+//! {"event":"timing","elapsed_ms":12.5,"time":1700000000013}
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Map, Value};
+
+use crate::diagnostic::Diagnostic;
+
+/// An event of the session.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// An input was entered at the prompt, in a script or by a client.
+    Input(&'a str),
+
+    /// A command was run, with the name that it has in the configuration.
+    Command(&'a str),
+
+    /// An input was evaluated, with everything that it printed.
+    Result { output: &'a str, failed: bool },
+
+    /// The compiler emitted a diagnostic.
+    Diagnostic(&'a Diagnostic),
+
+    /// The time that it took to evaluate an input.
+    Timing(Duration),
+}
+
+impl Event<'_> {
+    /// Convert the event to its JSON representation.
+    fn to_json(&self) -> Map<String, Value> {
+        let mut fields = Map::new();
+
+        let kind = match self {
+            Event::Input(input) => {
+                fields.insert("input".to_string(), Value::from(*input));
+                "input"
+            }
+            Event::Command(name) => {
+                fields.insert("command".to_string(), Value::from(*name));
+                "command"
+            }
+            Event::Result { output, failed } => {
+                fields.insert("output".to_string(), Value::from(*output));
+                fields.insert("failed".to_string(), Value::from(*failed));
+                "result"
+            }
+            Event::Diagnostic(diagnostic) => {
+                fields.extend(diagnostic.to_json());
+                "diagnostic"
+            }
+            Event::Timing(elapsed) => {
+                fields.insert("elapsed_ms".to_string(), Value::from(elapsed.as_secs_f64() * 1e3));
+                "timing"
+            }
+        };
+
+        fields.insert("event".to_string(), Value::from(kind));
+        fields
+    }
+}
+
+/// The log that the events of the session are written to.
+#[derive(Debug)]
+pub struct EventLog {
+    file: File,
+}
+
+impl EventLog {
+    /// Create the log, truncating the file at the given path.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self { file: File::create(path)? })
+    }
+
+    /// Write an event to the log. The log is flushed after each event, so
+    /// that it can be followed whilst the session is running.
+    pub fn record(&mut self, event: Event<'_>) {
+        let mut fields = event.to_json();
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        fields.insert("time".to_string(), Value::from(time.as_millis() as u64));
+
+        if let Err(err) = writeln!(self.file, "{}", Value::Object(fields)) {
+            eprintln!("failed to write to the event log: {err}");
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Exporting evaluated values to other formats with `:export`, so that the
+//! data of a session can be handed to other tools. Records become objects and
+//! lists and tuples become arrays in JSON, whilst CSV supports scalars, lists
+//! of scalars and lists of records.
+
+use std::{fmt, path::Path};
+
+use serde_json::Value as Json;
+
+use crate::{
+    error::{InteractiveError, InteractiveResult},
+    value::{Field, Value},
+};
+
+/// A format that values can be exported to, or read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Csv,
+    Json,
+}
+
+impl DataFormat {
+    /// Parse the name of a format.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(DataFormat::Csv),
+            "json" => Some(DataFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Infer the format from the extension of the given path, defaulting to
+    /// JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => DataFormat::Csv,
+            _ => DataFormat::Json,
+        }
+    }
+}
+
+impl fmt::Display for DataFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataFormat::Csv => write!(f, "csv"),
+            DataFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Serialize the value in the given format.
+pub fn export(value: &Value, format: DataFormat) -> InteractiveResult<String> {
+    match format {
+        DataFormat::Json => Ok(format!("{}\n", to_json(value))),
+        DataFormat::Csv => to_csv(value).ok_or_else(|| InteractiveError::Unexportable {
+            format: format.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// A scalar value that is printed as an atom.
+enum Scalar {
+    Bool(bool),
+    Integer(String),
+    Float(f64),
+    String(String),
+}
+
+impl Scalar {
+    /// Interpret the text of an atom as a scalar. Atoms which aren't
+    /// literals, e.g. names of enum variants, are treated as strings.
+    fn parse(atom: &str) -> Self {
+        let number = atom.replace('_', "");
+        let is_numeric = atom.starts_with(|ch: char| ch.is_ascii_digit() || ch == '-')
+            || matches!(atom, "inf" | "NaN");
+
+        match atom {
+            "true" => Scalar::Bool(true),
+            "false" => Scalar::Bool(false),
+            _ if is_numeric && number.parse::<i128>().is_ok() => Scalar::Integer(number),
+            _ if is_numeric => match number.parse::<f64>() {
+                Ok(value) => Scalar::Float(value),
+                Err(_) => Scalar::String(atom.to_string()),
+            },
+            _ => Scalar::String(unquote(atom).unwrap_or_else(|| atom.to_string())),
+        }
+    }
+}
+
+/// Remove the quotes and escapes of a string or character literal.
+fn unquote(literal: &str) -> Option<String> {
+    let inner = literal
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .or_else(|| literal.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))?;
+
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            text.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some('r') => text.push('\r'),
+            Some('0') => text.push('\0'),
+            Some(ch) => text.push(ch),
+            None => text.push('\\'),
+        }
+    }
+
+    Some(text)
+}
+
+/// Convert the value to JSON. Numbers which aren't finite, such as `inf`,
+/// can't be represented in JSON, and become `null`.
+fn to_json(value: &Value) -> Json {
+    match value {
+        Value::Atom(atom) => match Scalar::parse(atom) {
+            Scalar::Bool(value) => Json::from(value),
+            Scalar::Integer(number) => {
+                if let Ok(integer) = number.parse::<i64>() {
+                    Json::from(integer)
+                } else if let Ok(integer) = number.parse::<u64>() {
+                    Json::from(integer)
+                } else {
+                    number.parse::<f64>().ok().filter(|number| number.is_finite()).into()
+                }
+            }
+            Scalar::Float(number) => Some(number).filter(|number| number.is_finite()).into(),
+            Scalar::String(text) => Json::from(text),
+        },
+        Value::Group { items, .. } if is_record(items) => Json::Object(
+            items
+                .iter()
+                .map(|field| (field.name.clone().unwrap_or_default(), to_json(&field.value)))
+                .collect(),
+        ),
+        Value::Group { items, .. } => {
+            Json::Array(items.iter().map(|field| to_json(&field.value)).collect())
+        }
+    }
+}
+
+/// Whether the fields of a group are all named.
+fn is_record(items: &[Field]) -> bool {
+    !items.is_empty() && items.iter().all(|field| field.name.is_some())
+}
+
+/// Convert the value to CSV, if it is a scalar, a list of scalars or a list
+/// of records with identical fields.
+fn to_csv(value: &Value) -> Option<String> {
+    let atoms = |items: &[Field]| -> Option<Vec<Scalar>> {
+        items
+            .iter()
+            .map(|item| match &item.value {
+                Value::Atom(atom) => Some(Scalar::parse(atom)),
+                Value::Group { .. } => None,
+            })
+            .collect()
+    };
+
+    let (headers, rows): (Option<Vec<String>>, Vec<Vec<Scalar>>) = match value {
+        Value::Atom(atom) => (None, vec![vec![Scalar::parse(atom)]]),
+        Value::Group { open: '[', items, .. } => match items.first().map(|row| &row.value) {
+            Some(Value::Group { items: first, .. }) if is_record(first) => {
+                let headers: Vec<_> = first.iter().filter_map(|f| f.name.clone()).collect();
+                let mut rows = vec![];
+
+                for row in items {
+                    let Value::Group { items: fields, .. } = &row.value else {
+                        return None;
+                    };
+
+                    let names: Vec<_> = fields.iter().filter_map(|f| f.name.clone()).collect();
+                    if names != headers || names.len() != fields.len() {
+                        return None;
+                    }
+
+                    rows.push(atoms(fields)?);
+                }
+
+                (Some(headers), rows)
+            }
+            _ => (None, atoms(items)?.into_iter().map(|cell| vec![cell]).collect()),
+        },
+        Value::Group { .. } => return None,
+    };
+
+    // A column which has any floats in it is written entirely as floats, so
+    // that tools which infer the type of a column from its cells read all of
+    // it as the same type.
+    let columns = rows.first().map_or(0, Vec::len);
+    let floats: Vec<_> = (0..columns)
+        .map(|column| rows.iter().any(|row| matches!(row[column], Scalar::Float(_))))
+        .collect();
+
+    let mut csv = String::new();
+
+    if let Some(headers) = headers {
+        let headers: Vec<_> = headers.iter().map(|header| csv_escape(header)).collect();
+        csv.push_str(&headers.join(","));
+        csv.push('\n');
+    }
+
+    for row in rows {
+        let cells: Vec<_> =
+            row.iter().zip(&floats).map(|(cell, &float)| csv_cell(cell, float)).collect();
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
+    }
+
+    Some(csv)
+}
+
+/// Convert a scalar to a CSV cell, writing integers as floats if the column
+/// is a float column. Numbers which aren't finite are quoted, since tools
+/// don't agree on how they are written.
+fn csv_cell(scalar: &Scalar, float: bool) -> String {
+    match scalar {
+        Scalar::Bool(value) => value.to_string(),
+        Scalar::Integer(number) if float => match number.parse::<f64>() {
+            Ok(value) => format!("{value:?}"),
+            Err(_) => number.clone(),
+        },
+        Scalar::Integer(number) => number.clone(),
+        Scalar::Float(value) if value.is_finite() => format!("{value:?}"),
+        Scalar::Float(value) => format!("\"{value}\""),
+        Scalar::String(text) => csv_escape(text),
+    }
+}
+
+/// Quote a CSV cell if it contains any special characters.
+fn csv_escape(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{export, to_csv, to_json, DataFormat, Scalar};
+    use crate::value::Value;
+
+    #[test]
+    fn parses_scalars() {
+        assert!(matches!(Scalar::parse("true"), Scalar::Bool(true)));
+        assert!(matches!(Scalar::parse("1_000"), Scalar::Integer(n) if n == "1000"));
+        assert!(matches!(Scalar::parse("-1.5"), Scalar::Float(n) if n == -1.5));
+        assert!(matches!(Scalar::parse("inf"), Scalar::Float(n) if n.is_infinite()));
+        assert!(matches!(Scalar::parse("1.2.3"), Scalar::String(s) if s == "1.2.3"));
+        assert!(matches!(Scalar::parse("None"), Scalar::String(s) if s == "None"));
+        assert!(matches!(Scalar::parse("\"a\\n\\\"b\\\"\""), Scalar::String(s) if s == "a\n\"b\""));
+        assert!(matches!(Scalar::parse("'c'"), Scalar::String(s) if s == "c"));
+    }
+
+    #[test]
+    fn writes_json() {
+        let value = Value::parse(
+            "(name = \"a\", items = [1, 2.5, true], big = 18446744073709551615, bad = NaN)",
+        );
+
+        assert_eq!(
+            to_json(&value),
+            json!({
+                "name": "a",
+                "items": [1, 2.5, true],
+                "big": 18446744073709551615u64,
+                "bad": null,
+            })
+        );
+    }
+
+    #[test]
+    fn writes_csv() {
+        assert_eq!(to_csv(&Value::parse("1")).unwrap(), "1\n");
+        assert_eq!(to_csv(&Value::parse("[1, \"a\"]")).unwrap(), "1\na\n");
+        assert_eq!(
+            to_csv(&Value::parse("[(x = 1, y = \"a, \\\"b\\\"\"), (x = 2.5, y = inf)]")).unwrap(),
+            "x,y\n1.0,\"a, \"\"b\"\"\"\n2.5,\"inf\"\n"
+        );
+    }
+
+    #[test]
+    fn rejects_values_that_csv_cant_represent() {
+        for value in ["[(x = 1), (y = 2)]", "[[1], [2]]", "(1, 2)", "[(x = [1])]"] {
+            assert!(to_csv(&Value::parse(value)).is_none(), "{value}");
+        }
+
+        assert!(export(&Value::parse("(1, 2)"), DataFormat::Csv).is_err());
+        assert_eq!(export(&Value::parse("(1, 2)"), DataFormat::Json).unwrap(), "[1,2]\n");
+    }
+}
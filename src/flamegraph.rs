@@ -0,0 +1,201 @@
+//! Records where time was spent across the compilations of an interactive
+//! session, broken down by the kind of each input and the input itself. Each
+//! input is timed from the single run that evaluates it. The recording is
+//! written as an SVG flamegraph if the file has the `svg` extension, and
+//! otherwise in the "folded stack" format which can be rendered by tools such
+//! as `inferno-flamegraph` or `flamegraph.pl`.
+//!
+//! @@Future: break each input down by the stages of the compiler, once the
+//! driver exposes the metrics that it records for each stage. Running the
+//! input again at an earlier stage to time that stage would evaluate it
+//! twice.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// The default file that is used when recording is enabled from within the
+/// session and no file was specified on the command line.
+pub const DEFAULT_FLAMEGRAPH_PATH: &str = "hashi.svg";
+
+/// The maximum number of characters of an input that are used as the name of
+/// its frame.
+const MAX_FRAME_LEN: usize = 32;
+
+/// The width of the SVG flamegraph, in pixels.
+const SVG_WIDTH: f64 = 1200.0;
+
+/// The height of each frame of the SVG flamegraph, in pixels.
+const FRAME_HEIGHT: f64 = 16.0;
+
+/// The approximate width of a character of the labels of frames, in pixels.
+const CHAR_WIDTH: f64 = 7.0;
+
+/// A recorder of folded stacks.
+#[derive(Debug)]
+pub struct FlameGraph {
+    /// The file that the folded stacks are written to.
+    path: PathBuf,
+
+    /// The accumulated time of each stack, keyed by the `;` separated frames
+    /// of the stack.
+    stacks: BTreeMap<String, Duration>,
+}
+
+impl FlameGraph {
+    /// Create a new recorder which will write to the given `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), stacks: BTreeMap::new() }
+    }
+
+    /// Get the path that the recorder writes to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record that the given stack of frames took `elapsed` time. Time for
+    /// stacks that have previously been recorded is accumulated.
+    pub fn record(&mut self, frames: &[&str], elapsed: Duration) {
+        let stack = frames.iter().map(|frame| sanitise_frame(frame)).collect::<Vec<_>>().join(";");
+        *self.stacks.entry(stack).or_default() += elapsed;
+    }
+
+    /// Write all of the recorded stacks to the file of the recorder, as an
+    /// SVG flamegraph or as folded stacks depending on its extension.
+    pub fn write(&self) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(&self.path)?);
+
+        match self.path.extension().is_some_and(|ext| ext == "svg") {
+            true => self.write_svg(&mut out)?,
+            false => self.write_folded(&mut out)?,
+        }
+
+        out.flush()
+    }
+
+    /// Write the stacks as folded stacks, the sample count of each stack is
+    /// the number of microseconds spent in it.
+    fn write_folded(&self, out: &mut impl Write) -> io::Result<()> {
+        for (stack, elapsed) in &self.stacks {
+            writeln!(out, "{stack} {}", elapsed.as_micros())?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the stacks as an SVG flamegraph, with the root frame at the
+    /// bottom and the width of each frame proportional to its time.
+    fn write_svg(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut root = Frame::default();
+        for (stack, elapsed) in &self.stacks {
+            root.insert(stack.split(';'), *elapsed);
+        }
+
+        let depth = root.depth();
+        let height = depth as f64 * FRAME_HEIGHT;
+        let scale = SVG_WIDTH / root.total.as_secs_f64().max(f64::EPSILON);
+
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SVG_WIDTH}" height="{height}" font-family="monospace" font-size="11">"#
+        )?;
+
+        let mut frames = vec![("all", &root, 0.0, 0)];
+        while let Some((name, frame, x, level)) = frames.pop() {
+            let width = frame.total.as_secs_f64() * scale;
+            let y = height - (level + 1) as f64 * FRAME_HEIGHT;
+            let title = escape_xml(&format!("{name} ({:.2?})", frame.total));
+
+            writeln!(
+                out,
+                r#"<g><title>{title}</title><rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{}" fill="{}"/>"#,
+                FRAME_HEIGHT - 1.0,
+                colour(name)
+            )?;
+
+            let chars = ((width - 4.0) / CHAR_WIDTH) as usize;
+            if chars > 2 {
+                let label: String = match name.chars().count() > chars {
+                    true => name.chars().take(chars - 2).chain("..".chars()).collect(),
+                    false => name.to_string(),
+                };
+
+                writeln!(
+                    out,
+                    r#"<text x="{:.1}" y="{:.1}">{}</text>"#,
+                    x + 2.0,
+                    y + FRAME_HEIGHT - 4.0,
+                    escape_xml(&label)
+                )?;
+            }
+
+            writeln!(out, "</g>")?;
+
+            let mut child_x = x;
+            for (name, child) in &frame.children {
+                frames.push((name, child, child_x, level + 1));
+                child_x += child.total.as_secs_f64() * scale;
+            }
+        }
+
+        writeln!(out, "</svg>")
+    }
+}
+
+/// A frame of the flamegraph, with the total time of the stacks that pass
+/// through it.
+#[derive(Debug, Default)]
+struct Frame {
+    total: Duration,
+    children: BTreeMap<String, Frame>,
+}
+
+impl Frame {
+    /// Add the time of a stack of frames beneath this one.
+    fn insert<'a>(&mut self, mut stack: impl Iterator<Item = &'a str>, elapsed: Duration) {
+        self.total += elapsed;
+        if let Some(name) = stack.next() {
+            self.children.entry(name.to_string()).or_default().insert(stack, elapsed);
+        }
+    }
+
+    /// The number of levels of frames, including this one.
+    fn depth(&self) -> usize {
+        1 + self.children.values().map(Frame::depth).max().unwrap_or(0)
+    }
+}
+
+/// Pick a warm colour for a frame from its name, so that the same frame has
+/// the same colour wherever it appears.
+fn colour(name: &str) -> String {
+    let hash =
+        name.bytes().fold(0u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u32));
+    format!("rgb({},{},{})", 205 + hash % 50, 80 + (hash / 50) % 150, (hash / 7500) % 55)
+}
+
+/// Escape the characters of text that are special in XML.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Convert an arbitrary string into a frame name, folded stacks use `;` to
+/// separate frames and a space to separate the count, so these are replaced
+/// along with any newlines.
+fn sanitise_frame(frame: &str) -> String {
+    let mut name: String = frame
+        .trim()
+        .chars()
+        .take(MAX_FRAME_LEN)
+        .map(|ch| if ch == ';' || ch.is_whitespace() { '_' } else { ch })
+        .collect();
+
+    if frame.trim().chars().count() > MAX_FRAME_LEN {
+        name.push_str("...");
+    }
+
+    name
+}
@@ -0,0 +1,166 @@
+//! Grading of submissions with `hashi grade --spec <spec> --submission
+//! <file>`. The submission is loaded into a fresh session, which doesn't use
+//! the user's configuration, and then the checks of the specification are
+//! run against it. The specification is a Hash source whose check lines are
+//! written in the same way as those of tutorials, e.g.
+//!
+//! ```text
+//! square_all := (xs: [i32]) -> [i32] => map(xs, (x) => x * x);
+//!
+//! :type square : (x: i32) -> i32
+//! :check square(3) == 9
+//! :expect square_all([1, 2]) => [1, 4]
+//! ```
+//!
+//! The rest of the specification is loaded after the submission, so that it
+//! can define helpers for the checks. The report is printed as JSON:
+//!
+//! ```text
+//! {"checks":[{"check":":check square(3) == 9","passed":true}],"loaded":true,"score":1,"status":"ok","total":1}
+//! ```
+//!
+//! The submission is graded in a child process under the resource limits,
+//! which reports each step as it finishes. If the child exceeds its CPU
+//! limit or takes longer than the wall-clock limit, the report has the
+//! `timeout` status (or `crashed` if it ended in any other way), and the
+//! checks which didn't finish haven't passed.
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    capture::capture_stdout,
+    check::Check,
+    config::Config,
+    error::{InteractiveError, InteractiveResult},
+    limits::{exceeded_cpu_limit, spawn_limited, wait_timeout},
+    options::Options,
+    session::Session,
+};
+
+/// Grade the submission against the specification in a child process under
+/// the given limits, returning the report.
+pub fn grade(
+    spec: &Path,
+    submission: &Path,
+    cpu_limit: Duration,
+    memory_limit: u64,
+    time_limit: Duration,
+) -> InteractiveResult<String> {
+    let internal = |err: io::Error| InteractiveError::Internal(err.to_string());
+    let (checks, _) = read_spec(spec)?;
+
+    let args = ["grade".as_ref(), "--spec".as_ref(), spec.as_os_str()]
+        .into_iter()
+        .chain(["--submission".as_ref(), submission.as_os_str()]);
+    let mut child = spawn_limited(args, cpu_limit, Some(memory_limit)).map_err(internal)?;
+
+    // The child reports whether the submission loaded, followed by the
+    // outcome of each check in order. The steps are read on another thread,
+    // so that the child can be killed if it doesn't finish in time.
+    let stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || {
+        BufReader::new(stdout)
+            .lines()
+            .map(|line| Ok(line?.trim() == "true"))
+            .collect::<io::Result<Vec<_>>>()
+    });
+
+    let (status, timed_out) = wait_timeout(&mut child, time_limit).map_err(internal)?;
+    let steps = reader.join().expect("the reader of the child panicked").map_err(internal)?;
+
+    let status = match () {
+        _ if timed_out || exceeded_cpu_limit(status) => "timeout",
+        _ if status.success() => "ok",
+        _ => "crashed",
+    };
+
+    let loaded = steps.first().copied().unwrap_or(false);
+    let passed: Vec<_> =
+        (0..checks.len()).map(|index| steps.get(index + 1).copied().unwrap_or(false)).collect();
+
+    Ok(report(&checks, loaded, &passed, status))
+}
+
+/// Grade the submission against the specification in this process, printing
+/// each step as it finishes. This runs in the child process, once the limits
+/// have been applied.
+pub fn run_grader(spec: &Path, submission: &Path) -> InteractiveResult<()> {
+    let (checks, helpers) = read_spec(spec)?;
+    let source =
+        fs::read_to_string(submission).map_err(|err| InteractiveError::io(submission, err))?;
+
+    let mut session = Session::new(Config::default(), Options::default());
+    session.dumb = true;
+    session.program_stdin = false;
+
+    // The output of the submission is captured so that only the steps are
+    // printed.
+    let loaded = capture_stdout(|| session.evaluate(&source))
+        .map_err(|err| InteractiveError::Internal(err.to_string()))?
+        .0
+        .is_ok();
+    println!("{loaded}");
+
+    let helpers = helpers.join("\n");
+    if loaded && !helpers.trim().is_empty() {
+        let _ = capture_stdout(|| session.evaluate(&helpers));
+    }
+
+    for check in &checks {
+        println!("{}", loaded && check.passes(&mut session));
+    }
+
+    Ok(())
+}
+
+/// Read the checks of a specification, along with the rest of its lines
+/// which define helpers for the checks.
+fn read_spec(spec: &Path) -> InteractiveResult<(Vec<Check>, Vec<String>)> {
+    let contents = fs::read_to_string(spec).map_err(|err| InteractiveError::io(spec, err))?;
+    let mut checks = vec![];
+    let mut helpers = vec![];
+
+    for (index, line) in contents.lines().enumerate() {
+        match Check::parse(line) {
+            Some(check) => {
+                checks.push(check.map_err(|message| InteractiveError::InvalidScript {
+                    path: spec.display().to_string(),
+                    line: index + 1,
+                    message,
+                })?)
+            }
+            None => helpers.push(line.to_string()),
+        }
+    }
+
+    Ok((checks, helpers))
+}
+
+/// Write the report of the outcome of grading.
+fn report(checks: &[Check], loaded: bool, passed: &[bool], status: &str) -> String {
+    let score = passed.iter().filter(|passed| **passed).count();
+
+    let results = checks.iter().zip(passed).map(|(check, passed)| {
+        let mut result = Map::new();
+        result.insert("check".to_string(), Value::from(check.to_string()));
+        result.insert("passed".to_string(), Value::from(*passed));
+        Value::Object(result)
+    });
+
+    let mut report = Map::new();
+    report.insert("status".to_string(), Value::from(status));
+    report.insert("loaded".to_string(), Value::from(loaded));
+    report.insert("score".to_string(), Value::from(score));
+    report.insert("total".to_string(), Value::from(checks.len()));
+    report.insert("checks".to_string(), Value::Array(results.collect()));
+
+    Value::Object(report).to_string()
+}
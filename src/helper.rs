@@ -0,0 +1,263 @@
+//! The helper for the line editor, which provides completions whilst the user
+//! is typing.
+//!
+//! Currently, this expands abbreviations of Unicode characters, typing
+//! `\lambda` followed by a tab inserts `λ`. The abbreviation table can be
+//! extended in the `[abbreviations]` section of the configuration. Within
+//! string literals that hold paths, such as the argument of `import`, file
+//! paths are completed instead.
+//!
+//! The helper also holds the state that is shared with the handlers of
+//! custom keybindings, such as whether the multi-line mode is enabled.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use rustyline::{
+    completion::{Completer, Pair},
+    highlight::Highlighter,
+    hint::Hinter,
+    validate::{ValidationContext, ValidationResult, Validator},
+    Context, Helper,
+};
+
+use crate::{
+    draft::Draft,
+    scan::{is_ident, scan, TokenKind},
+};
+
+/// The abbreviations that are available by default.
+const DEFAULT_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("pi", "π"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("phi", "φ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Lambda", "Λ"),
+    ("Sigma", "Σ"),
+    ("Pi", "Π"),
+    ("Omega", "Ω"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("and", "∧"),
+    ("or", "∨"),
+    ("not", "¬"),
+    ("ne", "≠"),
+    ("le", "≤"),
+    ("ge", "≥"),
+    ("->", "→"),
+    ("<-", "←"),
+    ("=>", "⇒"),
+    ("circ", "∘"),
+    ("times", "×"),
+    ("top", "⊤"),
+    ("bot", "⊥"),
+];
+
+/// The functions whose string arguments are paths.
+const PATH_FUNCTIONS: &[&str] = &["import"];
+
+/// The helper of the line editor.
+pub struct EditorHelper {
+    /// The table of abbreviations, mapping from the name of the abbreviation
+    /// (without the leading `\`) to the text that it expands to.
+    abbreviations: BTreeMap<String, String>,
+
+    /// Whether the multi-line mode is enabled, in which case pressing enter
+    /// inserts a newline and the input is only accepted on an empty line.
+    pub multiline: Arc<AtomicBool>,
+
+    /// Whether the line was accepted in order to re-run the last input.
+    pub rerun: Arc<AtomicBool>,
+
+    /// The draft that the input is saved to whilst it is being composed.
+    pub draft: Option<Draft>,
+
+    /// The text that the next line is initialised with, i.e. a draft that
+    /// was restored.
+    pub initial: Option<String>,
+}
+
+impl EditorHelper {
+    /// Create a new helper, the given abbreviations are added to the default
+    /// ones, overriding any defaults with the same name.
+    pub fn new(abbreviations: &BTreeMap<String, String>) -> Self {
+        Self {
+            abbreviations: Self::abbreviation_table(abbreviations),
+            multiline: Arc::new(AtomicBool::new(false)),
+            rerun: Arc::new(AtomicBool::new(false)),
+            draft: None,
+            initial: None,
+        }
+    }
+
+    /// Replace the additional abbreviations of the helper.
+    pub fn set_abbreviations(&mut self, abbreviations: &BTreeMap<String, String>) {
+        self.abbreviations = Self::abbreviation_table(abbreviations);
+    }
+
+    /// Build the table of the default abbreviations, extended with the given
+    /// ones.
+    fn abbreviation_table(abbreviations: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+        let mut table: BTreeMap<_, _> = DEFAULT_ABBREVIATIONS
+            .iter()
+            .map(|(name, symbol)| (name.to_string(), symbol.to_string()))
+            .collect();
+
+        table.extend(abbreviations.iter().map(|(name, symbol)| (name.clone(), symbol.clone())));
+        table
+    }
+
+    /// Get the completions at `pos` of the line, along with the index of the
+    /// line that they replace the text from.
+    pub fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<Pair>) {
+        complete_path(line, pos)
+            .or_else(|| self.complete_abbreviation(line, pos))
+            .unwrap_or((pos, vec![]))
+    }
+
+    /// Complete an abbreviation that ends at `pos`. If the abbreviation is an
+    /// exact match, then it is expanded, otherwise all of the abbreviations
+    /// that it is a prefix of are offered.
+    fn complete_abbreviation(&self, line: &str, pos: usize) -> Option<(usize, Vec<Pair>)> {
+        let start = line[..pos].rfind('\\')?;
+        let name = &line[start + 1..pos];
+
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return None;
+        }
+
+        if let Some(symbol) = self.abbreviations.get(name) {
+            let pair = Pair { display: symbol.clone(), replacement: symbol.clone() };
+            return Some((start, vec![pair]));
+        }
+
+        let candidates = self
+            .abbreviations
+            .iter()
+            .filter(|(candidate, _)| candidate.starts_with(name))
+            .map(|(candidate, symbol)| Pair {
+                display: format!("\\{candidate} {symbol}"),
+                replacement: symbol.clone(),
+            })
+            .collect();
+
+        Some((start, candidates))
+    }
+}
+
+/// Complete the path in the string literal that the cursor is in, if the
+/// literal holds a path. A literal holds a path if it is the argument of one
+/// of the [`PATH_FUNCTIONS`], or if it starts like a path, e.g. `./`.
+fn complete_path(line: &str, pos: usize) -> Option<(usize, Vec<Pair>)> {
+    let before = &line[..pos];
+
+    // Find the start of the literal that the cursor is in, if any.
+    let literal = scan(before).last().filter(|token| {
+        token.kind == TokenKind::Literal { terminated: false } && token.text.starts_with('"')
+    })?;
+
+    let start = literal.start + 1;
+    let text = &before[start..];
+    let callee = before[..start - 1].trim_end().strip_suffix('(').map(str::trim_end);
+    let is_argument = callee.is_some_and(|callee| {
+        PATH_FUNCTIONS
+            .iter()
+            .any(|name| callee.strip_suffix(name).is_some_and(|rest| !rest.ends_with(is_ident)))
+    });
+
+    let looks_like_path = ["./", "../", "/", "~/"].iter().any(|prefix| text.starts_with(prefix));
+    if !is_argument && !looks_like_path {
+        return None;
+    }
+
+    let (dir, prefix) = match text.rfind('/') {
+        Some(index) => (&text[..=index], &text[index + 1..]),
+        None => ("", text),
+    };
+
+    let search = match dir.strip_prefix("~/") {
+        Some(rest) => dirs_next::home_dir()?.join(rest),
+        None if dir.is_empty() => PathBuf::from("."),
+        None => PathBuf::from(dir),
+    };
+
+    let mut candidates: Vec<_> = fs::read_dir(search)
+        .ok()?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+
+            let suffix = if entry.path().is_dir() { "/" } else { "" };
+            Some(Pair {
+                display: format!("{name}{suffix}"),
+                replacement: format!("{name}{suffix}"),
+            })
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| a.display.cmp(&b.display));
+    Some((start + dir.len(), candidates))
+}
+
+impl Completer for EditorHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for EditorHelper {
+    type Hint = String;
+}
+
+impl Highlighter for EditorHelper {}
+
+impl Validator for EditorHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let incomplete = self.multiline.load(Ordering::SeqCst)
+            && !self.rerun.load(Ordering::SeqCst)
+            && !ctx.input().is_empty()
+            && !ctx.input().ends_with('\n');
+
+        // Each line that is added to a multi-line input is saved, so that
+        // the input isn't lost if the process exits.
+        if let (true, Some(draft)) = (incomplete, &self.draft) {
+            let _ = draft.save(ctx.input());
+        }
+
+        match incomplete {
+            true => Ok(ValidationResult::Incomplete),
+            false => Ok(ValidationResult::Valid(None)),
+        }
+    }
+}
+
+impl Helper for EditorHelper {}
@@ -0,0 +1,71 @@
+//! A lightweight syntax highlighter for Hash source code, which is used when
+//! printing source text back to the user.
+
+/// The keywords of the language.
+const KEYWORDS: &[&str] = &[
+    "as", "break", "continue", "else", "enum", "false", "for", "if", "impl", "import", "in",
+    "loop", "match", "mod", "mut", "priv", "pub", "return", "struct", "trait", "true", "type",
+    "unsafe", "while",
+];
+
+const KEYWORD: &str = "\x1b[35m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[33m";
+const COMMENT: &str = "\x1b[90m";
+const RESET: &str = "\x1b[0m";
+
+/// Highlight the given source code with ANSI colours.
+pub fn highlight(code: &str) -> String {
+    let mut output = String::with_capacity(code.len());
+    let mut rest = code;
+
+    while let Some(ch) = rest.chars().next() {
+        let (len, colour) = if rest.starts_with("//") {
+            (rest.find('\n').unwrap_or(rest.len()), Some(COMMENT))
+        } else if ch == '"' || ch == '\'' {
+            (literal_len(rest, ch), Some(STRING))
+        } else if ch.is_ascii_digit() {
+            (
+                rest.find(|c: char| !c.is_alphanumeric() && c != '_' && c != '.')
+                    .unwrap_or(rest.len()),
+                Some(NUMBER),
+            )
+        } else if ch.is_alphabetic() || ch == '_' {
+            let len = rest.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(rest.len());
+            (len, KEYWORDS.contains(&&rest[..len]).then_some(KEYWORD))
+        } else {
+            (ch.len_utf8(), None)
+        };
+
+        let (token, remaining) = rest.split_at(len);
+        match colour {
+            Some(colour) => {
+                output.push_str(colour);
+                output.push_str(token);
+                output.push_str(RESET);
+            }
+            None => output.push_str(token),
+        }
+
+        rest = remaining;
+    }
+
+    output
+}
+
+/// Get the length of the string or character literal at the start of `text`,
+/// which is delimited by `quote`.
+fn literal_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+
+    for (index, ch) in text.char_indices().skip(1) {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ch if ch == quote => return index + ch.len_utf8(),
+            _ => {}
+        }
+    }
+
+    text.len()
+}
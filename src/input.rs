@@ -0,0 +1,321 @@
+//! Reading lines of input from the user. When running in a capable terminal,
+//! the line editor is used. However, in dumb terminals (e.g. Emacs inferior
+//! mode) or when the input is piped, a plain prompt is used which does not
+//! emit any escape sequences or move the cursor.
+
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, IsTerminal, Write},
+    path::PathBuf,
+    sync::atomic::Ordering,
+};
+
+use rustyline::{error::ReadlineError, Cmd, Editor, EventHandler, KeyEvent};
+
+use crate::{
+    config::Config,
+    draft::Draft,
+    helper::EditorHelper,
+    keybindings::{Action, RerunLastHandler, ToggleMultilineHandler},
+};
+
+/// The prompt that is displayed when reading a new input.
+const PROMPT: &str = ">>> ";
+
+/// The prompt that is displayed when reading further lines of an input that
+/// spans multiple lines.
+const CONTINUATION_PROMPT: &str = "... ";
+
+/// Get the path of the file that the history of the line editor is
+/// persisted in.
+pub fn history_path() -> Option<PathBuf> {
+    dirs_next::data_dir().map(|dir| dir.join("hashi").join("history"))
+}
+
+/// A source of lines, from which inputs are assembled.
+pub trait LineSource {
+    /// Read a line, displaying the given `prompt` if the source is
+    /// interactive.
+    fn readline(&mut self, prompt: &str) -> Result<String, ReadlineError>;
+
+    /// Add a line that was read to the history of the source.
+    fn add_history_entry(&mut self, _line: &str) {}
+
+    /// Save the part of an input that has been read so far, so that it
+    /// isn't lost if the process exits before the input is complete.
+    fn save_draft(&mut self, _text: &str) {}
+
+    /// Read a single input. An input consists of a single line,
+    /// unless the line ends with a `\`, in which case the input continues
+    /// onto the next line, or if it is a heredoc, e.g.
+    ///
+    /// ```text
+    /// >>> <<EOF
+    /// ... foo := (x: i32) -> i32 => {
+    /// ...     x + 1
+    /// ... };
+    /// ... EOF
+    /// ```
+    ///
+    /// where all of the lines up to the terminator are collected verbatim into
+    /// a single input. Each line is added to the history as it is read.
+    fn read_input(&mut self) -> Result<String, ReadlineError> {
+        let line = self.readline(PROMPT)?;
+        self.add_history_entry(&line);
+
+        let Some(terminator) = heredoc_terminator(&line) else {
+            return self.read_continuation(line);
+        };
+
+        let mut lines = vec![];
+
+        loop {
+            match self.readline(CONTINUATION_PROMPT) {
+                Ok(line) if line.trim_end() == terminator => break,
+                Ok(line) => {
+                    self.add_history_entry(&line);
+                    lines.push(line);
+                    self.save_draft(&lines.join("\n"));
+                }
+                // If the input ends before the terminator, then the heredoc
+                // is implicitly terminated.
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(lines.join("\n"))
+    }
+
+    /// Read the continuation lines of an input for as long as the current
+    /// line ends with a `\`. The trailing backslash is removed from each of
+    /// the lines.
+    fn read_continuation(&mut self, mut line: String) -> Result<String, ReadlineError> {
+        let mut lines = vec![];
+
+        while let Some(stripped) = line.strip_suffix('\\') {
+            lines.push(stripped.to_string());
+            self.save_draft(&lines.join("\n"));
+
+            line = match self.readline(CONTINUATION_PROMPT) {
+                Ok(line) => line,
+                // If the input ends, then the continuation is dropped.
+                Err(ReadlineError::Eof) => return Ok(lines.join("\n")),
+                Err(err) => return Err(err),
+            };
+
+            self.add_history_entry(&line);
+        }
+
+        lines.push(line);
+        Ok(lines.join("\n"))
+    }
+}
+
+/// A source of input lines for the REPL.
+pub enum LineReader {
+    /// Read lines using the line editor, along with the file that the
+    /// history is persisted to, and the keys that were bound from the
+    /// configuration.
+    Editor(Box<Editor<EditorHelper>>, Option<PathBuf>, Vec<KeyEvent>),
+
+    /// Read plain lines from the standard input.
+    Dumb,
+}
+
+impl LineReader {
+    /// Create a new reader, the plain reader is used if `dumb` is set or if
+    /// the terminal is detected to be a dumb terminal. The line editor loads
+    /// any previous history from the `history` file, and uses the
+    /// abbreviations and keybindings of the configuration.
+    pub fn new(dumb: bool, history: Option<PathBuf>, config: &Config) -> Self {
+        if dumb || is_dumb_terminal() {
+            return LineReader::Dumb;
+        }
+
+        let mut editor = Editor::<EditorHelper>::new();
+        editor.set_helper(Some(EditorHelper::new(&config.abbreviations)));
+        if let Some(path) = &history {
+            // The history file might not exist yet, which is fine.
+            let _ = editor.load_history(path);
+        }
+
+        let mut reader = LineReader::Editor(Box::new(editor), history, vec![]);
+        reader.apply_config(config);
+        reader
+    }
+
+    /// Apply the abbreviations and keybindings of the configuration to the
+    /// line editor, replacing those of any previous configuration.
+    pub fn apply_config(&mut self, config: &Config) {
+        let LineReader::Editor(editor, _, bound) = self else {
+            return;
+        };
+
+        for key in bound.drain(..) {
+            editor.unbind_sequence(key);
+        }
+
+        let Some(helper) = editor.helper_mut() else {
+            return;
+        };
+
+        helper.set_abbreviations(&config.abbreviations);
+        let (multiline, rerun) = (helper.multiline.clone(), helper.rerun.clone());
+
+        for binding in &config.keybindings {
+            let handler = match &binding.action {
+                Action::Editor(command) => EventHandler::Simple(command.clone()),
+                Action::Insert(text) => EventHandler::Simple(Cmd::Insert(1, text.clone())),
+                Action::RerunLast => {
+                    EventHandler::Conditional(Box::new(RerunLastHandler(rerun.clone())))
+                }
+                Action::ToggleMultiline => {
+                    EventHandler::Conditional(Box::new(ToggleMultilineHandler(multiline.clone())))
+                }
+            };
+
+            editor.bind_sequence(binding.key, handler);
+            bound.push(binding.key);
+        }
+    }
+
+    /// Check whether the last line was accepted in order to re-run the last
+    /// input, clearing the request.
+    pub fn take_rerun(&mut self) -> bool {
+        match self {
+            LineReader::Editor(editor, ..) => {
+                editor.helper().is_some_and(|helper| helper.rerun.swap(false, Ordering::SeqCst))
+            }
+            LineReader::Dumb => false,
+        }
+    }
+
+    /// Save the inputs that are being composed to the given draft. Drafts
+    /// are only kept by the line editor, since they are restored into it.
+    pub fn set_draft(&mut self, draft: Draft) {
+        if let Some(helper) = self.helper_mut() {
+            helper.draft = Some(draft);
+        }
+    }
+
+    /// Get the draft that was left behind by a previous session, if any.
+    pub fn previous_draft(&mut self) -> Option<String> {
+        self.helper_mut()?.draft.as_ref()?.previous()
+    }
+
+    /// Remove the draft that was left behind by a previous session, once it
+    /// has been restored or declined.
+    pub fn discard_previous_draft(&mut self) {
+        if let Some(draft) = self.helper_mut().and_then(|helper| helper.draft.as_mut()) {
+            draft.discard_previous();
+        }
+    }
+
+    /// Initialise the next line with a draft that is being restored.
+    pub fn restore_draft(&mut self, text: String) {
+        if let Some(helper) = self.helper_mut() {
+            helper.initial = Some(text);
+        }
+    }
+
+    /// Remove the draft, once an input has been read or abandoned.
+    pub fn clear_draft(&mut self) {
+        if let Some(draft) = self.helper_mut().and_then(|helper| helper.draft.as_ref()) {
+            draft.clear();
+        }
+    }
+
+    fn helper_mut(&mut self) -> Option<&mut EditorHelper> {
+        match self {
+            LineReader::Editor(editor, ..) => editor.helper_mut(),
+            LineReader::Dumb => None,
+        }
+    }
+
+    /// Whether the reader is a plain reader.
+    pub fn is_dumb(&self) -> bool {
+        matches!(self, LineReader::Dumb)
+    }
+}
+
+impl LineSource for LineReader {
+    fn readline(&mut self, prompt: &str) -> Result<String, ReadlineError> {
+        match self {
+            LineReader::Editor(editor, ..) => {
+                // Inputs of the multi-line mode are accepted by an empty line,
+                // which isn't part of the input.
+                let mut line = match editor.helper_mut().and_then(|h| h.initial.take()) {
+                    Some(initial) => editor.readline_with_initial(prompt, (&initial, ""))?,
+                    None => editor.readline(prompt)?,
+                };
+                let len = line.trim_end_matches('\n').len();
+                line.truncate(len);
+                Ok(line)
+            }
+            LineReader::Dumb => {
+                let mut stdout = io::stdout();
+                write!(stdout, "{prompt}")?;
+                stdout.flush()?;
+
+                let mut line = String::new();
+                if io::stdin().lock().read_line(&mut line)? == 0 {
+                    return Err(ReadlineError::Eof);
+                }
+
+                let len = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(len);
+                Ok(line)
+            }
+        }
+    }
+
+    fn save_draft(&mut self, text: &str) {
+        if let Some(draft) = self.helper_mut().and_then(|helper| helper.draft.as_ref()) {
+            let _ = draft.save(text);
+        }
+    }
+
+    /// Lines are immediately appended to the history file so that they
+    /// aren't lost if the process is terminated.
+    fn add_history_entry(&mut self, line: &str) {
+        if let LineReader::Editor(editor, history, _) = self {
+            if editor.add_history_entry(line) {
+                if let Some(path) = history {
+                    let _ = append_history(path, line);
+                }
+            }
+        }
+    }
+}
+
+/// Get the terminator of a heredoc if the line begins one, i.e. it is of the
+/// form `<<TERMINATOR`.
+fn heredoc_terminator(line: &str) -> Option<&str> {
+    let terminator = line.trim().strip_prefix("<<")?.trim();
+
+    let is_valid =
+        !terminator.is_empty() && terminator.chars().all(|ch| ch.is_alphanumeric() || ch == '_');
+
+    is_valid.then_some(terminator)
+}
+
+/// Append a single line to the history file.
+fn append_history(path: &PathBuf, line: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{line}")
+}
+
+/// Check if the current terminal is not capable of line editing, either
+/// because it declares itself as `dumb`, or because the input or output is
+/// not a terminal.
+fn is_dumb_terminal() -> bool {
+    env::var("TERM").is_ok_and(|term| term == "dumb")
+        || !io::stdin().is_terminal()
+        || !io::stdout().is_terminal()
+}
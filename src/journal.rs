@@ -0,0 +1,107 @@
+//! Journaling of the inputs of an interactive session, so that the state of
+//! a session can be reconstructed after a crash. Every input is appended to
+//! the journal before it is evaluated, and the journal is removed when the
+//! session ends cleanly.
+//!
+//! Each session has its own journal (see [`crate::lockfile`]). A journal
+//! that isn't locked was left behind by a session that didn't end cleanly, so
+//! the next session takes it over for recovery, and `hashi --recover` replays
+//! its inputs. The journals of the sessions that are still running are left
+//! alone.
+//!
+//! Inputs are written as frames, in the same format as the protocol of the
+//! daemon.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    daemon::{read_frame, write_frame},
+    lockfile::{create_locked, orphans},
+};
+
+/// The extension of journal files.
+const EXTENSION: &str = "journal";
+
+/// Get the directory that the journals of sessions are kept in.
+pub fn journal_dir() -> Option<PathBuf> {
+    dirs_next::data_dir().map(|dir| dir.join("hashi").join("journals"))
+}
+
+/// The journal of the current session.
+#[derive(Debug)]
+pub struct Journal {
+    /// The path of the journal.
+    path: PathBuf,
+
+    /// The file that inputs are appended to, which is locked whilst the
+    /// session runs.
+    file: File,
+
+    /// The journal of a session that didn't end cleanly, which the current
+    /// session holds the lock on until it has been recovered.
+    previous: Option<(PathBuf, File)>,
+}
+
+impl Journal {
+    /// Start the journal of the current session in the given directory. If a
+    /// journal was left behind by a session that didn't end cleanly, then it
+    /// is taken over for recovery, and `true` is returned along with the new
+    /// journal.
+    pub fn start(dir: &Path) -> io::Result<(Self, bool)> {
+        // The orphan is found before the journal of the current session is
+        // created, which would be found otherwise.
+        let previous = orphans(dir, EXTENSION).into_iter().next();
+        let (path, file) = create_locked(dir, EXTENSION)?;
+
+        let crashed = previous.is_some();
+        Ok((Self { path, file, previous }, crashed))
+    }
+
+    /// Append an input to the journal.
+    pub fn record(&mut self, input: &str) -> io::Result<()> {
+        write_frame(&mut self.file, input)?;
+        self.file.sync_data()
+    }
+
+    /// Read the inputs of the journal that was taken over for recovery, if
+    /// there is one.
+    pub fn previous_inputs(&self) -> io::Result<Vec<String>> {
+        let Some((_, file)) = &self.previous else {
+            return Ok(vec![]);
+        };
+
+        let mut reader = BufReader::new(file);
+        let mut inputs = vec![];
+
+        // The last frame might have been cut short by the crash, in which
+        // case it is dropped.
+        while let Ok(Some(input)) = read_frame(&mut reader) {
+            inputs.push(input);
+        }
+
+        Ok(inputs)
+    }
+
+    /// Remove the journal that was taken over for recovery, once it has been
+    /// replayed.
+    pub fn discard_previous(&mut self) -> io::Result<()> {
+        match self.previous.take() {
+            Some((path, file)) => {
+                drop(file);
+                fs::remove_file(path)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Remove the journal, since the session ended cleanly. A journal that
+    /// was taken over but not recovered is left for the next session.
+    pub fn remove(self) -> io::Result<()> {
+        drop(self.file);
+        fs::remove_file(&self.path)
+    }
+}
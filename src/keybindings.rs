@@ -0,0 +1,207 @@
+//! Custom keybindings for the line editor, which are read from the
+//! `[keybindings]` section of the configuration, e.g.
+//!
+//! ```toml
+//! [keybindings]
+//! "C-r" = "history-search-backward"
+//! "F5" = "rerun-last"
+//! "M-m" = "toggle-multiline"
+//! "M-l" = "insert:λx => x"
+//! ```
+//!
+//! Keys are written as a character or key name (`Tab`, `Enter`, `Esc`,
+//! `Up`, `F1`, ...), prefixed by any of the `C-` (control), `M-` (alt) and
+//! `S-` (shift) modifiers. Actions are either the readline name of an editor
+//! command, or one of the REPL actions `rerun-last`, `toggle-multiline` and
+//! `insert:<snippet>`.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use rustyline::{
+    Anchor, At, Cmd, ConditionalEventHandler, Event, EventContext, KeyCode, KeyEvent, Modifiers,
+    Movement, RepeatCount, Word,
+};
+
+/// A key that is bound to an action.
+#[derive(Debug, Clone)]
+pub struct Keybinding {
+    /// The key that triggers the action.
+    pub key: KeyEvent,
+
+    /// The action that is performed.
+    pub action: Action,
+}
+
+/// An action that a key can be bound to.
+#[derive(Debug, Clone)]
+pub enum Action {
+    /// A command of the line editor.
+    Editor(Cmd),
+
+    /// Insert a snippet of text.
+    Insert(String),
+
+    /// Run the last input again.
+    RerunLast,
+
+    /// Toggle whether pressing enter inserts a newline rather than running
+    /// the input, in which case the input is run by entering an empty line.
+    ToggleMultiline,
+}
+
+/// Parse a keybinding from the text of its key and action.
+pub fn parse_keybinding(key: &str, action: &str) -> Result<Keybinding, String> {
+    let key = parse_key(key).ok_or_else(|| format!("unknown key `{key}`"))?;
+    let action = parse_action(action).ok_or_else(|| format!("unknown action `{action}`"))?;
+    Ok(Keybinding { key, action })
+}
+
+/// Parse a key, e.g. `C-x`, `M-Enter` or `F5`.
+fn parse_key(text: &str) -> Option<KeyEvent> {
+    let mut modifiers = Modifiers::NONE;
+    let mut rest = text;
+
+    loop {
+        let (modifier, remaining) = match rest.split_at_checked(2) {
+            Some(("C-", remaining)) if !remaining.is_empty() => (Modifiers::CTRL, remaining),
+            Some(("M-", remaining)) if !remaining.is_empty() => (Modifiers::ALT, remaining),
+            Some(("S-", remaining)) if !remaining.is_empty() => (Modifiers::SHIFT, remaining),
+            _ => break,
+        };
+
+        modifiers |= modifier;
+        rest = remaining;
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(ch), None) => KeyCode::Char(ch),
+        _ => match rest {
+            "Backspace" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Down" => KeyCode::Down,
+            "End" => KeyCode::End,
+            "Enter" => KeyCode::Enter,
+            "Esc" => KeyCode::Esc,
+            "Home" => KeyCode::Home,
+            "Left" => KeyCode::Left,
+            "PageDown" => KeyCode::PageDown,
+            "PageUp" => KeyCode::PageUp,
+            "Right" => KeyCode::Right,
+            "Tab" => KeyCode::Tab,
+            "Up" => KeyCode::Up,
+            _ => KeyCode::F(rest.strip_prefix('F')?.parse().ok().filter(|n| (1..=24).contains(n))?),
+        },
+    };
+
+    Some(KeyEvent(code, modifiers))
+}
+
+/// Parse an action, these are named after the equivalent readline commands.
+fn parse_action(text: &str) -> Option<Action> {
+    if let Some(snippet) = text.strip_prefix("insert:") {
+        return Some(Action::Insert(snippet.to_string()));
+    }
+
+    let command = match text {
+        "rerun-last" => return Some(Action::RerunLast),
+        "toggle-multiline" => return Some(Action::ToggleMultiline),
+        "accept-line" => Cmd::AcceptLine,
+        "beginning-of-line" => Cmd::Move(Movement::BeginningOfLine),
+        "end-of-line" => Cmd::Move(Movement::EndOfLine),
+        "backward-char" => Cmd::Move(Movement::BackwardChar(1)),
+        "forward-char" => Cmd::Move(Movement::ForwardChar(1)),
+        "backward-word" => Cmd::Move(Movement::BackwardWord(1, Word::Emacs)),
+        "forward-word" => Cmd::Move(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs)),
+        "kill-line" => Cmd::Kill(Movement::EndOfLine),
+        "backward-kill-line" => Cmd::Kill(Movement::BeginningOfLine),
+        "kill-whole-line" => Cmd::Kill(Movement::WholeLine),
+        "clear-screen" => Cmd::ClearScreen,
+        "complete" => Cmd::Complete,
+        "history-search-backward" => Cmd::HistorySearchBackward,
+        "history-search-forward" => Cmd::HistorySearchForward,
+        "reverse-search-history" => Cmd::ReverseSearchHistory,
+        "forward-search-history" => Cmd::ForwardSearchHistory,
+        "previous-history" => Cmd::PreviousHistory,
+        "next-history" => Cmd::NextHistory,
+        "beginning-of-history" => Cmd::BeginningOfHistory,
+        "end-of-history" => Cmd::EndOfHistory,
+        "newline" => Cmd::Newline,
+        "undo" => Cmd::Undo(1),
+        "yank" => Cmd::Yank(1, Anchor::Before),
+        "transpose-chars" => Cmd::TransposeChars,
+        "transpose-words" => Cmd::TransposeWords(1),
+        "upcase-word" => Cmd::UpcaseWord,
+        "downcase-word" => Cmd::DowncaseWord,
+        "capitalize-word" => Cmd::CapitalizeWord,
+        "noop" => Cmd::Noop,
+        _ => return None,
+    };
+
+    Some(Action::Editor(command))
+}
+
+/// Handles a key that is bound to [`Action::RerunLast`], by accepting the
+/// line and flagging that the last input should be run instead.
+pub struct RerunLastHandler(pub Arc<AtomicBool>);
+
+impl ConditionalEventHandler for RerunLastHandler {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, _: &EventContext) -> Option<Cmd> {
+        self.0.store(true, Ordering::SeqCst);
+        Some(Cmd::AcceptLine)
+    }
+}
+
+/// Handles a key that is bound to [`Action::ToggleMultiline`].
+pub struct ToggleMultilineHandler(pub Arc<AtomicBool>);
+
+impl ConditionalEventHandler for ToggleMultilineHandler {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, _: &EventContext) -> Option<Cmd> {
+        self.0.fetch_xor(true, Ordering::SeqCst);
+        Some(Cmd::Noop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustyline::{Cmd, KeyCode, KeyEvent, Modifiers, Movement};
+
+    use super::{parse_action, parse_key, parse_keybinding, Action};
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(parse_key("x"), Some(KeyEvent(KeyCode::Char('x'), Modifiers::NONE)));
+        assert_eq!(parse_key("C-r"), Some(KeyEvent(KeyCode::Char('r'), Modifiers::CTRL)));
+        assert_eq!(parse_key("C-M-Enter"), Some(KeyEvent(KeyCode::Enter, Modifiers::CTRL_ALT)));
+        assert_eq!(parse_key("S-Tab"), Some(KeyEvent(KeyCode::Tab, Modifiers::SHIFT)));
+        assert_eq!(parse_key("F12"), Some(KeyEvent(KeyCode::F(12), Modifiers::NONE)));
+        assert_eq!(parse_key("M--"), Some(KeyEvent(KeyCode::Char('-'), Modifiers::ALT)));
+
+        for other in ["", "C-", "F0", "F25", "Return", "C-xy"] {
+            assert_eq!(parse_key(other), None, "{other}");
+        }
+    }
+
+    #[test]
+    fn parses_actions() {
+        assert!(matches!(parse_action("rerun-last"), Some(Action::RerunLast)));
+        assert!(matches!(parse_action("toggle-multiline"), Some(Action::ToggleMultiline)));
+        assert!(
+            matches!(parse_action("insert:λx => x"), Some(Action::Insert(s)) if s == "λx => x")
+        );
+        assert!(matches!(
+            parse_action("kill-line"),
+            Some(Action::Editor(Cmd::Kill(Movement::EndOfLine)))
+        ));
+        assert!(parse_action("self-destruct").is_none());
+    }
+
+    #[test]
+    fn reports_unknown_keys_and_actions() {
+        assert_eq!(parse_keybinding("Hyper-x", "undo").unwrap_err(), "unknown key `Hyper-x`");
+        assert_eq!(parse_keybinding("C-x", "nothing").unwrap_err(), "unknown action `nothing`");
+    }
+}
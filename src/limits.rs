@@ -0,0 +1,178 @@
+//! Resource limits of the process, which are applied before running
+//! untrusted code so that it can't run forever or exhaust the memory of the
+//! machine. Exceeding the CPU limit terminates the process with `SIGXCPU`
+//! (or `SIGKILL` a second later if `SIGXCPU` is handled), and allocations
+//! beyond the memory limit fail.
+//!
+//! The resources that the children of the process have used can be read
+//! back, so that they can be reported after running untrusted code. Running the
+//! code in a child process means that the parent survives the limits being
+//! exceeded, and can report how the child ended. The parent also kills the
+//! child once a wall-clock deadline passes, since a child which is blocked,
+//! e.g. sleeping or waiting on I/O, doesn't use any CPU time.
+
+use std::{
+    env,
+    ffi::OsStr,
+    io,
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Limit the CPU time and the address space of the process.
+#[cfg(unix)]
+pub fn apply_limits(cpu: Option<Duration>, memory: Option<u64>) -> io::Result<()> {
+    // The hard CPU limit is a second beyond the soft one, since the process
+    // is killed outright (rather than sent `SIGXCPU`) when it reaches the
+    // hard limit.
+    let cpu = cpu.map(|cpu| cpu.as_secs().max(1)).map(|cpu| (cpu, cpu + 1));
+    let limits =
+        [(libc::RLIMIT_CPU, cpu), (libc::RLIMIT_AS, memory.map(|memory| (memory, memory)))];
+
+    for (resource, limit) in limits {
+        let Some((soft, hard)) = limit else {
+            continue;
+        };
+
+        let limit = libc::rlimit { rlim_cur: soft as libc::rlim_t, rlim_max: hard as libc::rlim_t };
+
+        // ##Safety: `limit` is a valid `rlimit` for the duration of the call.
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// Resource limits aren't supported on non-unix platforms.
+#[cfg(not(unix))]
+pub fn apply_limits(cpu: Option<Duration>, memory: Option<u64>) -> io::Result<()> {
+    match (cpu, memory) {
+        (None, None) => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "resource limits aren't supported on this platform",
+        )),
+    }
+}
+
+/// The resources that a process has used.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Usage {
+    /// The CPU time spent running the code of the process.
+    pub user: Duration,
+
+    /// The CPU time spent in the kernel on behalf of the process.
+    pub system: Duration,
+
+    /// The peak resident memory of the process, in bytes.
+    pub max_resident: u64,
+}
+
+/// Get the resources that the children of the process which have been
+/// waited for have used.
+#[cfg(unix)]
+pub fn children_usage() -> io::Result<Usage> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+
+    // ##Safety: `usage` is valid for writes, and is initialised by the call
+    // if it succeeds.
+    let usage = unsafe {
+        if libc::getrusage(libc::RUSAGE_CHILDREN, usage.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        usage.assume_init()
+    };
+
+    let time = |time: libc::timeval| {
+        Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+    };
+
+    // The peak resident memory is reported in kilobytes, except on macOS.
+    let scale = if cfg!(target_os = "macos") { 1 } else { 1024 };
+
+    Ok(Usage {
+        user: time(usage.ru_utime),
+        system: time(usage.ru_stime),
+        max_resident: usage.ru_maxrss as u64 * scale,
+    })
+}
+
+/// Resource usage isn't supported on non-unix platforms.
+#[cfg(not(unix))]
+pub fn children_usage() -> io::Result<Usage> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "resource usage isn't supported on this platform",
+    ))
+}
+
+/// Run this executable again as a child process with the given arguments,
+/// which apply the limits in the child before it does anything else. The
+/// arguments are those of a mode which accepts `--cpu-limit`,
+/// `--memory-limit` and `--child`. The output of the child is piped.
+pub fn spawn_limited(
+    args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    cpu: Duration,
+    memory: Option<u64>,
+) -> io::Result<Child> {
+    let mut command = Command::new(env::current_exe()?);
+    command.args(args).arg("--cpu-limit").arg(format!("{}ms", cpu.as_millis())).arg("--child");
+
+    if let Some(memory) = memory {
+        command.arg("--memory-limit").arg(memory.to_string());
+    }
+
+    command.stdin(Stdio::null()).stdout(Stdio::piped()).spawn()
+}
+
+/// How often a child process is polled whilst waiting for it to end.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Wait for a child process to end, killing it if it is still running once
+/// the given wall-clock time has passed. Returns how the child ended, and
+/// whether it was killed for running out of time.
+pub fn wait_timeout(child: &mut Child, timeout: Duration) -> io::Result<(ExitStatus, bool)> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, false));
+        }
+
+        if Instant::now() >= deadline {
+            child.kill()?;
+            return Ok((child.wait()?, true));
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Whether a child process was terminated for exceeding its CPU limit.
+#[cfg(unix)]
+pub fn exceeded_cpu_limit(status: ExitStatus) -> bool {
+    matches!(termination_signal(status), Some(libc::SIGXCPU | libc::SIGKILL))
+}
+
+/// CPU limits aren't supported on non-unix platforms.
+#[cfg(not(unix))]
+pub fn exceeded_cpu_limit(_: ExitStatus) -> bool {
+    false
+}
+
+/// The signal that terminated a child process, if it was terminated by one,
+/// e.g. `SIGXCPU` if it exceeded its CPU limit.
+#[cfg(unix)]
+pub fn termination_signal(status: ExitStatus) -> Option<i32> {
+    std::os::unix::process::ExitStatusExt::signal(&status)
+}
+
+/// Processes aren't terminated by signals on non-unix platforms.
+#[cfg(not(unix))]
+pub fn termination_signal(_: ExitStatus) -> Option<i32> {
+    None
+}
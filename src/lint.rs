@@ -0,0 +1,188 @@
+//! Linting of inputs for common mistakes that lead to confusing syntax
+//! errors, such as prompt prefixes that are pasted along with the input,
+//! smart quotes that are copied from documents, or indentation which mixes
+//! tabs and spaces. Mistakes that can be fixed unambiguously are fixed, and
+//! the others are reported with a caret pointing at them.
+
+use std::{borrow::Cow, fmt};
+
+use crate::preprocess::strip_prompt;
+
+/// A mistake that was found in an input.
+#[derive(Debug, Clone)]
+pub struct Lint {
+    /// The message that describes the mistake.
+    pub message: String,
+
+    /// The line that the mistake is on (zero-based), and the column of the
+    /// mistake within the line, if it is pointed at.
+    pub location: Option<(usize, usize)>,
+
+    /// Whether the mistake was fixed.
+    pub fixed: bool,
+}
+
+/// A lint along with the input that it refers to, for printing.
+pub struct LintDisplay<'a> {
+    lint: &'a Lint,
+    input: &'a str,
+}
+
+impl Lint {
+    /// Display the lint, pointing at its location in the given input.
+    pub fn display<'a>(&'a self, input: &'a str) -> LintDisplay<'a> {
+        LintDisplay { lint: self, input }
+    }
+}
+
+impl fmt::Display for LintDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.lint.fixed { "note" } else { "warning" };
+        write!(f, "{kind}: {}", self.lint.message)?;
+
+        if let Some((line, column)) = self.lint.location {
+            let text = self.input.lines().nth(line).unwrap_or_default();
+
+            // Tabs are shown as single spaces so that the caret lines up.
+            let text = text.replace('\t', " ");
+            write!(f, "\n  | {text}\n  | {}^", " ".repeat(column))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Check the input for common mistakes, returning the input with any of the
+/// fixable mistakes fixed, along with all of the mistakes that were found.
+pub fn lint(input: &str) -> (Cow<'_, str>, Vec<Lint>) {
+    let mut lints = vec![];
+    let mut input = Cow::Borrowed(input);
+
+    if let Some(stripped) = strip_prompts(&input) {
+        input = Cow::Owned(stripped);
+        lints.push(Lint {
+            message: "removed the pasted prompt prefixes from the input".to_string(),
+            location: None,
+            fixed: true,
+        });
+    }
+
+    if let Some(replaced) = replace_smart_quotes(&input) {
+        input = Cow::Owned(replaced);
+        lints.push(Lint {
+            message: "replaced smart quotes with plain quotes".to_string(),
+            location: None,
+            fixed: true,
+        });
+    }
+
+    lints.extend(mixed_indentation(&input));
+    (input, lints)
+}
+
+/// Remove the prompt prefixes from an input that starts with a prompt, since
+/// such input is never valid.
+fn strip_prompts(input: &str) -> Option<String> {
+    strip_prompt(input)?;
+
+    let lines: Vec<_> = input.lines().map(|line| strip_prompt(line).unwrap_or(line)).collect();
+    Some(lines.join("\n"))
+}
+
+/// Replace smart quotes with the plain quotes that they were meant to be.
+/// Smart double quotes always delimit a string, even when they are paired
+/// with a plain one, whereas smart single quotes within string literals are
+/// kept, since they are probably intended.
+fn replace_smart_quotes(input: &str) -> Option<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut replaced = false;
+
+    for ch in input.chars() {
+        let ch = match ch {
+            '\u{201C}' | '\u{201D}' => {
+                replaced = true;
+                '"'
+            }
+            '\u{2018}' | '\u{2019}' if !in_string => {
+                replaced = true;
+                '\''
+            }
+            ch => ch,
+        };
+
+        if ch == '"' && !escaped {
+            in_string = !in_string;
+        }
+
+        escaped = in_string && ch == '\\' && !escaped;
+        output.push(ch);
+    }
+
+    replaced.then_some(output)
+}
+
+/// Find the lines whose indentation mixes tabs and spaces, or which are
+/// indented differently to the first indented line.
+fn mixed_indentation(input: &str) -> Vec<Lint> {
+    let mut lints = vec![];
+    let mut style = None;
+
+    for (index, line) in input.lines().enumerate() {
+        let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        let Some(first) = indent.chars().next() else {
+            continue;
+        };
+
+        if let Some(column) = indent.find(|ch| ch != first) {
+            lints.push(Lint {
+                message: "indentation mixes tabs and spaces".to_string(),
+                location: Some((index, column)),
+                fixed: false,
+            });
+            continue;
+        }
+
+        match style {
+            None => style = Some(first),
+            Some(style) if style != first => lints.push(Lint {
+                message: format!(
+                    "indented with {}, but previous lines are indented with {}",
+                    if first == '\t' { "tabs" } else { "spaces" },
+                    if style == '\t' { "tabs" } else { "spaces" },
+                ),
+                location: Some((index, 0)),
+                fixed: false,
+            }),
+            Some(_) => {}
+        }
+    }
+
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::replace_smart_quotes;
+
+    #[test]
+    fn replaces_paired_smart_quotes() {
+        assert_eq!(replace_smart_quotes("x := \u{201C}a\u{201D};").as_deref(), Some("x := \"a\";"));
+    }
+
+    #[test]
+    fn replaces_smart_quote_closing_plain_quote() {
+        assert_eq!(replace_smart_quotes("x := \"a\u{201D};").as_deref(), Some("x := \"a\";"));
+        assert_eq!(
+            replace_smart_quotes("\"a\u{201D} + \u{2018}b\u{2019}").as_deref(),
+            Some("\"a\" + 'b'")
+        );
+    }
+
+    #[test]
+    fn keeps_smart_single_quotes_in_strings() {
+        assert_eq!(replace_smart_quotes("x := \"it\u{2019}s\";"), None);
+        assert_eq!(replace_smart_quotes("x := \"\\\"it\u{2019}s\";"), None);
+    }
+}
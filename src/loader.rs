@@ -0,0 +1,277 @@
+//! Loading of source files into the session with `:load`. The argument is
+//! either a file, a directory of sources, or a glob pattern such as
+//! `src/**/*.hash`. The modules are evaluated one at a time in dependency
+//! order, so that modules are loaded after the modules that they import.
+//! Loading can be interrupted between modules with `Ctrl-C`, and resumed
+//! later with `:load --continue`.
+//!
+//! Every module that a load attempted is remembered along with whether it
+//! failed, so that `:reload` can load the modules which have changed since
+//! again, including those which have been fixed.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use regex::Regex;
+
+use crate::{
+    config::modified_time,
+    error::{InteractiveError, InteractiveResult},
+};
+
+/// The extension of Hash source files.
+pub const SOURCE_EXTENSION: &str = "hash";
+
+/// The outcome of loading a single module.
+#[derive(Debug)]
+pub struct ModuleResult {
+    /// The path of the module.
+    pub path: PathBuf,
+
+    /// The number of errors that were emitted for the module.
+    pub errors: usize,
+
+    /// The number of other diagnostics that were emitted for the module.
+    pub warnings: usize,
+}
+
+/// A module that the session has attempted to load.
+#[derive(Debug, Clone)]
+pub struct LoadedModule {
+    /// The path of the module.
+    pub path: PathBuf,
+
+    /// The time that the module was last modified when it was loaded.
+    pub modified: Option<SystemTime>,
+
+    /// The number of errors that were emitted when the module was last
+    /// loaded.
+    pub errors: usize,
+}
+
+impl LoadedModule {
+    /// Record that the module at the given path was loaded with the given
+    /// number of errors.
+    pub fn new(path: &Path, errors: usize) -> Self {
+        Self { path: path.to_path_buf(), modified: modified_time(path), errors }
+    }
+
+    /// Check whether the module has changed since it was loaded.
+    pub fn is_stale(&self) -> bool {
+        modified_time(&self.path) != self.modified
+    }
+}
+
+/// The modules that remain to be loaded by a `:load` command.
+#[derive(Debug)]
+pub struct LoadQueue {
+    /// The modules that have not yet been loaded.
+    pub pending: VecDeque<PathBuf>,
+
+    /// The total number of modules that are being loaded.
+    pub total: usize,
+
+    /// The outcomes of the modules that have been loaded so far.
+    pub results: Vec<ModuleResult>,
+}
+
+impl LoadQueue {
+    /// Create a queue of the modules to load for the given argument, which is
+    /// either a single file, a directory of sources or a glob pattern.
+    pub fn new(target: &str) -> InteractiveResult<Self> {
+        let mut modules = vec![];
+
+        if is_glob(target) {
+            let base = glob_base(target);
+            let pattern = glob_to_regex(target)?;
+            collect_matching(base, &pattern, &mut modules)
+                .map_err(|err| InteractiveError::io(base, err))?;
+        } else {
+            let path = Path::new(target);
+            collect_sources(path, &mut modules).map_err(|err| InteractiveError::io(path, err))?;
+        }
+
+        if modules.is_empty() {
+            return Err(InteractiveError::NoSources(target.to_string()));
+        }
+
+        modules.sort();
+        let modules = dependency_order(modules);
+
+        Ok(Self::from_modules(modules))
+    }
+
+    /// Create a queue of the given modules, which are already in dependency
+    /// order.
+    pub fn from_modules(modules: Vec<PathBuf>) -> Self {
+        Self { total: modules.len(), pending: modules.into(), results: vec![] }
+    }
+
+    /// Get the (1-based) index of the next module that will be loaded.
+    pub fn position(&self) -> usize {
+        self.total - self.pending.len() + 1
+    }
+
+    /// Print a summary of the diagnostics of each of the loaded modules.
+    pub fn print_summary(&self) {
+        let failed = self.results.iter().filter(|result| result.errors > 0).count();
+        println!("Loaded {} modules ({failed} failed)", self.results.len());
+
+        for result in self.results.iter().filter(|result| result.errors + result.warnings > 0) {
+            println!(
+                "  {}: {} error(s), {} warning(s)",
+                result.path.display(),
+                result.errors,
+                result.warnings
+            );
+        }
+    }
+}
+
+/// Check whether the given target is a glob pattern.
+fn is_glob(target: &str) -> bool {
+    target.contains(['*', '?'])
+}
+
+/// Get the directory which contains all of the matches of a glob pattern,
+/// i.e. the longest leading path that contains no wildcards.
+fn glob_base(pattern: &str) -> &Path {
+    let glob_start = pattern.find(['*', '?']).unwrap_or(pattern.len());
+
+    match pattern[..glob_start].rfind('/') {
+        Some(0) => Path::new("/"),
+        Some(index) => Path::new(&pattern[..index]),
+        None => Path::new("."),
+    }
+}
+
+/// Convert a glob pattern into a regular expression which matches paths.
+/// `**` matches any number of directories, `*` matches within a single path
+/// component, and `?` matches a single character.
+fn glob_to_regex(pattern: &str) -> InteractiveResult<Regex> {
+    let normalised = pattern.strip_prefix("./").unwrap_or(pattern);
+    let mut regex = String::from("^(?:\\./)?");
+    let mut rest = normalised;
+
+    while let Some(ch) = rest.chars().next() {
+        let (consumed, fragment) = if rest.starts_with("**/") {
+            (3, "(?:[^/]*/)*".to_string())
+        } else if rest.starts_with("**") {
+            (2, ".*".to_string())
+        } else if ch == '*' {
+            (1, "[^/]*".to_string())
+        } else if ch == '?' {
+            (1, "[^/]".to_string())
+        } else {
+            (ch.len_utf8(), regex::escape(&ch.to_string()))
+        };
+
+        regex.push_str(&fragment);
+        rest = &rest[consumed..];
+    }
+
+    regex.push('$');
+    Regex::new(&regex).map_err(|err| InteractiveError::InvalidPattern {
+        pattern: pattern.to_string(),
+        message: err.to_string(),
+    })
+}
+
+/// Collect all of the files within the directory `dir` whose paths match the
+/// given pattern.
+fn collect_matching(dir: &Path, pattern: &Regex, sources: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_matching(&path, pattern, sources)?;
+        } else if pattern.is_match(&path.to_string_lossy()) {
+            sources.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the sources at the given path, if the path is a directory then
+/// all of the Hash sources within it are collected recursively.
+fn collect_sources(path: &Path, sources: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        sources.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_sources(&path, sources)?;
+        } else if path.extension().is_some_and(|ext| ext == SOURCE_EXTENSION) {
+            sources.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Order the given modules so that each module comes after the modules that
+/// it imports, as long as those are also being loaded. Otherwise, the given
+/// order of the modules is preserved. Modules that import each other are
+/// loaded in the order that they are first reached.
+fn dependency_order(modules: Vec<PathBuf>) -> Vec<PathBuf> {
+    let import = Regex::new(r#"import\s*\(\s*"([^"]+)"\s*\)"#).unwrap();
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let indices: HashMap<_, _> =
+        modules.iter().enumerate().map(|(index, path)| (canonical(path), index)).collect();
+
+    let dependencies: Vec<Vec<usize>> = modules
+        .iter()
+        .map(|path| {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            let contents = fs::read_to_string(path).unwrap_or_default();
+
+            import
+                .captures_iter(&contents)
+                .filter_map(|captures| {
+                    let imported = dir.join(captures.get(1)?.as_str());
+                    let with_extension = imported.with_extension(SOURCE_EXTENSION);
+
+                    [imported, with_extension]
+                        .iter()
+                        .find_map(|candidate| indices.get(&canonical(candidate)).copied())
+                })
+                .collect()
+        })
+        .collect();
+
+    fn visit(
+        index: usize,
+        dependencies: &[Vec<usize>],
+        visited: &mut HashSet<usize>,
+        order: &mut Vec<usize>,
+    ) {
+        if !visited.insert(index) {
+            return;
+        }
+
+        for &dependency in &dependencies[index] {
+            visit(dependency, dependencies, visited, order);
+        }
+
+        order.push(index);
+    }
+
+    let mut visited = HashSet::new();
+    let mut order = vec![];
+
+    for index in 0..modules.len() {
+        visit(index, &dependencies, &mut visited, &mut order);
+    }
+
+    order.into_iter().map(|index| modules[index].clone()).collect()
+}
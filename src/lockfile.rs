@@ -0,0 +1,56 @@
+//! Files which belong to a running session, such as its journal and the
+//! draft of its input. Each session creates its own file, which it holds a
+//! lock on until it exits. A file that isn't locked was left behind by a
+//! session that exited without cleaning up, so it can be taken over by
+//! another session, whereas the files of the sessions that are still running
+//! are left alone.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Create the file of the current session in the given directory, locking
+/// it until the session exits.
+pub fn create_locked(dir: &Path, extension: &str) -> io::Result<(PathBuf, File)> {
+    fs::create_dir_all(dir)?;
+
+    // The start time is part of the name in case the process id of a
+    // session that exited is reused.
+    let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let path = dir.join(format!("{}-{}.{extension}", process::id(), started.as_millis()));
+
+    let file = OpenOptions::new().create(true).read(true).write(true).truncate(true).open(&path)?;
+    file.try_lock().map_err(io::Error::from)?;
+    Ok((path, file))
+}
+
+/// Find the files in the directory with the given extension which aren't
+/// locked by a running session, locking them for the current one. The most
+/// recently modified files come first.
+pub fn orphans(dir: &Path, extension: &str) -> Vec<(PathBuf, File)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut candidates: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .filter_map(|path| Some((fs::metadata(&path).and_then(|m| m.modified()).ok()?, path)))
+        .collect();
+
+    candidates.sort();
+    candidates
+        .into_iter()
+        .rev()
+        .filter_map(|(_, path)| {
+            let file = OpenOptions::new().read(true).write(true).open(&path).ok()?;
+            file.try_lock().ok()?;
+            Some((path, file))
+        })
+        .collect()
+}
@@ -1,20 +1,126 @@
 //! The main entry point for the Hash interpreter.
 
+mod ansi;
+mod args;
+mod assert;
+mod capture;
+mod check;
 mod command;
+mod config;
+mod daemon;
+mod definition;
+mod diagnostic;
+mod directive;
+mod draft;
+mod dump;
 mod error;
+mod events;
+mod export;
+mod flamegraph;
+mod grade;
+mod helper;
+mod highlight;
+mod input;
+mod journal;
+mod keybindings;
+mod limits;
+mod lint;
+mod loader;
+mod lockfile;
+mod memo;
+mod messages;
+mod notify;
+mod options;
+mod outline;
+mod pipe;
+mod policy;
+mod preprocess;
+mod protocol;
+mod read;
+mod rename;
+mod sandbox;
+mod scan;
+mod scratch;
+mod script;
+mod session;
+mod signals;
+mod sink;
+mod sources;
+mod standby;
+mod stats;
+mod status;
+mod suggest;
+mod table;
+mod timeline;
+mod truncate;
+mod tutorial;
+mod typediff;
+mod value;
 
-use std::{env, panic, process::exit};
+use std::{
+    borrow::Cow,
+    env, fs, io,
+    ops::ControlFlow,
+    panic,
+    path::Path,
+    process::exit,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
+use ansi::{incomplete_escape, sanitise, strip_ansi};
+use args::{Arguments, Mode};
+use assert::check_assertion;
+use capture::{capture_stdout, stream_stdout};
+use clap::{CommandFactory, Parser};
 use command::InteractiveCommand;
-use error::InteractiveError;
-use hash_driver::{driver::Driver, Compiler, CompilerBuilder};
-use hash_pipeline::{
-    interface::CompilerInterface,
-    settings::{CompilerSettings, CompilerStageKind},
-};
+use config::{modified_time, Config};
+use daemon::{attach, default_socket_path, Client};
+use definition::{binding_at, find_definition, name_at};
+use diagnostic::Diagnostic;
+use directive::Directives;
+use draft::{draft_dir, Draft};
+use dump::DumpDir;
+use error::{InteractiveError, InteractiveResult};
+use events::{Event, EventLog};
+use export::{export, DataFormat};
+use flamegraph::{FlameGraph, DEFAULT_FLAMEGRAPH_PATH};
+use grade::{grade, run_grader};
+use hash_pipeline::{interface::CompilerInterface, settings::CompilerStageKind};
 use hash_reporting::report::Report;
-use hash_utils::{crash::crash_handler, log, logging::CompilerLogger};
-use rustyline::{error::ReadlineError, Editor};
+use hash_utils::{
+    crash::crash_handler,
+    log::{self, LevelFilter},
+    logging::CompilerLogger,
+};
+use highlight::highlight;
+use input::{history_path, LineReader, LineSource};
+use journal::{journal_dir, Journal};
+use limits::apply_limits;
+use lint::lint;
+use loader::LoadQueue;
+use messages::message;
+use options::{Options, Prelude};
+use outline::Outline;
+use pipe::{page, split_pipeline};
+use preprocess::preprocess;
+use read::{to_binding, to_literal};
+use regex::Regex;
+use rename::{is_identifier, rename};
+use rustyline::error::ReadlineError;
+use sandbox::{run_transcript, sandbox_run};
+use scratch::{Scratch, EDITOR_ENV_VAR};
+use script::run_script;
+use session::{run_input, LastError, Session};
+use sink::{Channel, FileSink};
+use sources::grep_lines;
+use suggest::Fix;
+use table::tabulate;
+use timeline::{parse_reference, Status};
+use truncate::{Kept, Truncator};
+use tutorial::run_tutorial;
+use typediff::{compare, render_pair, Type};
+use value::{diff, Value};
 
 /// The logger that is used by the compiler for `log!` statements.
 pub static COMPILER_LOGGER: CompilerLogger = CompilerLogger;
@@ -25,45 +131,253 @@ pub const VERSION: &str = env!("EXECUTABLE_VERSION");
 /// Utility to print the version of the current interactive backend
 #[inline(always)]
 pub fn print_version() {
-    println!("Version {VERSION}");
+    println!("{}", message!("version", version = VERSION));
 }
 
 /// Function that is called on a graceful interpreter exit
-pub fn goodbye() -> ! {
-    println!("Goodbye!");
-    exit(0)
+pub fn goodbye(code: i32) -> ! {
+    println!("{}", message!("goodbye"));
+    exit(code)
 }
 
 fn main() {
     panic::set_hook(Box::new(crash_handler));
     log::set_logger(&COMPILER_LOGGER).unwrap_or_else(|_| panic!("couldn't initiate logger"));
 
-    // @@Future: Maybe support a restricted subset of command line arguments from
-    // the settings?
-    let mut settings = CompilerSettings::new();
+    let args = Arguments::parse();
+
+    if let Some(Mode::Completions { shell }) = args.mode {
+        let mut command = Arguments::command();
+        clap_complete::generate(shell, &mut command, "hashi", &mut io::stdout());
+        return;
+    }
+
+    messages::init(args.locale.as_deref());
+    let socket = args.socket.clone().unwrap_or_else(default_socket_path);
+
+    if let Some(Mode::Attach) = args.mode {
+        let config = Config::load(args.config.as_deref()).unwrap_or_default();
+        let mut rl = LineReader::new(args.dumb, history_path(), &config);
+        if let Err(err) = attach(&socket, &mut rl) {
+            eprintln!("{}", Report::from(InteractiveError::io(&socket, err)));
+            exit(1);
+        }
+
+        return;
+    }
+
+    if let Some(Mode::Grade { spec, submission, cpu_limit, memory_limit, time_limit, child }) =
+        &args.mode
+    {
+        // The child grades under the limits, so that the parent survives them
+        // being exceeded to report a timeout.
+        let result = match child {
+            true => apply_limits(Some(*cpu_limit), Some(memory_limit * 1024 * 1024))
+                .map_err(|err| InteractiveError::Internal(err.to_string()))
+                .and_then(|_| run_grader(spec, submission)),
+            false => grade(spec, submission, *cpu_limit, *memory_limit, *time_limit)
+                .map(|report| println!("{report}")),
+        };
+
+        if let Err(err) = result {
+            eprintln!("{}", Report::from(err));
+            exit(1);
+        }
+
+        return;
+    }
+
+    if let Some(Mode::SandboxRun { transcript, cpu_limit, memory_limit, time_limit, child }) =
+        &args.mode
+    {
+        // The child runs the transcript under the limits, so that the parent
+        // survives them being exceeded to report on the run.
+        let result = match child {
+            true => apply_limits(Some(*cpu_limit), Some(memory_limit * 1024 * 1024))
+                .map_err(|err| InteractiveError::Internal(err.to_string()))
+                .and_then(|_| run_transcript(transcript)),
+            false => sandbox_run(transcript, *cpu_limit, *memory_limit, *time_limit),
+        };
 
-    // Configure the settings to only run up to the typechecking stage, and
-    // consequently to evaluate the TIR, as this is what the interpreter
-    // currently supports.
-    settings.set_stage(CompilerStageKind::Analysis);
-    settings.semantic_settings.eval_tir = true;
+        if let Err(err) = result {
+            eprintln!("{}", Report::from(err));
+            exit(1);
+        }
+
+        return;
+    }
+
+    // Inputs that are given with `--eval` are run by the daemon if one is
+    // listening, which avoids starting a session altogether.
+    if let Some(input) = &args.eval {
+        if let Ok(mut client) = Client::connect(&socket) {
+            match client.evaluate(input) {
+                Ok(output) => print!("{output}"),
+                Err(err) => {
+                    eprintln!("{}", Report::from(InteractiveError::io(&socket, err)));
+                    exit(1);
+                }
+            }
+
+            return;
+        }
+    }
+
+    // Signals are only handled specially by the interactive prompt, so they
+    // can stop the daemon and `--eval` as usual.
+    let interactive = args.mode.is_none() && args.eval.is_none();
+    if interactive {
+        signals::block();
+    }
+
+    let mut session = start_session(&args);
+
+    if let Some(Mode::Daemon { idle_timeout }) = args.mode {
+        session.dumb = true;
+        session.prepare_standby();
+        if let Err(err) = daemon::serve(session, &socket, idle_timeout) {
+            eprintln!("{}", Report::from(InteractiveError::io(&socket, err)));
+            exit(1);
+        }
+
+        return;
+    }
 
-    let mut compiler = CompilerBuilder::build_with_settings(settings);
+    if let Some(Mode::Tutorial { path }) = &args.mode {
+        let mut rl = LineReader::new(args.dumb, history_path(), &session.config);
+        session.dumb = rl.is_dumb();
+
+        if let Err(err) = run_tutorial(&mut session, &mut rl, path) {
+            println!("{}", Report::from(err));
+        }
+
+        session.finish();
+        return;
+    }
+
+    if let Some(input) = &args.eval {
+        session.dumb = true;
+        // Quitting has nothing to cut short, since there is only the one input.
+        session.log_input(input);
+        let _ = execute(&mut session, input);
+        session.finish();
+        exit(session.exit_code());
+    }
 
     print_version(); // Display the version on start-up
-    let mut rl = Editor::<()>::new();
+    session.prepare_standby();
+    let mut rl = LineReader::new(args.dumb, history_path(), &session.config);
+    session.dumb = rl.is_dumb();
+
+    if let Some(path) = journal_dir() {
+        match Journal::start(&path) {
+            Ok((journal, crashed)) => {
+                if crashed && !args.recover {
+                    println!(
+                        "The previous session ended unexpectedly, use `hashi --recover` to replay its inputs"
+                    );
+                }
+
+                session.journal = Some(journal);
+            }
+            Err(err) => eprintln!("{}", Report::from(InteractiveError::io(&path, err))),
+        }
+    }
+
+    if args.recover {
+        recover(&mut session, &mut rl);
+    }
+
+    if let Some(dir) = draft_dir() {
+        match Draft::start(&dir) {
+            Ok(draft) => {
+                rl.set_draft(draft);
+                restore_draft(&mut rl);
+            }
+            Err(err) => eprintln!("{}", Report::from(InteractiveError::io(&dir, err))),
+        }
+    }
+
+    // The session is shared with the signal handler, which waits for the
+    // current evaluation to complete before shutting the session down.
+    let status = session.status.clone();
+    let session = Arc::new(Mutex::new(session));
+    signals::spawn_handler(session.clone(), status, args.status_file.clone());
+
+    let mut last_input: Option<String> = None;
 
     loop {
-        let line = rl.readline(">>> ");
+        // Changes to the configuration file and the scratch buffer are
+        // picked up before each input.
+        {
+            let mut session = session.lock().unwrap();
+            scratch::evaluate_changes(&mut session);
+
+            if session.config.is_stale() {
+                match session.reload_config() {
+                    Ok(()) => println!("Configuration reloaded"),
+                    Err(err) => println!("{}", Report::from(err)),
+                }
+            }
+
+            if std::mem::take(&mut session.config_changed) {
+                rl.apply_config(&session.config);
+            }
+        }
+
+        let line = rl.read_input();
+        rl.clear_draft();
 
         match line {
             Ok(line) => {
-                rl.add_history_entry(line.as_str());
-                execute(&mut compiler, line.as_str());
+                let line = match (rl.take_rerun(), &last_input) {
+                    (true, Some(last)) => {
+                        println!("{last}");
+                        last.clone()
+                    }
+                    (true, None) => continue,
+                    (false, _) => line,
+                };
+
+                let mut session = session.lock().unwrap();
+
+                // `!n` runs the input with the given number in the timeline.
+                let line = match parse_reference(&line) {
+                    Some(number) => match session.timeline.get(number) {
+                        Some(entry) => {
+                            println!("{}", entry.input);
+                            entry.input.clone()
+                        }
+                        None => {
+                            println!("There is no input {number} in the timeline");
+                            continue;
+                        }
+                    },
+                    None => line,
+                };
+
+                session.record_input(&line);
+                session.log_input(&line);
+
+                let start = Instant::now();
+                let status = match execute(&mut session, line.as_str()) {
+                    ControlFlow::Break(()) => {
+                        session.finish();
+                        goodbye(session.exit_code());
+                    }
+                    _ if session.pending_load.is_some() && is_load(&line) => Status::Interrupted,
+                    ControlFlow::Continue(status) => status,
+                };
+
+                session.timeline.record(&line, status, start.elapsed());
+                session.config.notify.finished(&line, status, start.elapsed());
+                last_input = Some(line);
             }
             Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-                println!("Exiting!");
-                break;
+                println!("{}", message!("exiting"));
+                let mut session = session.lock().unwrap();
+                session.finish();
+                exit(session.exit_code());
             }
             Err(err) => {
                 eprintln!("{}", Report::from(InteractiveError::Internal(format!("{err}"))));
@@ -72,60 +386,860 @@ fn main() {
     }
 }
 
-/// Function to process a single line of input from the REPL instance.
-fn execute(compiler: &mut Driver<Compiler>, input: &str) {
-    // If the entered line has no content, just skip even evaluating it.
-    if input.is_empty() {
+/// Start a session with the configuration, prelude and profiling that are
+/// specified by the arguments.
+fn start_session(args: &Arguments) -> Session {
+    let config = Config::load(args.config.as_deref()).unwrap_or_else(|err| {
+        eprintln!("{}", Report::from(err));
+        // The path is kept so that the file is watched for a fix.
+        let path = args.config.clone().or_else(Config::default_path);
+        let modified = path.as_deref().and_then(modified_time);
+        Config { path, modified, ..Config::default() }
+    });
+
+    let prelude = match &args.prelude {
+        Some(path) => Prelude::File(path.clone()),
+        None if args.no_prelude => Prelude::None,
+        None => Prelude::Default,
+    };
+
+    let mut session = Session::new(config, Options { prelude, ..Options::default() });
+    session.flamegraph = args.flamegraph.clone().map(FlameGraph::new);
+
+    session.program_stdin = !args.no_program_stdin;
+
+    if let Some(path) = &args.event_log {
+        match EventLog::create(path) {
+            Ok(log) => session.event_log = Some(log),
+            Err(err) => eprintln!("{}", Report::from(InteractiveError::io(path, err))),
+        }
+    }
+
+    if let Some(path) = &args.program_output {
+        match FileSink::create(path) {
+            Ok(sink) => session.sink = Box::new(sink),
+            Err(err) => eprintln!("{}", Report::from(InteractiveError::io(path, err))),
+        }
+    }
+
+    if let Some(path) = &args.dump_dir {
+        match DumpDir::create(path) {
+            Ok(dir) => session.dump_dir = Some(dir),
+            Err(err) => eprintln!("{}", Report::from(InteractiveError::io(path, err))),
+        }
+    }
+
+    session
+}
+
+/// Offer to replay the inputs of the previous session from its journal,
+/// optionally skipping the last input since it probably caused the crash.
+fn recover(session: &mut Session, rl: &mut LineReader) {
+    let Some(journal) = &session.journal else {
         return;
+    };
+
+    let inputs = match journal.previous_inputs() {
+        Ok(inputs) => inputs,
+        Err(err) => return eprintln!("failed to read the journal: {err}"),
+    };
+
+    let Some(last) = inputs.last() else {
+        return println!("There is no journal to recover");
+    };
+
+    println!("The journal has {} inputs, the last of which was:", inputs.len());
+    for line in last.lines() {
+        println!("  {line}");
+    }
+
+    let answer = rl.readline("Replay all of the inputs (a), all but the last (l), or none (n)? ");
+    let count = match answer.as_deref().map(str::trim) {
+        Ok("a") => inputs.len(),
+        Ok("l") => inputs.len() - 1,
+        _ => return,
+    };
+
+    for input in &inputs[..count] {
+        println!(">>> {input}");
+        session.record_input(input);
+        session.log_input(input);
+        if execute(session, input).is_break() {
+            break;
+        }
+    }
+
+    if let Some(Err(err)) = session.journal.as_mut().map(Journal::discard_previous) {
+        eprintln!("failed to remove the journal: {err}");
+    }
+}
+
+/// Offer to restore the input that was being composed when the previous
+/// session ended into the line editor.
+fn restore_draft(rl: &mut LineReader) {
+    let Some(draft) = rl.previous_draft() else {
+        return;
+    };
+
+    println!("An unsent input was saved when the previous session ended:");
+    for line in draft.lines() {
+        println!("  {line}");
+    }
+
+    // A restored draft becomes the draft of this session, in case it exits
+    // before the input is sent.
+    if let Ok("y" | "Y") = rl.readline("Restore it (y/N)? ").as_deref().map(str::trim) {
+        rl.save_draft(&draft);
+        rl.restore_draft(draft);
+    }
+
+    rl.discard_previous_draft();
+}
+
+/// Check whether an input is a command that loads modules.
+fn is_load(input: &str) -> bool {
+    matches!(
+        InteractiveCommand::try_from(input.trim()),
+        Ok(InteractiveCommand::Load(_)
+            | InteractiveCommand::ContinueLoad
+            | InteractiveCommand::Reload)
+    )
+}
+
+/// Infer the type of an expression, as it is printed by `:t`.
+fn type_of(session: &mut Session, expr: &str) -> InteractiveResult<String> {
+    let (_, output) = capture_stdout(|| execute(session, &format!(":t {expr}")))
+        .map_err(|err| InteractiveError::Internal(err.to_string()))?;
+
+    if session.compiler.diagnostics().iter().any(|report| report.is_error()) {
+        print!("{output}");
+        return Err(InteractiveError::EvaluationFailed(expr.to_string()));
+    }
+
+    let output = strip_ansi(&output);
+    let ty = output.lines().rev().map(str::trim).find(|line| !line.is_empty());
+    ty.map(str::to_string).ok_or_else(|| InteractiveError::EvaluationFailed(expr.to_string()))
+}
+
+/// Run the stages of a pipeline, feeding the output of each stage into the
+/// next one as its piped input. The pipeline stops at a stage that quits, and
+/// fails if any of its stages failed.
+fn run_pipeline(session: &mut Session, stages: &[&str]) -> ControlFlow<(), Status> {
+    let Some((last, stages)) = stages.split_last() else {
+        return ControlFlow::Continue(Status::Ok);
+    };
+
+    let mut status = Status::Ok;
+
+    for stage in stages {
+        match capture_stdout(|| execute(session, stage)) {
+            Ok((ControlFlow::Break(()), _)) => return ControlFlow::Break(()),
+            Ok((ControlFlow::Continue(stage), output)) => {
+                if stage == Status::Error {
+                    status = Status::Error;
+                }
+
+                session.piped = Some(strip_ansi(&output).trim_end().to_string())
+            }
+            Err(err) => return failed(InteractiveError::Internal(err.to_string())),
+        }
+    }
+
+    let flow = execute(session, last);
+    session.piped = None;
+
+    match flow {
+        ControlFlow::Continue(Status::Ok) => ControlFlow::Continue(status),
+        flow => flow,
+    }
+}
+
+/// Function to process a single line of input from the REPL instance,
+/// returning whether it succeeded. The input breaks if it quits the session,
+/// in which case it is up to the caller to finish the session (or to refuse
+/// to).
+fn execute(session: &mut Session, input: &str) -> ControlFlow<(), Status> {
+    if let Some(stages) = split_pipeline(input) {
+        return run_pipeline(session, &stages);
+    }
+
+    let mut input = preprocess(&session.options, input);
+
+    if session.options.lint {
+        let (fixed, lints) = lint(&input);
+        for found in &lints {
+            println!("{}", found.display(&fixed));
+        }
+
+        if let Cow::Owned(fixed) = fixed {
+            input = Cow::Owned(fixed);
+        }
     }
 
     // Clear the diagnostics from the previous run.
-    compiler.diagnostics_mut().clear();
+    session.compiler.diagnostics_mut().clear();
+
+    let (directives, input) = match Directives::parse(&input) {
+        Ok(parsed) => parsed,
+        Err(err) => return failed(err),
+    };
+
+    // If the entered line has no content, just skip even evaluating it.
+    if input.is_empty() {
+        return ControlFlow::Continue(Status::Ok);
+    }
 
-    let command = InteractiveCommand::try_from(input);
+    let compiler = &mut session.compiler;
+
+    let command = InteractiveCommand::try_from(input).and_then(|command| {
+        if let Err(err) = session.config.policy.check(&command) {
+            session.denied.push(command.name());
+            return Err(err);
+        }
+
+        Ok(command)
+    });
+
+    if let Ok(command) = &command {
+        session.stats.record_command(command.name());
+    }
+
+    // Any input other than a query might change the definitions of the
+    // session, which invalidates the memoised queries.
+    if !matches!(command, Ok(InteractiveCommand::Type(_))) {
+        session.queries.advance();
+    }
+
+    if let Some(log) = &mut session.event_log {
+        match &command {
+            Ok(InteractiveCommand::Code(_)) | Err(_) => {}
+            Ok(command) => log.record(Event::Command(command.name())),
+        }
+    }
 
     match command {
-        Ok(InteractiveCommand::Quit) => goodbye(),
+        Ok(InteractiveCommand::Quit) => return ControlFlow::Break(()),
+        Ok(InteractiveCommand::Clear) if session.dumb => {
+            // Dumb terminals can't be cleared, so the command does nothing.
+        }
         Ok(InteractiveCommand::Clear) => {
             // check if this is either a unix/windows system and then execute
             // the appropriate clearing command
-            if cfg!(target_os = "windows") {
-                std::process::Command::new("cls").status().unwrap();
-            } else {
-                std::process::Command::new("clear").status().unwrap();
+            let program = if cfg!(target_os = "windows") { "cls" } else { "clear" };
+            if let Err(err) = session.config.policy.check_process(program) {
+                return failed(err);
             }
+
+            std::process::Command::new(program).status().unwrap();
         }
         Ok(InteractiveCommand::Version) => print_version(),
+        Ok(InteractiveCommand::FlameGraph(true)) => {
+            if session.flamegraph.is_none() {
+                session.flamegraph = Some(FlameGraph::new(DEFAULT_FLAMEGRAPH_PATH));
+            }
+        }
+        Ok(InteractiveCommand::FlameGraph(false)) => session.stop_flamegraph(),
+        Ok(InteractiveCommand::Why) => match session.last_error.clone() {
+            Some(LastError { input, .. }) => {
+                println!("Re-running the last failing input with full verbosity:");
+                for line in input.lines() {
+                    println!("  {line}");
+                }
+
+                // Emit all of the compiler's logging along with the elaborated
+                // TIR, and then restore all of the previous settings.
+                let previous = session.compiler.settings().clone();
+                let settings = session.compiler.settings_mut();
+                settings.ast_settings_mut().dump = false;
+                settings.set_stage(CompilerStageKind::Analysis);
+                settings.semantic_settings.dump_tir = true;
+
+                // The output is sanitised and written to the sink, like that of
+                // the other evaluations.
+                let level = log::max_level();
+                log::set_max_level(LevelFilter::Trace);
+                session.run_to_sink(input);
+                log::set_max_level(level);
+
+                *session.compiler.settings_mut() = previous;
+            }
+            None => println!("No input has failed in this session"),
+        },
+        Ok(InteractiveCommand::LastError(name)) => {
+            let error = session.last_error.as_ref().and_then(|error| error.errors.first());
+
+            match error {
+                Some(_) if !is_identifier(name) => {
+                    return failed(InteractiveError::InvalidArgument {
+                        command: ":last-error".to_string(),
+                        arg: name.to_string(),
+                    })
+                }
+                Some(error) => return execute(session, &error.to_binding(name)),
+                None => println!("No input has failed in this session"),
+            }
+        }
+        Ok(InteractiveCommand::Diff(left, right)) => {
+            let values = session.evaluate(left).and_then(|l| Ok((l, session.evaluate(right)?)));
+
+            match values {
+                Ok((l, r)) => {
+                    let differences = diff(&Value::parse(&l), &Value::parse(&r));
+                    if differences.is_empty() {
+                        println!("The values are equal");
+                    }
+
+                    for difference in differences {
+                        print!("{difference}");
+                    }
+                }
+                Err(err) => return failed(err),
+            }
+        }
+        Ok(InteractiveCommand::CompareTypes(left, right)) => {
+            let types = type_of(session, left).and_then(|l| Ok((l, type_of(session, right)?)));
+
+            match types {
+                Ok((l, r)) => {
+                    let (l, r) = (Type::parse(&l), Type::parse(&r));
+                    let disagreements = compare(&l, &r);
+                    if disagreements.is_empty() {
+                        println!("The types are the same: {l}");
+                        return ControlFlow::Continue(Status::Ok);
+                    }
+
+                    let (l, r) = match session.dumb {
+                        true => (l.to_string(), r.to_string()),
+                        false => render_pair(&l, &r, &disagreements),
+                    };
+
+                    println!("- {l}");
+                    println!("+ {r}");
+                    for disagreement in disagreements {
+                        print!("{disagreement}");
+                    }
+                }
+                Err(err) => return failed(err),
+            }
+        }
+        Ok(InteractiveCommand::Assert(expr)) => match check_assertion(session, expr) {
+            Ok(true) => {}
+            Ok(false) => {
+                session.failed_assertions += 1;
+                return ControlFlow::Continue(Status::Error);
+            }
+            Err(err) => {
+                session.failed_assertions += 1;
+                return failed(err);
+            }
+        },
+        Ok(InteractiveCommand::Export { expr, format, out }) => {
+            let path = Path::new(out);
+            let format = format.unwrap_or_else(|| DataFormat::from_path(path));
+            let piped = session.piped.take();
+
+            let result = match out {
+                "-" => Ok(()),
+                _ => session.config.policy.check_path(path),
+            }
+            .and_then(|_| match (expr, piped) {
+                ("", Some(piped)) => Ok(piped),
+                ("", None) => Err(InteractiveError::MissingOperand(":export".to_string())),
+                (expr, _) => session.evaluate(expr),
+            })
+            .and_then(|value| export(&Value::parse(&value), format))
+            .and_then(|data| match out {
+                "-" => {
+                    print!("{data}");
+                    Ok(())
+                }
+                _ => fs::write(path, data).map_err(|err| InteractiveError::io(path, err)),
+            });
+
+            match result {
+                Ok(()) if out == "-" => {}
+                Ok(()) if expr.is_empty() => {
+                    println!("{}", message!("exported-piped", path = out, format = format))
+                }
+                Ok(()) => {
+                    println!("{}", message!("exported", expr = expr, path = out, format = format))
+                }
+                Err(err) => return failed(err),
+            }
+        }
+        Ok(InteractiveCommand::Read { path, format, name }) => {
+            let file = Path::new(path);
+            let format = format.unwrap_or_else(|| DataFormat::from_path(file));
+
+            let binding = session
+                .config
+                .policy
+                .check_path(file)
+                .and_then(|_| {
+                    fs::read_to_string(file).map_err(|err| InteractiveError::io(file, err))
+                })
+                .and_then(|contents| {
+                    to_literal(&contents, format).map_err(|message| InteractiveError::InvalidData {
+                        path: path.to_string(),
+                        message,
+                    })
+                })
+                .and_then(|literal| to_binding(name, &literal));
+
+            match binding {
+                Ok(binding) => return execute(session, &binding),
+                Err(err) => return failed(err),
+            }
+        }
+        Ok(InteractiveCommand::ShowAll) => match &session.truncated_output {
+            Some(output) => session.sink.write(Channel::Program, output),
+            None => println!("The output of the last evaluation wasn't truncated"),
+        },
+        Ok(InteractiveCommand::SaveOutput(out)) => {
+            let path = Path::new(out);
+            let result = match &session.truncated_output {
+                Some(output) => session.config.policy.check_path(path).and_then(|_| {
+                    fs::write(path, output).map_err(|err| InteractiveError::io(path, err))
+                }),
+                None => {
+                    println!("The output of the last evaluation wasn't truncated");
+                    return ControlFlow::Continue(Status::Ok);
+                }
+            };
+
+            match result {
+                Ok(()) => println!("Saved the output of the last evaluation to `{out}`"),
+                Err(err) => return failed(err),
+            }
+        }
+        Ok(InteractiveCommand::Page) => match session.piped.take() {
+            Some(piped) => {
+                if let Err(err) = page(&piped, &session.config.policy) {
+                    return failed(err);
+                }
+            }
+            None => println!("`:page` shows the output of a command, e.g. `:d main | :page`"),
+        },
+        Ok(InteractiveCommand::Grep { pattern, context }) => match Regex::new(pattern) {
+            Ok(regex) if session.piped.is_some() => {
+                let piped = session.piped.take().unwrap_or_default();
+                for (index, lines) in grep_lines(&piped, &regex, context).iter().enumerate() {
+                    if index > 0 && context > 0 {
+                        println!("--");
+                    }
+
+                    for line in lines {
+                        println!("{}", line.text);
+                    }
+                }
+            }
+            Ok(regex) => {
+                for (index, found) in session.sources.grep(&regex, context).enumerate() {
+                    if index > 0 && context > 0 {
+                        println!("--");
+                    }
+
+                    for line in &found.lines {
+                        let separator = if line.matched { ':' } else { '-' };
+                        println!(
+                            "{}{separator}{}{separator} {}",
+                            found.source.name,
+                            line.line,
+                            line.text.trim_end()
+                        );
+                    }
+                }
+            }
+            Err(err) => {
+                return failed(InteractiveError::InvalidPattern {
+                    pattern: pattern.to_string(),
+                    message: err.to_string(),
+                })
+            }
+        },
+        Ok(InteractiveCommand::Def(name)) => match find_definition(&session.sources, name) {
+            Some(definition) => {
+                println!("{}:{}", definition.source.name, definition.line);
+
+                if session.dumb {
+                    println!("{}", definition.text);
+                } else {
+                    println!("{}", highlight(definition.text));
+                }
+            }
+            None => return failed(InteractiveError::UndefinedName(name.to_string())),
+        },
+        Ok(InteractiveCommand::Scratch(name)) => {
+            let name = name.unwrap_or("scratch");
+            let scratch = match session.scratch.take() {
+                Some(scratch) if scratch.name == name => Ok(scratch),
+                Some(previous) => {
+                    previous.remove();
+                    Scratch::open(name)
+                }
+                None => Scratch::open(name),
+            };
+
+            match scratch
+                .and_then(|scratch| Ok((scratch.open_in_editor(&session.config.policy)?, scratch)))
+            {
+                Ok((opened, scratch)) => {
+                    println!("Scratch buffer `{name}` is at `{}`", scratch.path.display());
+                    if !opened {
+                        println!("Set `{EDITOR_ENV_VAR}` to open scratch buffers in an editor");
+                    }
+
+                    session.scratch = Some(scratch);
+                }
+                Err(err) => return failed(err),
+            }
+        }
+        Ok(InteractiveCommand::Outline(target)) => {
+            // Loaded modules can be referred to by the name of their file.
+            let loaded = session.sources.get(Some(target)).or_else(|| {
+                session.sources.iter().find(|source| {
+                    Path::new(&source.name).file_stem().is_some_and(|stem| stem == target)
+                })
+            });
+
+            let contents = match loaded {
+                Some(source) => Ok(source.contents.clone()),
+                None => session.config.policy.check_path(Path::new(target)).and_then(|_| {
+                    fs::read_to_string(target).map_err(|err| InteractiveError::io(target, err))
+                }),
+            };
+
+            match contents {
+                Ok(contents) => print!("{}", Outline::new(&contents)),
+                Err(err) => return failed(err),
+            }
+        }
+        Ok(InteractiveCommand::Rename { old, new, write }) => {
+            if let Err(err) = rename(session, old, new, write) {
+                return failed(err);
+            }
+        }
+        Ok(InteractiveCommand::Fix) => match session.pending_fix.take() {
+            Some(fix) => {
+                // The imports and the input are code, so they can't quit.
+                for import in &fix.imports {
+                    let import = import.to_import();
+                    println!("{import}");
+                    let _ = execute(session, &import);
+                }
+
+                let _ = execute(session, &fix.input);
+            }
+            None => println!("There is no fix to apply"),
+        },
+        Ok(InteractiveCommand::Load(target)) => {
+            let path = Path::new(target);
+            match session.config.policy.check_path(path).and_then(|_| LoadQueue::new(target)) {
+                Ok(queue) => session.load(queue),
+                Err(err) => return failed(err),
+            }
+        }
+        Ok(InteractiveCommand::ContinueLoad) => match session.pending_load.take() {
+            Some(queue) => session.load(queue),
+            None => println!("There is no interrupted load to continue"),
+        },
+        Ok(InteractiveCommand::Reload) => session.reload(),
+        Ok(InteractiveCommand::Source(path)) => {
+            let path = Path::new(path);
+            match session.config.policy.check_path(path).and_then(|_| run_script(session, path)) {
+                Ok(ControlFlow::Break(())) => return ControlFlow::Break(()),
+                Ok(ControlFlow::Continue(())) => {}
+                Err(err) => return failed(err),
+            }
+        }
+        Ok(InteractiveCommand::Reset) => {
+            session.reset();
+            println!("Session reset, using the {} prelude", session.options.prelude);
+        }
+        Ok(InteractiveCommand::Profile(None)) => {
+            println!("Profiles:\n  default: the default options");
+            for (name, options) in &session.config.profiles {
+                let options: Vec<_> = options.iter().map(|(k, v)| format!("{k} = {v}")).collect();
+                println!("  {name}: {}", options.join(", "));
+            }
+        }
+        Ok(InteractiveCommand::Profile(Some("default"))) => {
+            let prelude = session.options.prelude.clone();
+            session.options = Options { prelude, ..Options::default() };
+            log::set_max_level(session.options.log_level);
+            println!("Using the default options");
+        }
+        Ok(InteractiveCommand::Profile(Some(name))) => {
+            let result = match session.config.profiles.get(name) {
+                Some(profile) => session.options.apply_profile(profile),
+                None => Err(InteractiveError::InvalidArgument {
+                    command: ":profile".to_string(),
+                    arg: name.to_string(),
+                }),
+            };
+
+            match result {
+                Ok(messages) => println!("Using the `{name}` profile: {}", messages.join(", ")),
+                Err(err) => return failed(err),
+            }
+        }
+        Ok(InteractiveCommand::TypeAt { source, line, column }) => {
+            let location = format!("{}:{line}:{column}", source.unwrap_or("the last input"));
+            let Some(at) = session.sources.get(source) else {
+                println!("There is no name at {location}");
+                return ControlFlow::Continue(Status::Ok);
+            };
+
+            // @@Future: this finds the name at the position textually and
+            // gets the type of its top-level binding in the session, so the
+            // types of locals and other expressions will be available once
+            // the compiler can look up the type of a span.
+            match name_at(at, line, column) {
+                Some((offset, name)) => {
+                    let name = name.to_string();
+                    println!("{}:{line}:{column}: `{name}`", at.name);
+
+                    if binding_at(&session.sources, at, offset, &name).is_some() {
+                        let _ = execute(session, &format!(":t {name}"));
+                    } else {
+                        println!(
+                            "The type of `{name}` isn't available, since it isn't a top-level binding"
+                        );
+                    }
+                }
+                None => println!("There is no name at {location}"),
+            }
+        }
+        Ok(InteractiveCommand::Timeline { errors }) => {
+            print!("{}", session.timeline.render(errors, session.dumb))
+        }
+        Ok(InteractiveCommand::Stats { all: false }) => print!("{}", session.stats),
+        Ok(InteractiveCommand::Stats { all: true }) if !session.config.stats => {
+            println!("Statistics aren't recorded across sessions, enable them in the `[stats]` section of the configuration")
+        }
+        Ok(InteractiveCommand::Stats { all: true }) => match session.all_stats() {
+            Ok(stats) => print!("{stats}"),
+            Err(err) => return failed(err),
+        },
+        Ok(InteractiveCommand::ReloadConfig) => match session.reload_config() {
+            Ok(()) => println!("Configuration reloaded"),
+            Err(err) => return failed(err),
+        },
+        Ok(InteractiveCommand::Set { key, value }) => match session.options.set(key, value) {
+            Ok(message) => println!("{message}"),
+            Err(err) => return failed(err),
+        },
         Ok(
             ref inner @ (InteractiveCommand::Type(expr)
             | InteractiveCommand::Display(expr)
             | InteractiveCommand::Code(expr)),
         ) => {
+            if let InteractiveCommand::Type(expr) = inner {
+                if let Some(output) = session.queries.get_type(expr) {
+                    print!("{output}");
+                    return ControlFlow::Continue(Status::Ok);
+                }
+            }
+
+            if let Some(dir) = &mut session.dump_dir {
+                if let Err(err) = dir.record(compiler, expr) {
+                    eprintln!("failed to write the artifacts of the input: {err}");
+                }
+            }
+
             let settings = compiler.settings_mut();
 
             // if the mode is specified to emit the type `:t` of the expr or the dump tree
             // `:d`
-            match inner {
+            let frame = match inner {
                 InteractiveCommand::Type(_) => {
                     // @@Hack: if display is previously set `:d`, then this interferes with this
                     // mode.
                     settings.ast_settings_mut().dump = false;
-                    settings.set_stage(CompilerStageKind::Analysis)
+                    settings.set_stage(CompilerStageKind::Analysis);
+                    "type"
                 }
                 InteractiveCommand::Display(_) => {
                     settings.ast_settings_mut().dump = true;
-                    settings.set_stage(CompilerStageKind::Parse)
+                    settings.set_stage(CompilerStageKind::Parse);
+                    "display"
                 }
                 _ => {
                     settings.ast_settings_mut().dump = false;
+                    "eval"
                 }
-            }
+            };
+
+            // Apply any of the dumping directives for just this input.
+            let settings = compiler.settings_mut();
+            let dump_tir = settings.semantic_settings.dump_tir;
+            settings.ast_settings_mut().dump |= directives.dump_ast || session.options.dump_ast;
+            settings.semantic_settings.dump_tir |= directives.dump_tir || session.options.dump_tir;
 
             // Add the interactive block to the state
-            compiler.run_interactive(expr.to_string());
-        }
-        Err(err) => {
-            println!("{}", Report::from(err))
+            let start = Instant::now();
+            session.status.begin(expr);
+
+            // The output of the program is streamed to the sink as it is
+            // written. The last complete line is held back until the
+            // evaluation finishes, since it might be the value of the input
+            // which is printed as a table. Partial lines are written straight
+            // away, since the program might be prompting for input.
+            let mut output = String::new();
+            if matches!(inner, InteractiveCommand::Code(_)) {
+                let sink = &mut session.sink;
+                let mut pending = String::new();
+                let program_stdin = session.program_stdin;
+                let escapes = session.options.program_escapes;
+                let mut truncator =
+                    Truncator::new(session.options.print_limit.filter(|_| sink.shows_program()));
+                let mut kept = Kept::default();
+
+                let streamed = stream_stdout(
+                    || run_input(compiler, program_stdin, expr.to_string()),
+                    |chunk, terminal| {
+                        pending.push_str(chunk);
+                        let held = match pending.ends_with('\n') {
+                            true => pending[..pending.len() - 1].rfind('\n').map(|i| i + 1),
+                            false => Some(incomplete_escape(&pending).unwrap_or(pending.len())),
+                        };
+
+                        if let Some(index) = held.filter(|&index| index > 0) {
+                            let text = sanitise(&pending[..index], escapes);
+                            sink.write_to(Channel::Program, truncator.admit(&text), terminal);
+                            kept.push(&text);
+                            pending.drain(..index);
+                        }
+                    },
+                );
+
+                if let Err(err) = streamed {
+                    eprintln!("failed to capture output: {err}");
+                }
+
+                let mut rest = match session.options.print_table {
+                    true => tabulate(&pending).into_owned(),
+                    false => pending,
+                };
+                rest = sanitise(&rest, escapes).into_owned();
+
+                // The prompt should always start on a new line.
+                if !rest.is_empty() && !rest.ends_with('\n') {
+                    rest.push('\n');
+                }
+
+                session.sink.write(Channel::Program, truncator.admit(&rest));
+                kept.push(&rest);
+                output = kept.into_string();
+
+                // The output is kept so that it can be recovered.
+                session.truncated_output = None;
+                if truncator.is_truncated() {
+                    session.sink.write(Channel::Repl, &truncator.note());
+                    session.truncated_output = Some(output.clone());
+                }
+            } else if matches!(inner, InteractiveCommand::Type(_)) {
+                // The type is captured so that it can be memoised.
+                match capture_stdout(|| compiler.run_interactive(expr.to_string())) {
+                    Ok(((), captured)) => {
+                        print!("{captured}");
+                        output = captured;
+                    }
+                    Err(_) => compiler.run_interactive(expr.to_string()),
+                }
+            } else {
+                run_input(compiler, session.program_stdin, expr.to_string());
+            }
+
+            let elapsed = start.elapsed();
+            compiler.settings_mut().semantic_settings.dump_tir = dump_tir;
+
+            if directives.time {
+                session.sink.write(Channel::Repl, &format!("took {elapsed:.2?}\n"));
+            }
+
+            if let Some(threshold) = session.options.warn_slow.filter(|t| elapsed > *t) {
+                session.sink.write(
+                    Channel::Repl,
+                    &format!(
+                        "warning: evaluation took {elapsed:.2?}, which is over the \
+                         `warn-slow` threshold of {threshold:?}, use `:flamegraph on` to profile \
+                         the session\n"
+                    ),
+                );
+            }
+
+            let failed = compiler.diagnostics().iter().any(|report| report.is_error());
+            session.status.end(failed);
+
+            if matches!(inner, InteractiveCommand::Code(_)) {
+                session.stats.record_evaluation(elapsed);
+
+                // Inputs which failed didn't define anything, so they aren't
+                // sources of the definitions of the session.
+                if !failed {
+                    session.sources.add_input(expr);
+                }
+            }
+
+            if matches!(inner, InteractiveCommand::Type(_)) && !failed && !output.is_empty() {
+                session.queries.insert_type(expr, output.clone());
+            }
+
+            if let Some(log) = &mut session.event_log {
+                for report in compiler.diagnostics().iter() {
+                    log.record(Event::Diagnostic(&Diagnostic::from_report(report)));
+                }
+
+                log.record(Event::Result { output: &output, failed });
+                log.record(Event::Timing(elapsed));
+            }
+
+            if failed {
+                let errors: Vec<_> = compiler
+                    .diagnostics()
+                    .iter()
+                    .filter(|report| report.is_error())
+                    .map(Diagnostic::from_report)
+                    .collect();
+
+                let imports = session.module_index.suggest_imports(&errors);
+                for suggestion in &imports {
+                    println!(
+                        "help: import `{}` to bring `{}` into scope",
+                        suggestion.module, suggestion.name
+                    );
+                }
+
+                for code in errors.iter().filter_map(|error| error.code.as_deref()) {
+                    session.stats.record_error(code);
+                }
+
+                session.pending_fix = (!imports.is_empty()).then(|| {
+                    println!("Run `:fix` to add the imports and retry");
+                    Fix { imports, input: expr.to_string() }
+                });
+
+                session.last_error = Some(LastError { input: expr.to_string(), errors });
+            } else {
+                session.pending_fix = None;
+            }
+
+            if let Some(flamegraph) = &mut session.flamegraph {
+                flamegraph.record(&["hashi", frame, expr], elapsed);
+            }
         }
+        Err(err) => return failed(err),
     }
+
+    match session.compiler.diagnostics().iter().any(|report| report.is_error()) {
+        true => ControlFlow::Continue(Status::Error),
+        false => ControlFlow::Continue(Status::Ok),
+    }
+}
+
+/// Report the error of an input that failed.
+fn failed(err: InteractiveError) -> ControlFlow<(), Status> {
+    println!("{}", Report::from(err));
+    ControlFlow::Continue(Status::Error)
 }
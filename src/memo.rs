@@ -0,0 +1,57 @@
+//! Memoisation of the results of `:t`, so that editor integrations which
+//! repeatedly ask for the type of the same code don't elaborate it each time.
+//! Results are keyed by the text of the query and the generation of the
+//! session, which is advanced whenever the definitions of the session might
+//! have changed.
+//!
+//! ##Note: completions aren't memoised, since they depend on the text of the
+//! line and the file system rather than on the session, and don't run the
+//! compiler.
+
+use std::collections::HashMap;
+
+/// The number of results that are kept before stale results are evicted.
+const MAX_ENTRIES: usize = 256;
+
+/// The memoised results of the queries of a session.
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    /// The generation of the session.
+    generation: u64,
+
+    /// The output of `:t` for each expression, along with the generation
+    /// that it was computed in.
+    types: HashMap<String, (u64, String)>,
+}
+
+impl QueryCache {
+    /// Advance the generation of the session, invalidating all of the
+    /// results computed so far.
+    pub fn advance(&mut self) {
+        self.generation += 1;
+    }
+
+    /// Get the output of `:t` for the expression, if it was computed in the
+    /// current generation.
+    pub fn get_type(&self, expr: &str) -> Option<&str> {
+        let (generation, output) = self.types.get(expr.trim())?;
+        (*generation == self.generation).then_some(output.as_str())
+    }
+
+    /// Remember the output of `:t` for the expression.
+    pub fn insert_type(&mut self, expr: &str, output: String) {
+        if self.types.len() >= MAX_ENTRIES {
+            let generation = self.generation;
+            self.types.retain(|_, (computed, _)| *computed == generation);
+
+            // All of the results are current, so an arbitrary one is dropped.
+            if let Some(key) =
+                self.types.keys().next().cloned().filter(|_| self.types.len() >= MAX_ENTRIES)
+            {
+                self.types.remove(&key);
+            }
+        }
+
+        self.types.insert(expr.trim().to_string(), (self.generation, output));
+    }
+}
@@ -0,0 +1,170 @@
+//! The catalog of the messages that the REPL itself prints, so that they can
+//! be translated. The diagnostics of the compiler are not part of the catalog,
+//! since they are produced by the reporting crate.
+//!
+//! The locale is selected with `--locale`, or otherwise the `HASHI_LOCALE` or
+//! `LANG` environment variables. The catalog for a locale is read from
+//! `<locale>.toml` in the directory given by `HASHI_LOCALE_DIR`, or otherwise
+//! `hashi/locales` within the user's configuration directory. A catalog maps
+//! the keys of messages to their translation, where `{name}` is replaced by
+//! the argument of the message with that name, e.g.
+//!
+//! ```toml
+//! goodbye = "Au revoir !"
+//! unrecognised-command = "commande inconnue `{command}`"
+//! ```
+//!
+//! Messages which are missing from the catalog are printed in English. The
+//! JSON reports of `grade` and `sandbox-run` are read by other programs, so
+//! they aren't part of the catalog.
+//!
+//! @@Future: move the confirmations and hints that the older commands print
+//! into the catalog, which currently covers the errors of the REPL and the
+//! messages of exporting, reading, renaming, grading and the daemon.
+
+use std::{collections::HashMap, env, fmt, fs, path::PathBuf, sync::OnceLock};
+
+use toml_edit::Document;
+
+/// The environment variable that can be used to select the locale.
+pub const LOCALE_ENV_VAR: &str = "HASHI_LOCALE";
+
+/// The environment variable that can be used to specify the directory that
+/// catalogs are read from.
+pub const LOCALE_DIR_ENV_VAR: &str = "HASHI_LOCALE_DIR";
+
+/// The English messages, which are used for any message that is missing from
+/// the catalog of the selected locale.
+const DEFAULT_MESSAGES: &[(&str, &str)] = &[
+    ("version", "Version {version}"),
+    ("goodbye", "Goodbye!"),
+    ("exiting", "Exiting!"),
+    ("unrecognised-command", "unrecognised command `{command}`"),
+    ("unexpected-argument", "unexpected argument `{arg}` for command"),
+    ("missing-operand", "missing operand for `{arg}`"),
+    ("invalid-argument", "invalid argument `{arg}` for `{command}`"),
+    ("io", "failed to access `{path}`: {message}"),
+    ("no-sources", "no sources found for `{target}`"),
+    ("invalid-pattern", "invalid pattern `{pattern}`: {message}"),
+    ("evaluation-failed", "failed to evaluate `{expr}`"),
+    ("not-boolean", "assertion `{expr}` did not evaluate to a boolean"),
+    ("unexportable", "`{value}` can't be exported as {format}"),
+    ("invalid-data", "failed to read data from `{path}`: {message}"),
+    ("undefined-name", "no definition of `{name}` was found in the session"),
+    ("name-in-use", "`{name}` is already defined in the session"),
+    ("unknown-directive", "unknown directive `#!{name}`"),
+    ("invalid-script", "{path}:{line}: {message}"),
+    ("source-cycle", "`{path}` is already being sourced: {chain}"),
+    ("unknown-option", "unknown option `{key}`"),
+    ("invalid-config", "invalid configuration in `{path}`: {message}"),
+    ("disabled-command", "the `{command}` command is disabled in this deployment"),
+    ("absolute-path", "absolute paths such as `{path}` are not allowed in this deployment"),
+    ("escaping-path", "paths outside of the working directory such as `{path}` are not allowed in this deployment"),
+    ("disabled-process", "starting programs such as `{program}` is not allowed in this deployment"),
+    ("exported", "Exported `{expr}` to `{path}` as {format}"),
+    ("exported-piped", "Exported the piped input to `{path}` as {format}"),
+    ("empty-data", "the file is empty"),
+    ("invalid-field-name", "`{name}` is not a valid field name"),
+    ("ragged-row", "row {row} has a different number of columns to the header"),
+    (
+        "rename-hint",
+        "Use `:rename {old} {new} --write` to write the changes to {count} loaded files",
+    ),
+    ("invalid-check", "expected `{syntax}`, found `{found}`"),
+    ("daemon-listening", "Listening on `{path}`"),
+    ("daemon-idle", "Idle for {timeout}, shutting down"),
+    ("daemon-disconnected", "client disconnected: {message}"),
+    ("daemon-in-use", "a daemon is already listening on the socket"),
+    ("daemon-unsupported", "daemons are only supported on unix"),
+    ("socket-not-owned", "the socket belongs to another user"),
+    ("malformed-frame", "malformed frame header"),
+    ("oversized-frame", "frame of {len} bytes exceeds the limit of {limit} bytes"),
+    ("frame-not-utf8", "frame is not valid UTF-8"),
+];
+
+/// The catalog of the selected locale.
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Select the locale that messages are printed in, this should be called
+/// once at start-up. If the catalog of the locale can't be read, then the
+/// English messages are used.
+pub fn init(locale: Option<&str>) {
+    let locale =
+        locale.map(str::to_string).or_else(|| env::var(LOCALE_ENV_VAR).ok()).or_else(|| {
+            // Only the language of `LANG` is used, e.g. `fr` from `fr_FR.UTF-8`.
+            let lang = env::var("LANG").ok()?;
+            let language = lang.split(['_', '.']).next()?;
+            (!language.is_empty() && language != "C" && language != "en")
+                .then(|| language.to_string())
+        });
+
+    let catalog = locale.and_then(|locale| match read_catalog(&locale) {
+        Ok(catalog) => catalog,
+        Err(message) => {
+            eprintln!("failed to read the messages for locale `{locale}`: {message}");
+            None
+        }
+    });
+
+    let _ = CATALOG.set(catalog.unwrap_or_default());
+}
+
+/// Read the catalog of the given locale, returning `None` if there is no
+/// catalog for the locale.
+fn read_catalog(locale: &str) -> Result<Option<HashMap<String, String>>, String> {
+    let dir = env::var_os(LOCALE_DIR_ENV_VAR)
+        .map(PathBuf::from)
+        .or_else(|| dirs_next::config_dir().map(|dir| dir.join("hashi").join("locales")));
+
+    let Some(path) = dir.map(|dir| dir.join(format!("{locale}.toml"))) else {
+        return Ok(None);
+    };
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+    let document = contents.parse::<Document>().map_err(|err| err.to_string())?;
+
+    document
+        .iter()
+        .map(|(key, value)| match value.as_str() {
+            Some(message) => Ok((key.to_string(), message.to_string())),
+            None => Err(format!("message `{key}` must be a string")),
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+/// Get the message with the given key in the selected locale, replacing its
+/// `{name}` placeholders with the given arguments. Use the [`message!`] macro
+/// rather than calling this directly.
+pub fn lookup(key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let template = CATALOG
+        .get()
+        .and_then(|catalog| catalog.get(key))
+        .map(String::as_str)
+        .or_else(|| DEFAULT_MESSAGES.iter().find(|(k, _)| *k == key).map(|(_, m)| *m))
+        .unwrap_or(key);
+
+    let mut text = template.to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+
+    text
+}
+
+/// Get a message from the catalog, with its arguments given by name, e.g.
+/// `message!("unknown-option", key = "foo")`.
+macro_rules! message {
+    ($key:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::messages::lookup(
+            $key,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),*],
+        )
+    };
+}
+
+pub(crate) use message;
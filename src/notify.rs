@@ -0,0 +1,94 @@
+//! Notifications when an evaluation that took a long time finishes, e.g.
+//! `:load`ing a big project, configured in the `[notify]` section of the
+//! configuration. The notification is a user command (such as a desktop
+//! notification) and/or the terminal bell.
+//!
+//! ##Note: whether the terminal is focused can't be detected reliably, so the
+//! threshold stands in for it, since the user has probably switched away
+//! from an evaluation that takes that long.
+
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use hash_reporting::report::Report;
+
+use crate::{error::InteractiveError, timeline::Status};
+
+/// The duration after which an evaluation is considered long-running, if the
+/// configuration doesn't specify one.
+pub const DEFAULT_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// How to notify the user that a long-running evaluation has finished.
+#[derive(Debug, Clone)]
+pub struct Notify {
+    /// The shell command that is run. The input, its status and how long it
+    /// took are passed in the `HASHI_INPUT`, `HASHI_STATUS` and
+    /// `HASHI_ELAPSED_MS` environment variables.
+    pub command: Option<String>,
+
+    /// Whether the terminal bell is rung.
+    pub bell: bool,
+
+    /// How long an evaluation must take for the user to be notified.
+    pub threshold: Duration,
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Self { command: None, bell: false, threshold: DEFAULT_THRESHOLD }
+    }
+}
+
+impl Notify {
+    /// Notify the user that the input finished, if it took longer than the
+    /// threshold.
+    pub fn finished(&self, input: &str, status: Status, elapsed: Duration) {
+        if elapsed < self.threshold {
+            return;
+        }
+
+        if self.bell {
+            let mut stdout = io::stdout();
+            let _ = write!(stdout, "\x07");
+            let _ = stdout.flush();
+        }
+
+        if let Some(command) = &self.command {
+            if let Err(err) = self.run(command, input, status, elapsed) {
+                eprintln!("{}", Report::from(err));
+            }
+        }
+    }
+
+    /// Run the notification command in the background.
+    fn run(
+        &self,
+        command: &str,
+        input: &str,
+        status: Status,
+        elapsed: Duration,
+    ) -> Result<(), InteractiveError> {
+        let (shell, flag) = if cfg!(target_os = "windows") { ("cmd", "/C") } else { ("sh", "-c") };
+
+        let mut child = Command::new(shell)
+            .arg(flag)
+            .arg(command)
+            .env("HASHI_INPUT", input)
+            .env("HASHI_STATUS", status.name())
+            .env("HASHI_ELAPSED_MS", elapsed.as_millis().to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| InteractiveError::io(shell, err))?;
+
+        // The command is waited for in the background so that it doesn't
+        // hold up the next input.
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
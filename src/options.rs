@@ -0,0 +1,285 @@
+//! Options of the REPL which can be changed at runtime with `:set`.
+
+use std::{fmt, path::PathBuf, time::Duration};
+
+use hash_utils::log::{self, LevelFilter};
+
+use crate::{
+    ansi::EscapePolicy,
+    error::{InteractiveError, InteractiveResult},
+};
+
+/// The prelude that a session starts with.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Prelude {
+    /// The standard prelude of the language.
+    #[default]
+    Default,
+
+    /// No prelude, the session starts with an empty scope.
+    None,
+
+    /// A custom prelude which is read from the given file.
+    File(PathBuf),
+}
+
+impl fmt::Display for Prelude {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Prelude::Default => write!(f, "default"),
+            Prelude::None => write!(f, "none"),
+            Prelude::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// The number of bytes that an evaluation may print by default.
+const DEFAULT_PRINT_LIMIT: usize = 64 * 1024;
+
+/// The options of the REPL.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Clean up pasted input by stripping Markdown code fences and prompt
+    /// prefixes before it is parsed (`paste.clean`).
+    pub paste_clean: bool,
+
+    /// The prelude that new sessions start with (`prelude`), changing this
+    /// takes effect when the session is next `:reset`.
+    pub prelude: Prelude,
+
+    /// Print lists of records that are evaluated as aligned tables
+    /// (`print.table`).
+    pub print_table: bool,
+
+    /// Check inputs for common mistakes before they are run, fixing them
+    /// where possible (`lint`).
+    pub lint: bool,
+
+    /// Warn about evaluations which take longer than this (`warn-slow`).
+    pub warn_slow: Option<Duration>,
+
+    /// Which escape sequences in the output of evaluated programs reach the
+    /// terminal (`program.escapes`).
+    pub program_escapes: EscapePolicy,
+
+    /// The number of bytes that an evaluation may print before the rest of
+    /// its output is truncated (`print.limit`).
+    pub print_limit: Option<usize>,
+
+    /// Print the AST of every input (`dump.ast`).
+    pub dump_ast: bool,
+
+    /// Print the TIR of every input (`dump.tir`).
+    pub dump_tir: bool,
+
+    /// The level of the compiler's logging that is printed (`log`).
+    pub log_level: LevelFilter,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            paste_clean: false,
+            prelude: Prelude::default(),
+            print_table: true,
+            lint: true,
+            warn_slow: None,
+            program_escapes: EscapePolicy::default(),
+            print_limit: Some(DEFAULT_PRINT_LIMIT),
+            dump_ast: false,
+            dump_tir: false,
+            log_level: LevelFilter::Off,
+        }
+    }
+}
+
+impl Options {
+    /// Apply the options of a profile, returning the messages that describe
+    /// the new states of the options. The profile is applied to the default
+    /// options rather than the current ones (other than the prelude, which
+    /// only changes on `:reset`), so switching between profiles doesn't
+    /// depend on the order they were used in. If any of the options can't be
+    /// set, then none of them are changed.
+    pub fn apply_profile(
+        &mut self,
+        options: &[(String, String)],
+    ) -> InteractiveResult<Vec<String>> {
+        let mut profile = Options { prelude: self.prelude.clone(), ..Options::default() };
+        let messages = options
+            .iter()
+            .map(|(key, value)| profile.set(key, Some(value)))
+            .collect::<InteractiveResult<_>>();
+
+        // Setting the options of the profile might have already changed the
+        // log level, so it is restored if the profile isn't applied.
+        if messages.is_ok() {
+            *self = profile;
+        }
+
+        log::set_max_level(self.log_level);
+        messages
+    }
+
+    /// Set the option `key` to the given `value`. If no value is given for a
+    /// boolean option, the option is toggled. The message that describes the
+    /// new state of the option is returned.
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> InteractiveResult<String> {
+        match key {
+            "paste.clean" => set_bool(&mut self.paste_clean, key, value),
+            "print.table" => set_bool(&mut self.print_table, key, value),
+            "lint" => set_bool(&mut self.lint, key, value),
+            "dump.ast" => set_bool(&mut self.dump_ast, key, value),
+            "dump.tir" => set_bool(&mut self.dump_tir, key, value),
+            "log" => {
+                let value =
+                    value.ok_or_else(|| InteractiveError::MissingOperand(key.to_string()))?;
+                self.log_level = value.parse().map_err(|_| InteractiveError::InvalidArgument {
+                    command: key.to_string(),
+                    arg: value.to_string(),
+                })?;
+
+                log::set_max_level(self.log_level);
+                Ok(format!("{key} is {}", self.log_level))
+            }
+            "prelude" => {
+                self.prelude = match value {
+                    Some("default") => Prelude::Default,
+                    Some("none") => Prelude::None,
+                    Some(path) => Prelude::File(PathBuf::from(path)),
+                    None => return Err(InteractiveError::MissingOperand(key.to_string())),
+                };
+
+                Ok(format!("prelude is {}, this applies after the next `:reset`", self.prelude))
+            }
+            "warn-slow" => {
+                self.warn_slow = match value {
+                    Some("off") => None,
+                    Some(value) => Some(parse_duration(value).ok_or_else(|| {
+                        InteractiveError::InvalidArgument {
+                            command: key.to_string(),
+                            arg: value.to_string(),
+                        }
+                    })?),
+                    None => return Err(InteractiveError::MissingOperand(key.to_string())),
+                };
+
+                match self.warn_slow {
+                    Some(threshold) => Ok(format!("{key} is {threshold:?}")),
+                    None => Ok(format!("{key} is off")),
+                }
+            }
+            "print.limit" => {
+                self.print_limit = match value {
+                    Some("off") => None,
+                    Some(value) => Some(parse_size(value).ok_or_else(|| {
+                        InteractiveError::InvalidArgument {
+                            command: key.to_string(),
+                            arg: value.to_string(),
+                        }
+                    })?),
+                    None => return Err(InteractiveError::MissingOperand(key.to_string())),
+                };
+
+                match self.print_limit {
+                    Some(limit) => Ok(format!("{key} is {limit} bytes")),
+                    None => Ok(format!("{key} is off")),
+                }
+            }
+            "program.escapes" => {
+                let value =
+                    value.ok_or_else(|| InteractiveError::MissingOperand(key.to_string()))?;
+                self.program_escapes = EscapePolicy::parse(value).ok_or_else(|| {
+                    InteractiveError::InvalidArgument {
+                        command: key.to_string(),
+                        arg: value.to_string(),
+                    }
+                })?;
+
+                Ok(format!("{key} is {}", self.program_escapes))
+            }
+            _ => Err(InteractiveError::UnknownOption(key.to_string())),
+        }
+    }
+}
+
+/// Set a boolean option, toggling it if no value is specified.
+fn set_bool(option: &mut bool, key: &str, value: Option<&str>) -> InteractiveResult<String> {
+    *option = match value {
+        None => !*option,
+        Some("on" | "true") => true,
+        Some("off" | "false") => false,
+        Some(value) => {
+            return Err(InteractiveError::InvalidArgument {
+                command: key.to_string(),
+                arg: value.to_string(),
+            })
+        }
+    };
+
+    Ok(format!("{key} is {}", if *option { "on" } else { "off" }))
+}
+
+/// Parse a duration with a unit, e.g. `500ms`, `1.5s` or `2m`.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let split = text.find(|ch: char| !ch.is_ascii_digit() && ch != '.')?;
+    let (amount, unit) = text.split_at(split);
+    let amount: f64 = amount.parse().ok()?;
+
+    let seconds = match unit {
+        "us" | "µs" => amount / 1_000_000.0,
+        "ms" => amount / 1_000.0,
+        "s" => amount,
+        "m" => amount * 60.0,
+        _ => return None,
+    };
+
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Parse a number of bytes with an optional unit, e.g. `4096`, `64kb` or
+/// `1mb`.
+pub fn parse_size(text: &str) -> Option<usize> {
+    let split = text.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(text.len());
+    let (amount, unit) = text.split_at(split);
+    let amount: usize = amount.parse().ok()?;
+
+    let scale = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" | "k" => 1024,
+        "mb" | "m" => 1024 * 1024,
+        _ => return None,
+    };
+
+    amount.checked_mul(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{parse_duration, parse_size};
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("250us"), Some(Duration::from_micros(250)));
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+
+        for other in ["", "10", "s", "1.5.5s", "-1s", "1h", "1 s"] {
+            assert_eq!(parse_duration(other), None, "{other}");
+        }
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("64kb"), Some(64 * 1024));
+        assert_eq!(parse_size("1M"), Some(1024 * 1024));
+        assert_eq!(parse_size("10b"), Some(10));
+
+        for other in ["", "kb", "1.5mb", "1gb", "-1", "99999999999999999999mb"] {
+            assert_eq!(parse_size(other), None, "{other}");
+        }
+    }
+}
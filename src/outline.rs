@@ -0,0 +1,191 @@
+//! Building a structural outline of the items that a module declares.
+//!
+//! @@Future: the outline is found by splitting the module into declarations
+//! textually, it should be built from the AST once the compiler exposes the
+//! parsed module to the frontend.
+
+use std::{fmt, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::scan::statements;
+
+/// The length after which signatures are truncated.
+const MAX_SIGNATURE_LEN: usize = 72;
+
+/// The start of a declaration, up to the `:` after its name.
+static DECLARATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:(?:pub|priv|mut)\s+)*[\p{Alphabetic}_][\w]*\s*:").unwrap()
+});
+
+/// The kind of an item in the outline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Struct,
+    Enum,
+    Trait,
+    Function,
+    Module,
+    Impl,
+    Constant,
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemKind::Struct => write!(f, "struct"),
+            ItemKind::Enum => write!(f, "enum"),
+            ItemKind::Trait => write!(f, "trait"),
+            ItemKind::Function => write!(f, "fn"),
+            ItemKind::Module => write!(f, "mod"),
+            ItemKind::Impl => write!(f, "impl"),
+            ItemKind::Constant => write!(f, "const"),
+        }
+    }
+}
+
+/// An item that is declared by a module.
+#[derive(Debug)]
+pub struct Item {
+    /// The name that the item is bound to.
+    pub name: String,
+
+    /// The kind of the item.
+    pub kind: ItemKind,
+
+    /// The signature of the item, i.e. its declaration without its body.
+    pub signature: String,
+
+    /// The (1-based) line that the item is declared on.
+    pub line: usize,
+
+    /// The items that are declared within the item, for modules and impls.
+    pub children: Vec<Item>,
+}
+
+/// The outline of a module.
+#[derive(Debug)]
+pub struct Outline(pub Vec<Item>);
+
+impl Outline {
+    /// Build the outline of the given module source.
+    pub fn new(contents: &str) -> Self {
+        Self(items(contents, 0, contents.len()))
+    }
+}
+
+impl fmt::Display for Outline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_items(f: &mut fmt::Formatter<'_>, items: &[Item], depth: usize) -> fmt::Result {
+            for item in items {
+                let indent = "  ".repeat(depth);
+                writeln!(f, "{:>4}  {indent}{:<6} {}", item.line, item.kind, item.signature)?;
+                write_items(f, &item.children, depth + 1)?;
+            }
+
+            Ok(())
+        }
+
+        write_items(f, &self.0, 0)
+    }
+}
+
+/// Find the items that are declared between the bytes `offset` and `end` of
+/// the module.
+fn items(module: &str, offset: usize, end: usize) -> Vec<Item> {
+    let text = &module[offset..end];
+
+    statements(text)
+        .into_iter()
+        .filter_map(|(start, end)| {
+            let statement = &text[start..end];
+            let header = DECLARATION.find(statement)?;
+            let leading = statement.len() - statement.trim_start().len();
+            let line = module[..offset + start + leading].matches('\n').count() + 1;
+
+            let (name, rest) = statement[leading..].split_once(':')?;
+            let name = name.split_whitespace().last()?;
+            let (ty, value) = match rest.strip_prefix('=') {
+                Some(value) => (None, value.trim()),
+                None => match rest.split_once('=') {
+                    Some((ty, value)) => (Some(ty.trim()), value.trim()),
+                    None => (Some(rest.trim()), ""),
+                },
+            };
+
+            let keyword = value.split(|ch: char| !ch.is_alphanumeric()).next().unwrap_or("");
+            let kind = match keyword {
+                "struct" => ItemKind::Struct,
+                "enum" => ItemKind::Enum,
+                "trait" => ItemKind::Trait,
+                "mod" => ItemKind::Module,
+                "impl" => ItemKind::Impl,
+                _ if is_function(value) => ItemKind::Function,
+                _ => ItemKind::Constant,
+            };
+
+            let signature = match kind {
+                ItemKind::Module | ItemKind::Impl | ItemKind::Trait => match value.find('{') {
+                    Some(brace) if brace > keyword.len() => {
+                        format!("{name} := {}", value[..brace].trim())
+                    }
+                    _ => name.to_string(),
+                },
+                ItemKind::Struct | ItemKind::Enum => format!("{name} := {value}"),
+                ItemKind::Function => {
+                    let end = value.find("=>").unwrap_or(value.len());
+                    format!("{name} := {}", value[..end].trim())
+                }
+                ItemKind::Constant => match ty {
+                    Some(ty) => format!("{name}: {ty}"),
+                    None => name.to_string(),
+                },
+            };
+
+            let children = match kind {
+                ItemKind::Module | ItemKind::Impl | ItemKind::Trait => {
+                    let body_start = start + header.end() + statement[header.end()..].find('{')?;
+                    let body_end = start + statement.rfind('}')?;
+                    items(module, offset + body_start + 1, offset + body_end)
+                }
+                _ => vec![],
+            };
+
+            Some(Item {
+                name: name.to_string(),
+                kind,
+                signature: truncate(&collapse(&signature)),
+                line,
+                children,
+            })
+        })
+        .collect()
+}
+
+/// Check whether the value of a declaration is a function, i.e. a parameter
+/// list (optionally preceded by type parameters) followed by `=>` or a
+/// return type.
+fn is_function(value: &str) -> bool {
+    let value = match value.strip_prefix('<') {
+        Some(rest) => rest.split_once('>').map_or("", |(_, rest)| rest.trim_start()),
+        None => value,
+    };
+
+    value.starts_with('(')
+        && value
+            .find("=>")
+            .is_some_and(|arrow| value[..arrow].trim_end().ends_with(')') || value.contains("->"))
+}
+
+/// Collapse the whitespace of a signature onto a single line.
+fn collapse(signature: &str) -> String {
+    signature.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Truncate a signature to [`MAX_SIGNATURE_LEN`] characters.
+fn truncate(signature: &str) -> String {
+    match signature.char_indices().nth(MAX_SIGNATURE_LEN) {
+        Some((index, _)) => format!("{}…", &signature[..index]),
+        None => signature.to_string(),
+    }
+}
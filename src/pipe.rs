@@ -0,0 +1,77 @@
+//! Piping the output of a command into another command, e.g.
+//! `:t it | :export --format json -` or `:d main | :page`.
+//!
+//! The output of each stage of a pipeline is captured, and made available to
+//! the next stage as its piped input. Commands which accept piped input use
+//! it in place of their operand, other commands ignore it.
+
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    error::{InteractiveError, InteractiveResult},
+    policy::Policy,
+    scan::scan,
+};
+
+/// The pager that is used if `PAGER` isn't set.
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Split a command into the stages of a pipeline, returning `None` if it
+/// isn't a pipeline. Stages are separated by a `|` that is followed by
+/// another command, and isn't within brackets or a literal, so that `|` can
+/// still be used within expressions.
+pub fn split_pipeline(input: &str) -> Option<Vec<&str>> {
+    if !input.trim_start().starts_with(':') {
+        return None;
+    }
+
+    let mut stages = vec![];
+    let mut start = 0;
+
+    for token in scan(input) {
+        if token.depth == 0
+            && token.is_punct('|')
+            && input[token.end()..].trim_start().starts_with(':')
+        {
+            stages.push(input[start..token.start].trim());
+            start = token.end();
+        }
+    }
+
+    stages.push(input[start..].trim());
+    (stages.len() > 1).then_some(stages)
+}
+
+/// Show the given text in the pager given by `PAGER`, or print it if the
+/// output isn't a terminal. The pager is only started if the policy allows
+/// it.
+pub fn page(text: &str, policy: &Policy) -> InteractiveResult<()> {
+    if !io::stdout().is_terminal() {
+        println!("{text}");
+        return Ok(());
+    }
+
+    let pager = env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let mut words = pager.split_whitespace();
+    let program = words.next().unwrap_or("less");
+    policy.check_process(program)?;
+
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| InteractiveError::io(program, err))?;
+
+    // The pager may exit before reading all of the text, which isn't an
+    // error.
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = writeln!(stdin, "{text}");
+    }
+
+    child.wait().map_err(|err| InteractiveError::io(program, err))?;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! A policy of which commands may be used within a deployment of the
+//! interactive mode. This is used to restrict what users can do when hashi
+//! is embedded in an environment such as a playground or a grading sandbox.
+//!
+//! ##Note: paths are checked lexically, so a symbolic link within the working
+//! directory can still lead outside of it.
+
+use std::{
+    collections::HashSet,
+    path::{Component, Path},
+};
+
+use crate::{
+    command::InteractiveCommand,
+    error::{InteractiveError, InteractiveResult},
+    messages::message,
+};
+
+/// The command policy of the session, by default every command is allowed.
+#[derive(Debug, Default)]
+pub struct Policy {
+    /// The names of commands which have been disabled.
+    disabled: HashSet<String>,
+
+    /// The names of the only commands which are allowed, if the policy is an
+    /// allowlist.
+    allowed: Option<HashSet<String>>,
+
+    /// Whether commands are confined to the working directory, preventing
+    /// them from accessing absolute paths and relative paths which lead out
+    /// of it.
+    pub deny_absolute_paths: bool,
+
+    /// Whether commands are prevented from starting other programs, such as
+    /// the pager of `:page` and the editor of `:scratch`.
+    pub deny_processes: bool,
+}
+
+impl Policy {
+    /// Create a new policy which disables the given commands. Commands can be
+    /// referred to with or without their leading `:`.
+    pub fn new(disabled: impl IntoIterator<Item = String>) -> Self {
+        let disabled =
+            disabled.into_iter().map(|name| name.trim_start_matches(':').to_string()).collect();
+
+        Self { disabled, ..Self::default() }
+    }
+
+    /// Create a new policy which only allows the given commands, so that
+    /// commands which are added later are disabled until they are reviewed.
+    /// Code is run by the `code` command.
+    pub fn allow_only(allowed: impl IntoIterator<Item = String>) -> Self {
+        let allowed =
+            allowed.into_iter().map(|name| name.trim_start_matches(':').to_string()).collect();
+
+        Self { allowed: Some(allowed), ..Self::default() }
+    }
+
+    /// Check whether the given command is allowed to be run.
+    pub fn check(&self, command: &InteractiveCommand) -> InteractiveResult<()> {
+        let allowed = self.allowed.as_ref().is_none_or(|allowed| allowed.contains(command.name()));
+
+        if !allowed || self.disabled.contains(command.name()) {
+            return Err(InteractiveError::Disallowed(message!(
+                "disabled-command",
+                command = command.name()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check whether a command is allowed to access the given path.
+    pub fn check_path(&self, path: &Path) -> InteractiveResult<()> {
+        if self.deny_absolute_paths && path.is_absolute() {
+            return Err(InteractiveError::Disallowed(message!(
+                "absolute-path",
+                path = path.display()
+            )));
+        }
+
+        if self.deny_absolute_paths && !is_within_working_dir(path) {
+            return Err(InteractiveError::Disallowed(message!(
+                "escaping-path",
+                path = path.display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check whether a command is allowed to start the given program.
+    pub fn check_process(&self, program: &str) -> InteractiveResult<()> {
+        if self.deny_processes {
+            return Err(InteractiveError::Disallowed(message!(
+                "disabled-process",
+                program = program
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Check whether a relative path stays within the working directory once its
+/// `.` and `..` components are resolved, e.g. `data/../x.csv` does whilst
+/// `data/../../x.csv` doesn't.
+fn is_within_working_dir(path: &Path) -> bool {
+    let mut depth = 0usize;
+
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(parent) => depth = parent,
+                None => return false,
+            },
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{is_within_working_dir, Policy};
+
+    #[test]
+    fn confines_paths_to_the_working_directory() {
+        let policy = Policy { deny_absolute_paths: true, ..Policy::default() };
+
+        for allowed in ["x.csv", "./data/x.csv", "data/../x.csv", "data/./../data/x.csv"] {
+            assert!(policy.check_path(Path::new(allowed)).is_ok(), "{allowed}");
+        }
+
+        for denied in ["/etc/passwd", "../x.csv", "../../etc/passwd", "data/../../x.csv"] {
+            assert!(policy.check_path(Path::new(denied)).is_err(), "{denied}");
+        }
+    }
+
+    #[test]
+    fn allows_any_path_by_default() {
+        let policy = Policy::default();
+        assert!(policy.check_path(Path::new("../../etc/passwd")).is_ok());
+        assert!(policy.check_path(Path::new("/etc/passwd")).is_ok());
+        assert!(!is_within_working_dir(Path::new("/etc/passwd")));
+    }
+
+    #[test]
+    fn denies_processes() {
+        let policy = Policy { deny_processes: true, ..Policy::default() };
+        assert!(policy.check_process("less").is_err());
+        assert!(Policy::default().check_process("less").is_ok());
+    }
+}
@@ -0,0 +1,36 @@
+//! Pre-processing of raw input before it is parsed into a command. Each
+//! stage of the pre-processor is enabled by the REPL [Options].
+
+use std::borrow::Cow;
+
+use crate::options::Options;
+
+/// Prompt prefixes that are commonly found in text that is copied from
+/// documentation or chats.
+const PROMPT_PREFIXES: &[&str] = &[">>> ", "... "];
+
+/// Run all of the enabled pre-processing stages on the given input.
+pub fn preprocess<'i>(options: &Options, input: &'i str) -> Cow<'i, str> {
+    let mut input = Cow::Borrowed(input);
+
+    if options.paste_clean {
+        input = Cow::Owned(clean_paste(&input));
+    }
+
+    input
+}
+
+/// Remove Markdown code fences and prompt prefixes from pasted input.
+fn clean_paste(input: &str) -> String {
+    input
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .map(|line| strip_prompt(line).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Remove a prompt prefix from the start of a line, if it has one.
+pub fn strip_prompt(line: &str) -> Option<&str> {
+    PROMPT_PREFIXES.iter().find_map(|prefix| line.strip_prefix(prefix))
+}
@@ -0,0 +1,212 @@
+//! The structured protocol of the daemon, which is used by clients such as
+//! the playground frontend. A connection switches to it by sending
+//! [`HANDSHAKE`] as its first frame, after which each frame holds a request
+//! as a JSON object, or a batch of requests as an array:
+//!
+//! ```text
+//! {"id":1,"method":"evaluate","input":"x := 1 + 2;"}
+//! [{"id":2,"method":"evaluate","input":"x"},{"id":3,"method":"complete","line":"\\lam","pos":4}]
+//! ```
+//!
+//! Each request is answered by a response frame holding its `id` and either
+//! its `result` or an `error`. The requests of a batch may be answered out of
+//! order, since completions don't need the compiler and are answered before
+//! any evaluations of the batch, so clients should match responses to
+//! requests by their `id`. Evaluating an input that quits is answered with an
+//! error, since the session is shared by the clients. The daemon also sends
+//! notifications, which have a `method` rather than an `id`:
+//!
+//! - `diagnostic`: a diagnostic emitted whilst evaluating the request `id`.
+//! - `progress`: the number of evaluations of a batch that are `done`, out of
+//!   the `total`.
+//!
+//! @@Future: requests are still handled one frame at a time, so completions
+//! sent in a separate frame wait for any evaluation that is running.
+
+use std::{
+    io::{self, BufRead, Write},
+    ops::ControlFlow,
+};
+
+use hash_pipeline::interface::CompilerInterface;
+use serde_json::{Map, Value};
+
+use crate::{
+    capture::capture_stdout,
+    daemon::{read_frame, write_frame},
+    diagnostic::Diagnostic,
+    helper::EditorHelper,
+    session::Session,
+    timeline::Status,
+};
+
+/// The frame that switches a connection to the structured protocol.
+pub const HANDSHAKE: &str = ":protocol json";
+
+/// The version of the protocol, which is sent in reply to the handshake.
+const VERSION: u64 = 1;
+
+/// A request of a client.
+#[derive(Debug)]
+struct Request {
+    /// The identifier of the request, which is echoed in its response.
+    id: Value,
+
+    /// What the client asked for.
+    method: Method,
+}
+
+/// The methods of the protocol.
+#[derive(Debug)]
+enum Method {
+    /// Evaluate an input, as if it was entered at the prompt.
+    Evaluate { input: String },
+
+    /// Complete the line at the given (byte) position.
+    Complete { line: String, pos: usize },
+}
+
+impl Request {
+    /// Parse a request from its JSON representation.
+    fn parse(value: &Value) -> Result<Self, String> {
+        let id = value.get("id").cloned().ok_or("request is missing its `id`")?;
+        let field = |name: &str| value.get(name).ok_or(format!("request is missing `{name}`"));
+
+        let method = match field("method")?.as_str() {
+            Some("evaluate") => {
+                let input = field("input")?.as_str().ok_or("`input` must be a string")?;
+                Method::Evaluate { input: input.to_string() }
+            }
+            Some("complete") => {
+                let line = field("line")?.as_str().ok_or("`line` must be a string")?;
+                let pos = field("pos")?.as_u64().ok_or("`pos` must be a number")? as usize;
+                if !line.is_char_boundary(pos) {
+                    return Err("`pos` must be within `line`, at a character boundary".to_string());
+                }
+
+                Method::Complete { line: line.to_string(), pos }
+            }
+            Some(method) => return Err(format!("unknown method `{method}`")),
+            None => return Err("`method` must be a string".to_string()),
+        };
+
+        Ok(Self { id, method })
+    }
+}
+
+/// Parse the requests of a frame, which is either a single request or a
+/// batch of them.
+fn parse_frame(payload: &str) -> Result<Vec<Request>, String> {
+    let value: Value = serde_json::from_str(payload).map_err(|err| err.to_string())?;
+
+    match value.as_array() {
+        Some(batch) => batch.iter().map(Request::parse).collect(),
+        None => Request::parse(&value).map(|request| vec![request]),
+    }
+}
+
+/// Write a response to a request.
+fn respond(writer: &mut impl Write, id: Value, result: Result<Value, String>) -> io::Result<()> {
+    let mut fields = Map::new();
+    fields.insert("id".to_string(), id);
+
+    match result {
+        Ok(result) => fields.insert("result".to_string(), result),
+        Err(message) => fields.insert("error".to_string(), Value::from(message)),
+    };
+
+    write_frame(writer, &Value::Object(fields).to_string())
+}
+
+/// Write a notification, which isn't a response to any request.
+fn notify(writer: &mut impl Write, method: &str, params: Map<String, Value>) -> io::Result<()> {
+    let mut fields = params;
+    fields.insert("method".to_string(), Value::from(method));
+    write_frame(writer, &Value::Object(fields).to_string())
+}
+
+/// Serve a client that has sent the [`HANDSHAKE`], until it disconnects.
+pub fn serve_client(
+    session: &mut Session,
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let mut version = Map::new();
+    version.insert("protocol".to_string(), Value::from("json"));
+    version.insert("version".to_string(), Value::from(VERSION));
+    write_frame(writer, &Value::Object(version).to_string())?;
+
+    let helper = EditorHelper::new(&session.config.abbreviations);
+
+    while let Some(payload) = read_frame(reader)? {
+        let requests = match parse_frame(&payload) {
+            Ok(requests) => requests,
+            Err(message) => {
+                respond(writer, Value::Null, Err(message))?;
+                continue;
+            }
+        };
+
+        // Completions are answered first, so that they aren't held up by the
+        // evaluations of the batch.
+        let (completions, evaluations): (Vec<_>, Vec<_>) = requests
+            .into_iter()
+            .partition(|request| matches!(request.method, Method::Complete { .. }));
+
+        for Request { id, method } in completions {
+            let Method::Complete { line, pos } = method else { continue };
+            let (start, candidates) = helper.candidates(&line, pos);
+
+            let mut result = Map::new();
+            result.insert("start".to_string(), Value::from(start));
+            result.insert(
+                "candidates".to_string(),
+                Value::from(
+                    candidates.into_iter().map(|pair| pair.replacement).collect::<Vec<_>>(),
+                ),
+            );
+            respond(writer, id, Ok(Value::Object(result)))?;
+        }
+
+        let total = evaluations.len();
+
+        for (done, Request { id, method }) in evaluations.into_iter().enumerate() {
+            let Method::Evaluate { input } = method else { continue };
+
+            session.log_input(&input);
+            let (flow, output) = capture_stdout(|| crate::execute(session, &input))?;
+            let diagnostics = session.compiler.diagnostics();
+
+            // The session is shared with other clients, so it can't be quit
+            // by one of them.
+            let result = match flow {
+                ControlFlow::Break(()) => {
+                    Err("`:q` can't be evaluated, disconnect instead".to_string())
+                }
+                ControlFlow::Continue(status) => {
+                    for report in diagnostics.iter() {
+                        let mut params = Diagnostic::from_report(report).to_json();
+                        params.insert("id".to_string(), id.clone());
+                        notify(writer, "diagnostic", params)?;
+                    }
+
+                    let mut result = Map::new();
+                    result.insert("output".to_string(), Value::from(output));
+                    result.insert("failed".to_string(), Value::from(status == Status::Error));
+                    Ok(Value::Object(result))
+                }
+            };
+
+            respond(writer, id, result)?;
+
+            if total > 1 {
+                let mut params = Map::new();
+                params.insert("done".to_string(), Value::from(done + 1));
+                params.insert("total".to_string(), Value::from(total));
+                notify(writer, "progress", params)?;
+            }
+        }
+    }
+
+    Ok(())
+}
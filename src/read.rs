@@ -0,0 +1,253 @@
+//! Reading external data into the session with `:read`, the inverse of
+//! `:export`. The data is converted into a Hash literal which is bound to a
+//! name in the session. JSON objects become named tuples, arrays become
+//! lists, and `null` becomes `()`. CSV files become a list of named tuples,
+//! where columns that only contain numbers are read as numbers.
+
+use serde_json::Value as Json;
+
+use crate::{
+    diagnostic::quote,
+    error::{InteractiveError, InteractiveResult},
+    export::DataFormat,
+    messages::message,
+};
+
+/// Convert the contents of a file in the given format into a Hash literal.
+/// Errors are described by a message.
+pub fn to_literal(contents: &str, format: DataFormat) -> Result<String, String> {
+    match format {
+        DataFormat::Json => {
+            let json: Json = serde_json::from_str(contents).map_err(|err| err.to_string())?;
+            json_literal(&json)
+        }
+        DataFormat::Csv => csv_literal(contents),
+    }
+}
+
+/// Create the declaration which binds the literal to the given name.
+pub fn to_binding(name: &str, literal: &str) -> InteractiveResult<String> {
+    if !is_identifier(name) {
+        return Err(InteractiveError::InvalidArgument {
+            command: ":read".to_string(),
+            arg: name.to_string(),
+        });
+    }
+
+    Ok(format!("{name} := {literal};"))
+}
+
+/// Convert a JSON value into a Hash literal.
+fn json_literal(json: &Json) -> Result<String, String> {
+    match json {
+        Json::Null => Ok("()".to_string()),
+        Json::Bool(value) => Ok(value.to_string()),
+        Json::Number(number) => Ok(number.to_string()),
+        Json::String(text) => Ok(quote(text)),
+        Json::Array(items) => {
+            let items = items.iter().map(json_literal).collect::<Result<Vec<_>, _>>()?;
+            Ok(format!("[{}]", items.join(", ")))
+        }
+        Json::Object(fields) => {
+            let fields = fields
+                .iter()
+                .map(|(name, value)| match is_identifier(name) {
+                    true => Ok(format!("{name} = {}", json_literal(value)?)),
+                    false => Err(message!("invalid-field-name", name = name)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(tuple(&fields))
+        }
+    }
+}
+
+/// Convert CSV data with a header row into a list of named tuples.
+fn csv_literal(contents: &str) -> Result<String, String> {
+    let mut rows = parse_csv(contents).into_iter();
+    let headers = rows.next().ok_or_else(|| message!("empty-data"))?;
+
+    if let Some(header) = headers.iter().find(|header| !is_identifier(header)) {
+        return Err(message!("invalid-field-name", name = header));
+    }
+
+    let rows: Vec<_> = rows.collect();
+    if let Some(index) = rows.iter().position(|row| row.len() != headers.len()) {
+        return Err(message!("ragged-row", row = index + 2));
+    }
+
+    // A column is numeric only if all of its cells are numbers, so that all
+    // of the records have the same type, and is read entirely as floats if
+    // any of its cells is a float.
+    let columns: Vec<_> = (0..headers.len())
+        .map(|column| {
+            let mut cells = rows.iter().map(|row| row[column].trim());
+            match cells.clone().all(is_number) {
+                true => Some(cells.any(|cell| !is_integer(cell))),
+                false => None,
+            }
+        })
+        .collect();
+
+    let records: Vec<_> = rows
+        .iter()
+        .map(|row| {
+            let fields: Vec<_> = headers
+                .iter()
+                .zip(row)
+                .zip(&columns)
+                .map(|((name, cell), float)| match float {
+                    Some(true) if is_integer(cell.trim()) => format!("{name} = {}.0", cell.trim()),
+                    Some(_) => format!("{name} = {}", cell.trim()),
+                    None => format!("{name} = {}", quote(cell)),
+                })
+                .collect();
+
+            tuple(&fields)
+        })
+        .collect();
+
+    Ok(format!("[{}]", records.join(", ")))
+}
+
+/// Check whether a cell is a finite decimal number, such as `-12`, `1.5` or
+/// `2.5e-3`, which can be used as a numeric literal. Other numbers that Rust
+/// can parse, such as `inf` and `NaN`, aren't literals in Hash.
+fn is_number(cell: &str) -> bool {
+    let (mantissa, exponent) = match cell.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (cell, None),
+    };
+
+    let digits = |text: &str| !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit());
+    let mantissa = mantissa.strip_prefix('-').unwrap_or(mantissa);
+    let valid_mantissa = match mantissa.split_once('.') {
+        Some((whole, fraction)) => digits(whole) && digits(fraction),
+        None => digits(mantissa),
+    };
+
+    valid_mantissa
+        && exponent
+            .is_none_or(|exponent| digits(exponent.strip_prefix(['-', '+']).unwrap_or(exponent)))
+}
+
+/// Check whether a numeric cell is an integer.
+fn is_integer(cell: &str) -> bool {
+    !cell.contains(['.', 'e', 'E'])
+}
+
+/// Parse the rows of CSV data, cells can be quoted with `"` in which case
+/// they can contain commas, newlines and escaped quotes (`""`).
+fn parse_csv(contents: &str) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(std::mem::take(&mut cell)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            ch => cell.push(ch),
+        }
+    }
+
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+
+    rows
+}
+
+/// Create a named tuple literal from its fields. Tuples with a single field
+/// need a trailing comma.
+fn tuple(fields: &[String]) -> String {
+    match fields {
+        [] => "()".to_string(),
+        [field] => format!("({field},)"),
+        fields => format!("({})", fields.join(", ")),
+    }
+}
+
+/// Check whether the name can be used as an identifier in Hash.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{csv_literal, is_number, parse_csv, to_literal};
+    use crate::{
+        export::{export, DataFormat},
+        value::Value,
+    };
+
+    /// Export the value in the given format and read it back as a literal.
+    fn round_trip(value: &str, format: DataFormat) -> String {
+        let exported = export(&Value::parse(value), format).unwrap();
+        to_literal(&exported, format).unwrap()
+    }
+
+    #[test]
+    fn parses_quoted_cells() {
+        let rows = parse_csv("name,note\r\na,\"x, \"\"y\"\"\"\nb,\"two\nlines\"\n");
+        assert_eq!(rows, [vec!["name", "note"], vec!["a", "x, \"y\""], vec!["b", "two\nlines"]]);
+    }
+
+    #[test]
+    fn only_finite_decimals_are_numbers() {
+        for number in ["0", "-12", "1.5", "2.5e-3", "1E10"] {
+            assert!(is_number(number), "{number}");
+        }
+
+        for other in ["", "inf", "-inf", "NaN", "infinity", "1.", ".5", "+1", "0x10", "1e"] {
+            assert!(!is_number(other), "{other}");
+        }
+    }
+
+    #[test]
+    fn reads_columns_with_non_finite_numbers_as_strings() {
+        assert_eq!(
+            csv_literal("x,y\n1,inf\n2,NaN\n").unwrap(),
+            "[(x = 1, y = \"inf\"), (x = 2, y = \"NaN\")]"
+        );
+    }
+
+    #[test]
+    fn reads_mixed_numeric_columns_as_floats() {
+        assert_eq!(csv_literal("x\n1\n2.5\n").unwrap(), "[(x = 1.0,), (x = 2.5,)]");
+    }
+
+    #[test]
+    fn round_trips_csv() {
+        assert_eq!(
+            round_trip("[(x = 1, y = 1.5), (x = 2, y = 3)]", DataFormat::Csv),
+            "[(x = 1, y = 1.5), (x = 2, y = 3.0)]"
+        );
+        assert_eq!(
+            round_trip("[(x = 1, y = inf), (x = 2, y = NaN)]", DataFormat::Csv),
+            "[(x = 1, y = \"inf\"), (x = 2, y = \"NaN\")]"
+        );
+    }
+
+    #[test]
+    fn round_trips_json() {
+        assert_eq!(
+            round_trip("[(name = \"a\", size = 1.5, big = inf)]", DataFormat::Json),
+            "[(name = \"a\", size = 1.5, big = ())]"
+        );
+    }
+}
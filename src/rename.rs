@@ -0,0 +1,407 @@
+//! Renaming a binding across the sources of the session.
+//!
+//! @@Future: occurrences of the name are found textually, skipping literals,
+//! comments, field accesses and field names. Scopes which rebind the name
+//! (locals, parameters, match bindings and loop variables) are left alone
+//! entirely and reported, rather than guessing which occurrences within them
+//! refer to the binding. This should use the resolver once the compiler
+//! exposes it to the frontend.
+
+use std::{fmt, fs, path::PathBuf};
+
+use crate::{
+    definition::find_definition,
+    error::{InteractiveError, InteractiveResult},
+    loader::LoadQueue,
+    messages::message,
+    scan::{is_ident, scan, statements, Token, TokenKind},
+    session::Session,
+    sources::Source,
+};
+
+/// The changes that renaming makes to a single source.
+#[derive(Debug)]
+pub struct Edit {
+    /// The name of the source.
+    pub source: String,
+
+    /// The contents of the source before the rename.
+    pub original: String,
+
+    /// The contents of the source after the rename.
+    pub renamed: String,
+
+    /// The (1-based) lines of the scopes that were left alone, since they
+    /// rebind the name.
+    pub shadowed: Vec<usize>,
+}
+
+impl Edit {
+    /// Whether the source is an interactive input, rather than a file.
+    pub fn is_input(&self) -> bool {
+        self.source.starts_with("<interactive:")
+    }
+}
+
+impl fmt::Display for Edit {
+    /// Print a preview of the lines that the rename changes.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = self.original.lines().zip(self.renamed.lines()).enumerate();
+
+        for (index, (before, after)) in lines.filter(|(_, (before, after))| before != after) {
+            writeln!(f, "{}:{}", self.source, index + 1)?;
+            writeln!(f, "- {before}")?;
+            writeln!(f, "+ {after}")?;
+        }
+
+        for line in &self.shadowed {
+            writeln!(
+                f,
+                "{}:{line}: left alone, since the name is rebound in this scope",
+                self.source
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Find the changes that renaming `old` to `new` makes to each of the given
+/// sources, skipping the sources which don't mention `old`.
+pub fn plan_rename<'s>(
+    sources: impl Iterator<Item = &'s Source>,
+    old: &str,
+    new: &str,
+) -> Vec<Edit> {
+    sources
+        .filter_map(|source| {
+            let text = &source.contents;
+            let shadowed: Vec<_> = shadowed_scopes(text, old)
+                .into_iter()
+                .map(|(start, _)| text[..start].matches('\n').count() + 1)
+                .collect();
+
+            let renamed = match rename_text(text, old, new) {
+                Some(renamed) => renamed,
+                None if !shadowed.is_empty() => text.clone(),
+                None => return None,
+            };
+
+            Some(Edit { source: source.name.clone(), original: text.clone(), renamed, shadowed })
+        })
+        .collect()
+}
+
+/// Check whether `name` can be used as the name of a binding.
+pub fn is_identifier(name: &str) -> bool {
+    name.starts_with(|ch: char| ch.is_alphabetic() || ch == '_') && name.chars().all(is_ident)
+}
+
+/// Rename the occurrences of the identifier `old` in `text`, returning
+/// `None` if there aren't any. Occurrences within literals and comments,
+/// field accesses such as `x.old`, field names such as `(old: i32)` or
+/// `(old = 1)`, and scopes which rebind `old` are left alone.
+pub fn rename_text(text: &str, old: &str, new: &str) -> Option<String> {
+    let shadowed = shadowed_scopes(text, old);
+    let fields = field_names(text, old);
+    let mut output = String::with_capacity(text.len());
+    let mut renamed = false;
+
+    for token in scan(text) {
+        let is_field = is_other_binding(text, token.start, &fields, &shadowed);
+
+        if token.kind == TokenKind::Ident && token.text == old && !is_field {
+            output.push_str(new);
+            renamed = true;
+        } else {
+            output.push_str(token.text);
+        }
+    }
+
+    renamed.then_some(output)
+}
+
+/// Check whether the occurrence of `name` at the given byte offset of `text`
+/// refers to something other than the top-level binding called `name`, i.e.
+/// it is a field, or it is within a scope which rebinds `name`.
+pub fn refers_elsewhere(text: &str, offset: usize, name: &str) -> bool {
+    is_other_binding(text, offset, &field_names(text, name), &shadowed_scopes(text, name))
+}
+
+/// Check whether the token at the given byte offset of `text` is a field
+/// access, one of the field names or within one of the shadowed scopes.
+fn is_other_binding(
+    text: &str,
+    offset: usize,
+    fields: &[usize],
+    shadowed: &[(usize, usize)],
+) -> bool {
+    is_field_access(text, offset)
+        || fields.contains(&offset)
+        || shadowed.iter().any(|&(start, end)| (start..end).contains(&offset))
+}
+
+/// Check whether the token at the given byte offset of `text` follows a `.`
+/// which accesses a field, such as `x.old` or `t.0.old`, rather than ending a
+/// range such as `0..old` or a number such as `1.`.
+fn is_field_access(text: &str, offset: usize) -> bool {
+    let Some(before) = text[..offset].trim_end().strip_suffix('.') else {
+        return false;
+    };
+
+    if before.ends_with('.') {
+        return false;
+    }
+
+    // A number before the `.` is a literal, unless it is itself the index of
+    // a tuple field such as in `t.0.old`.
+    let word = &before[before.trim_end_matches(is_ident).len()..];
+    let is_number = !word.is_empty() && word.chars().all(|ch| ch.is_ascii_digit());
+    !is_number || before[..before.len() - word.len()].ends_with('.')
+}
+
+/// The tokens of a text other than whitespace and comments, along with the
+/// index of the innermost bracket that each token is within, and the index
+/// of the bracket that closes each opening bracket.
+struct Tokens<'t> {
+    text: &'t str,
+    tokens: Vec<Token<'t>>,
+    enclosing: Vec<Option<usize>>,
+    closing: Vec<Option<usize>>,
+}
+
+impl<'t> Tokens<'t> {
+    fn new(text: &'t str) -> Self {
+        let tokens: Vec<_> = scan(text)
+            .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment))
+            .collect();
+
+        let mut enclosing = vec![None; tokens.len()];
+        let mut closing = vec![None; tokens.len()];
+        let mut open = vec![];
+
+        for (index, token) in tokens.iter().enumerate() {
+            if token.kind == TokenKind::Close {
+                if let Some(opening) = open.pop() {
+                    closing[opening] = Some(index);
+                }
+            }
+
+            enclosing[index] = open.last().copied();
+
+            if token.kind == TokenKind::Open {
+                open.push(index);
+            }
+        }
+
+        Self { text, tokens, enclosing, closing }
+    }
+
+    /// The occurrences of `name` which aren't field accesses, along with the
+    /// text after them.
+    fn occurrences<'s>(&'s self, name: &'s str) -> impl Iterator<Item = (usize, &'t str)> + 's {
+        (0..self.tokens.len()).filter_map(move |index| {
+            let token = &self.tokens[index];
+            let is_access = index > 0 && self.tokens[index - 1].is_punct('.');
+
+            (token.kind == TokenKind::Ident && token.text == name && !is_access)
+                .then(|| (index, self.text[token.end()..].trim_start()))
+        })
+    }
+
+    /// The bracket that the token is directly within, if it is the given
+    /// kind of bracket.
+    fn within(&self, index: usize, bracket: &str) -> Option<usize> {
+        self.enclosing[index].filter(|&open| self.tokens[open].text == bracket)
+    }
+
+    /// Whether the token is a parameter of a lambda (or a binding of a
+    /// pattern), i.e. it is within brackets which are followed by an arrow.
+    fn is_parameter(&self, index: usize) -> bool {
+        let Some(open) = self.within(index, "(") else {
+            return false;
+        };
+
+        let after = self.closing[open]
+            .map_or("", |close| self.text[self.tokens[close].end()..].trim_start());
+
+        after.starts_with("=>") || after.starts_with("->")
+    }
+
+    /// The span of the text within the brackets that start at `open`.
+    fn group(&self, open: usize) -> (usize, usize) {
+        let end = self.closing[open].map_or(self.text.len(), |close| self.tokens[close].end());
+        (self.tokens[open].start, end)
+    }
+
+    /// The span of the innermost brackets around the token, or of its
+    /// statement if it isn't within any.
+    fn around(&self, index: usize) -> (usize, usize) {
+        match self.enclosing[index] {
+            Some(open) => self.group(open),
+            None => {
+                let start = self.tokens[index].start;
+                statements(self.text)
+                    .into_iter()
+                    .find(|&(_, end)| start < end)
+                    .unwrap_or((0, self.text.len()))
+            }
+        }
+    }
+}
+
+/// Find the scopes of `text` which rebind `name`, i.e. which declare a local,
+/// a parameter, a match binding or a loop variable called `name`.
+fn shadowed_scopes(text: &str, name: &str) -> Vec<(usize, usize)> {
+    let tokens = Tokens::new(text);
+    let mut scopes = vec![];
+
+    for (index, after) in tokens.occurrences(name) {
+        let declares = after.starts_with(':') && !after.starts_with("::");
+        let previous = index.checked_sub(1).map(|previous| &tokens.tokens[previous]);
+
+        if tokens.is_parameter(index) {
+            let open = tokens.enclosing[index].unwrap_or(index);
+            let listed = previous.is_some_and(|token| token.text == "(" || token.text == ",");
+            if listed && (declares || after.starts_with(',') || after.starts_with(')')) {
+                scopes.push(tokens.around(open));
+            }
+        } else if previous.is_some_and(|token| token.text == "for") {
+            scopes.push(tokens.around(index));
+        } else if let Some(open) = tokens.enclosing[index] {
+            // Declarations within brackets other than a tuple or a struct are
+            // locals, which shadow the binding for the rest of the block.
+            let is_local = declares && tokens.within(index, "(").is_none();
+            let is_arm = after.starts_with("=>") && tokens.within(index, "{").is_some();
+
+            if is_local || is_arm {
+                scopes.push((tokens.tokens[index].start, tokens.group(open).1));
+            }
+        }
+    }
+
+    scopes
+}
+
+/// Find the (byte offsets of the) occurrences of `name` which are the names
+/// of fields, e.g. `(name: i32)` in a struct or `(name = 1)` in a tuple,
+/// rather than references to a binding.
+fn field_names(text: &str, name: &str) -> Vec<usize> {
+    let tokens = Tokens::new(text);
+
+    tokens
+        .occurrences(name)
+        .filter(|&(index, after)| {
+            let declares = after.starts_with(':') && !after.starts_with("::");
+            let assigns =
+                after.starts_with('=') && !after.starts_with("==") && !after.starts_with("=>");
+
+            tokens.within(index, "(").is_some()
+                && !tokens.is_parameter(index)
+                && (declares || assigns)
+        })
+        .map(|(index, _)| tokens.tokens[index].start)
+        .collect()
+}
+
+/// Rename the binding `old` to `new` across the inputs of the session, and
+/// the loaded files that mention it. A preview of the changes is printed,
+/// and the files are only written to (and loaded again) if `write` is set.
+///
+/// ##Note: the inputs of the session aren't run again, since they may have
+/// side effects. Instead, `new` is bound to the value of `old`, which stays
+/// bound until the session is reset.
+pub fn rename(session: &mut Session, old: &str, new: &str, write: bool) -> InteractiveResult<()> {
+    if !is_identifier(new) {
+        return Err(InteractiveError::InvalidArgument {
+            command: ":rename".to_string(),
+            arg: new.to_string(),
+        });
+    }
+
+    if find_definition(&session.sources, old).is_none() {
+        return Err(InteractiveError::UndefinedName(old.to_string()));
+    }
+
+    if find_definition(&session.sources, new).is_some() {
+        return Err(InteractiveError::NameInUse(new.to_string()));
+    }
+
+    let loaded: Vec<_> =
+        session.loaded.iter().map(|module| module.path.display().to_string()).collect();
+    let sources = session
+        .sources
+        .iter()
+        .filter(|source| source.name.starts_with("<interactive:") || loaded.contains(&source.name));
+    let edits = plan_rename(sources, old, new);
+
+    for edit in &edits {
+        print!("{edit}");
+    }
+
+    let files: Vec<_> = edits.iter().filter(|edit| !edit.is_input()).collect();
+
+    if !write && !files.is_empty() {
+        println!("{}", message!("rename-hint", old = old, new = new, count = files.len()));
+    }
+
+    session.evaluate(&format!("{new} := {old};"))?;
+
+    for edit in edits.iter().filter(|edit| edit.is_input()) {
+        session.sources.replace(&edit.source, &edit.renamed);
+    }
+
+    if write && !files.is_empty() {
+        let mut modules = vec![];
+
+        for edit in files {
+            let path = PathBuf::from(&edit.source);
+            session.config.policy.check_path(&path)?;
+            fs::write(&path, &edit.renamed).map_err(|err| InteractiveError::io(&path, err))?;
+            modules.push(path);
+        }
+
+        session.load(LoadQueue::from_modules(modules));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rename_text;
+
+    #[test]
+    fn renames_references() {
+        assert_eq!(
+            rename_text("n := 1;\nm := n + n;", "n", "count").as_deref(),
+            Some("count := 1;\nm := count + count;")
+        );
+        assert_eq!(rename_text("m := 1;", "n", "count"), None);
+    }
+
+    #[test]
+    fn renames_range_bounds() {
+        assert_eq!(
+            rename_text("for i in 0..n { print(i); }", "n", "count").as_deref(),
+            Some("for i in 0..count { print(i); }")
+        );
+        assert_eq!(rename_text("x := 1. + n;", "n", "m").as_deref(), Some("x := 1. + m;"));
+    }
+
+    #[test]
+    fn skips_fields_literals_and_comments() {
+        assert_eq!(
+            rename_text("p := (n = n); q := p.n + t.0.n; // n\ns := \"n\";", "n", "m").as_deref(),
+            Some("p := (n = m); q := p.n + t.0.n; // n\ns := \"n\";")
+        );
+    }
+
+    #[test]
+    fn skips_scopes_which_rebind_the_name() {
+        assert_eq!(
+            rename_text("f := (n: i32) => n + 1;\ng := f(n);", "n", "m").as_deref(),
+            Some("f := (n: i32) => n + 1;\ng := f(m);")
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Running untrusted transcripts with `hashi sandbox-run <transcript>`, e.g.
+//! to triage a reproduction case that was submitted by a user. A transcript
+//! is either an event log that was written with `--event-log`, or a script of
+//! inputs. Only the top-level inputs are recorded in an event log, so the
+//! inputs that commands such as `:why` run on their behalf aren't replayed
+//! twice. The inputs are run in a fresh session, which doesn't use the
+//! user's configuration, in a child process under resource limits, and with
+//! a policy which only allows the commands that evaluate code or inspect its
+//! results, confines them to the working directory and doesn't let them start
+//! other programs.
+//!
+//! The report is printed as JSON lines, one for each input as it finishes,
+//! followed by a summary of the resources that were used:
+//!
+//! ```text
+//! {"allowed":true,"diagnostics":[],"elapsed_ms":3.1,"event":"input","input":"x := 1;","output":"","status":"ok"}
+//! {"allowed":false,"command":"load","diagnostics":[],"elapsed_ms":0.1,"event":"input","input":":load /etc","output":"...","status":"error"}
+//! {"denied":["load"],"event":"summary","exit_code":0,"failed":1,"inputs":2,"max_resident_bytes":52428800,"reported":2,"signal":null,"system_cpu_ms":8.0,"timed_out":false,"user_cpu_ms":40.0,"wall_ms":45.2}
+//! ```
+//!
+//! The summary is written by the parent process once the child has ended,
+//! with the resources that the child used. If the transcript exceeds a
+//! resource limit then the child is terminated, e.g. by `SIGXCPU` (`signal`
+//! 24 on Linux), or killed once the wall-clock limit has passed (`timed_out`
+//! is `true`), in which case fewer inputs are `reported` than there are, and
+//! the input after the last one that was reported is the one that exceeded
+//! the limit.
+//!
+//! @@Future: evaluate the conditional blocks and expected errors of scripts,
+//! which are currently run as ordinary inputs.
+//!
+//! ##Note: the policy only restricts the commands of the REPL, code that
+//! `import`s a module can still read it from the file system.
+
+use std::{
+    ffi::OsStr,
+    fs,
+    io::{self, BufRead, BufReader},
+    ops::ControlFlow,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use hash_pipeline::interface::CompilerInterface;
+use rustyline::error::ReadlineError;
+use serde_json::{Map, Value};
+
+use crate::{
+    capture::capture_stdout,
+    command::InteractiveCommand,
+    config::Config,
+    diagnostic::Diagnostic,
+    error::{InteractiveError, InteractiveResult},
+    input::LineSource,
+    limits::{children_usage, spawn_limited, termination_signal, wait_timeout},
+    options::Options,
+    policy::Policy,
+    script::Script,
+    session::Session,
+    timeline::Status,
+};
+
+/// The only commands that are allowed whilst running a transcript. Commands
+/// which read or write files, spawn processes, show the sources of the
+/// session or change its options (such as its prelude) aren't allowed, and
+/// neither are commands which are added later until they are reviewed.
+const ALLOWED_COMMANDS: &[&str] = &[
+    "code",
+    "type",
+    "display",
+    "version",
+    "why",
+    "diff",
+    "compare-types",
+    "assert",
+    "fix",
+    "timeline",
+];
+
+/// Read the inputs of a transcript. The transcript is an event log if each of
+/// its lines is an event, and a script otherwise.
+fn transcript_inputs(path: &Path) -> InteractiveResult<Vec<String>> {
+    let contents = fs::read_to_string(path).map_err(|err| InteractiveError::io(path, err))?;
+
+    let events: Option<Vec<Value>> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str::<Value>(line).ok().filter(|event| event.is_object()))
+        .collect();
+
+    if let Some(events) = events.filter(|events| !events.is_empty()) {
+        let inputs = events
+            .iter()
+            .filter(|event| event.get("event").and_then(Value::as_str) == Some("input"))
+            .filter_map(|event| event.get("input")?.as_str().map(str::to_string));
+
+        return Ok(inputs.collect());
+    }
+
+    let mut script = Script::open(path)?;
+    let mut inputs = vec![];
+
+    loop {
+        match script.read_input() {
+            Ok(input) => inputs.push(input),
+            Err(ReadlineError::Eof) => break Ok(inputs),
+            Err(err) => break Err(InteractiveError::Internal(err.to_string())),
+        }
+    }
+}
+
+/// Run the transcript in a child process under the given limits, printing
+/// the report of each input as the child finishes it, followed by the
+/// summary once the child has ended.
+pub fn sandbox_run(
+    transcript: &Path,
+    cpu_limit: Duration,
+    memory_limit: u64,
+    time_limit: Duration,
+) -> InteractiveResult<()> {
+    let internal = |err: io::Error| InteractiveError::Internal(err.to_string());
+    let inputs = transcript_inputs(transcript)?.len();
+    let start = Instant::now();
+
+    let args = [OsStr::new("sandbox-run"), transcript.as_os_str()];
+    let mut child = spawn_limited(args, cpu_limit, Some(memory_limit)).map_err(internal)?;
+
+    // The reports are read on another thread, so that the child can be killed
+    // if it doesn't finish in time.
+    let stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || -> io::Result<_> {
+        let mut reported = 0usize;
+        let mut failed = 0usize;
+        let mut denied: Vec<String> = vec![];
+
+        for line in BufReader::new(stdout).lines() {
+            let line = line?;
+            println!("{line}");
+
+            let Ok(report) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+
+            reported += 1;
+            if report.get("status").and_then(Value::as_str) == Some("error") {
+                failed += 1;
+            }
+
+            if report.get("allowed").and_then(Value::as_bool) == Some(false) {
+                let command = report.get("command").and_then(Value::as_str);
+                if let Some(command) =
+                    command.filter(|command| !denied.iter().any(|d| d == command))
+                {
+                    denied.push(command.to_string());
+                }
+            }
+        }
+
+        Ok((reported, failed, denied))
+    });
+
+    let (status, timed_out) = wait_timeout(&mut child, time_limit).map_err(internal)?;
+    let (reported, failed, denied) =
+        reader.join().expect("the reader of the child panicked").map_err(internal)?;
+    let millis = |time: Duration| time.as_secs_f64() * 1000.0;
+
+    let mut summary = Map::new();
+    summary.insert("event".to_string(), Value::from("summary"));
+    summary.insert("inputs".to_string(), Value::from(inputs));
+    summary.insert("reported".to_string(), Value::from(reported));
+    summary.insert("failed".to_string(), Value::from(failed));
+    summary.insert("denied".to_string(), Value::from(denied));
+    summary.insert("exit_code".to_string(), Value::from(status.code()));
+    summary.insert("signal".to_string(), Value::from(termination_signal(status)));
+    summary.insert("timed_out".to_string(), Value::from(timed_out));
+    summary.insert("wall_ms".to_string(), Value::from(millis(start.elapsed())));
+
+    let usage = children_usage().map_err(internal)?;
+    summary.insert("user_cpu_ms".to_string(), Value::from(millis(usage.user)));
+    summary.insert("system_cpu_ms".to_string(), Value::from(millis(usage.system)));
+    summary.insert("max_resident_bytes".to_string(), Value::from(usage.max_resident));
+
+    println!("{}", Value::Object(summary));
+    Ok(())
+}
+
+/// Run the inputs of the transcript in a sandboxed session, printing the
+/// report as each input finishes. This runs in the child process, once the
+/// limits have been applied.
+pub fn run_transcript(transcript: &Path) -> InteractiveResult<()> {
+    let inputs = transcript_inputs(transcript)?;
+
+    let mut policy = Policy::allow_only(ALLOWED_COMMANDS.iter().map(|name| name.to_string()));
+    policy.deny_absolute_paths = true;
+    policy.deny_processes = true;
+
+    let mut session = Session::new(Config { policy, ..Config::default() }, Options::default());
+    session.dumb = true;
+    session.program_stdin = false;
+
+    let millis = |time: Duration| time.as_secs_f64() * 1000.0;
+
+    for input in &inputs {
+        let mut report = Map::new();
+        report.insert("event".to_string(), Value::from("input"));
+        report.insert("input".to_string(), Value::from(input.as_str()));
+
+        if let Ok(command) = InteractiveCommand::try_from(input.as_str()) {
+            if !matches!(command, InteractiveCommand::Code(_)) {
+                report.insert("command".to_string(), Value::from(command.name()));
+            }
+        }
+
+        // The policy is checked as the input is executed, so that the stages
+        // of pipelines and the commands that preprocessed inputs turn into
+        // are checked as well.
+        session.denied.clear();
+
+        let input_start = Instant::now();
+        let (flow, output) = capture_stdout(|| crate::execute(&mut session, input))
+            .map_err(|err| InteractiveError::Internal(err.to_string()))?;
+        let elapsed = input_start.elapsed();
+
+        let allowed = session.denied.is_empty();
+        if let Some(command) = session.denied.first() {
+            report.insert("command".to_string(), Value::from(*command));
+        }
+
+        let reports = session.compiler.diagnostics();
+        let diagnostics: Vec<_> = reports.iter().map(Diagnostic::from_report).collect();
+
+        let status = match (allowed, flow) {
+            (true, ControlFlow::Continue(status)) => status,
+            _ => Status::Error,
+        };
+
+        report.insert("allowed".to_string(), Value::from(allowed));
+        report.insert("status".to_string(), Value::from(status.name()));
+        report.insert("elapsed_ms".to_string(), Value::from(millis(elapsed)));
+        report.insert("output".to_string(), Value::from(output));
+        report.insert(
+            "diagnostics".to_string(),
+            Value::Array(diagnostics.iter().map(|d| Value::Object(d.to_json())).collect()),
+        );
+
+        println!("{}", Value::Object(report));
+    }
+
+    Ok(())
+}
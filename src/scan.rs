@@ -0,0 +1,174 @@
+//! Scanning the text of Hash sources into coarse tokens. The frontend
+//! searches the sources textually in several places (splitting statements,
+//! renaming bindings, splitting pipelines and completing paths), and they
+//! all need to skip over literals and comments, and to know how deeply
+//! brackets are nested. This is the one place that does that.
+//!
+//! @@Future: use the lexer of the compiler once it is exposed to the
+//! frontend.
+
+/// The kind of a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// An identifier, keyword or number.
+    Ident,
+
+    /// A string or character literal, including its quotes. A literal which
+    /// isn't terminated extends to the end of the text.
+    Literal { terminated: bool },
+
+    /// A line or block comment.
+    Comment,
+
+    /// An opening bracket, i.e. `(`, `[` or `{`.
+    Open,
+
+    /// A closing bracket.
+    Close,
+
+    /// A run of whitespace.
+    Whitespace,
+
+    /// Any other character.
+    Punct,
+}
+
+/// A token of the text.
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'t> {
+    /// The kind of the token.
+    pub kind: TokenKind,
+
+    /// The text of the token.
+    pub text: &'t str,
+
+    /// The byte offset of the token in the text.
+    pub start: usize,
+
+    /// The number of brackets that the token is nested within. A pair of
+    /// brackets has the depth of the text around it.
+    pub depth: usize,
+}
+
+impl Token<'_> {
+    /// The byte offset of the end of the token.
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    /// Whether the token is the given punctuation character.
+    pub fn is_punct(&self, ch: char) -> bool {
+        self.kind == TokenKind::Punct && self.text.starts_with(ch)
+    }
+}
+
+/// An iterator over the tokens of some text.
+#[derive(Debug, Clone)]
+pub struct Scanner<'t> {
+    text: &'t str,
+    position: usize,
+    depth: usize,
+}
+
+/// Scan the given text into tokens.
+pub fn scan(text: &str) -> Scanner<'_> {
+    Scanner { text, position: 0, depth: 0 }
+}
+
+impl<'t> Iterator for Scanner<'t> {
+    type Item = Token<'t>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.text[self.position..];
+        let ch = rest.chars().next()?;
+        let mut depth = self.depth;
+
+        let (kind, len) = match ch {
+            '"' | '\'' => {
+                let (len, terminated) = literal_len(rest);
+                (TokenKind::Literal { terminated }, len)
+            }
+            '/' if rest.starts_with("//") => {
+                (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+            }
+            '/' if rest.starts_with("/*") => {
+                (TokenKind::Comment, rest[2..].find("*/").map_or(rest.len(), |i| i + 4))
+            }
+            '(' | '[' | '{' => {
+                self.depth += 1;
+                (TokenKind::Open, 1)
+            }
+            ')' | ']' | '}' => {
+                self.depth = self.depth.saturating_sub(1);
+                depth = self.depth;
+                (TokenKind::Close, 1)
+            }
+            ch if is_ident(ch) => {
+                (TokenKind::Ident, rest.find(|ch| !is_ident(ch)).unwrap_or(rest.len()))
+            }
+            ch if ch.is_whitespace() => (
+                TokenKind::Whitespace,
+                rest.find(|ch: char| !ch.is_whitespace()).unwrap_or(rest.len()),
+            ),
+            ch => (TokenKind::Punct, ch.len_utf8()),
+        };
+
+        let token = Token { kind, text: &rest[..len], start: self.position, depth };
+        self.position += len;
+        Some(token)
+    }
+}
+
+/// Whether the character can be part of an identifier.
+pub fn is_ident(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Get the length of the string or character literal at the start of
+/// `text` including its quotes, and whether it is terminated. Character
+/// literals end at the end of the line.
+fn literal_len(text: &str) -> (usize, bool) {
+    let quote = text.chars().next().unwrap_or('"');
+    let mut escaped = false;
+
+    for (index, ch) in text.char_indices().skip(1) {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ch if ch == quote => return (index + 1, true),
+            '\n' if quote == '\'' => return (index, false),
+            _ => {}
+        }
+    }
+
+    (text.len(), false)
+}
+
+/// Split `text` into its statements, which are separated by `;`s that
+/// aren't nested within brackets, literals or comments. Comments before a
+/// statement aren't part of it.
+pub fn statements(text: &str) -> Vec<(usize, usize)> {
+    let mut statements = vec![];
+    let mut start = 0;
+
+    for token in scan(text) {
+        match token.kind {
+            TokenKind::Comment
+                if token.depth == 0 && text[start..token.start].trim().is_empty() =>
+            {
+                start = token.end();
+            }
+            TokenKind::Punct if token.depth == 0 && token.text == ";" => {
+                statements.push((start, token.start));
+                start = token.end();
+            }
+            _ => {}
+        }
+    }
+
+    if !text[start..].trim().is_empty() {
+        statements.push((start, text.len()));
+    }
+
+    statements
+}
@@ -0,0 +1,117 @@
+//! Scratch buffers, which are temporary files tied to the session that are
+//! evaluated again at the next prompt whenever they are saved. This is a
+//! middle ground between entering inputs at the prompt and working on a
+//! project.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{self, Command, Stdio},
+    time::SystemTime,
+};
+
+use crate::{
+    config::modified_time,
+    error::{InteractiveError, InteractiveResult},
+    policy::Policy,
+    session::Session,
+};
+
+/// The environment variable that holds the command which opens scratch
+/// buffers. The editor is started in the background, so it should open its
+/// own window rather than use the terminal.
+pub const EDITOR_ENV_VAR: &str = "HASHI_EDITOR";
+
+/// A scratch buffer of the session.
+#[derive(Debug)]
+pub struct Scratch {
+    /// The name of the buffer.
+    pub name: String,
+
+    /// The path of the file that holds the buffer.
+    pub path: PathBuf,
+
+    /// When the buffer was last evaluated.
+    modified: Option<SystemTime>,
+}
+
+impl Scratch {
+    /// Open the scratch buffer with the given name, creating its file if it
+    /// doesn't exist yet. The buffers of each session are kept in their own
+    /// temporary directory.
+    pub fn open(name: &str) -> InteractiveResult<Self> {
+        let dir = env::temp_dir().join(format!("hashi-scratch-{}", process::id()));
+        fs::create_dir_all(&dir).map_err(|err| InteractiveError::io(&dir, err))?;
+
+        let path = dir.join(format!("{name}.hash"));
+        if !path.exists() {
+            fs::write(&path, "").map_err(|err| InteractiveError::io(&path, err))?;
+        }
+
+        Ok(Self { name: name.to_string(), modified: modified_time(&path), path })
+    }
+
+    /// Check whether the buffer has been saved since it was last evaluated,
+    /// marking it as evaluated if it has.
+    pub fn take_change(&mut self) -> bool {
+        let modified = modified_time(&self.path);
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+
+    /// Open the buffer in the editor given by [`EDITOR_ENV_VAR`], returning
+    /// whether an editor is configured. The editor is only started if the
+    /// policy allows it.
+    pub fn open_in_editor(&self, policy: &Policy) -> InteractiveResult<bool> {
+        let Ok(editor) = env::var(EDITOR_ENV_VAR) else {
+            return Ok(false);
+        };
+
+        let mut words = editor.split_whitespace();
+        let Some(program) = words.next() else {
+            return Ok(false);
+        };
+
+        policy.check_process(program)?;
+        Command::new(program)
+            .args(words)
+            .arg(&self.path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| InteractiveError::io(program, err))?;
+
+        Ok(true)
+    }
+
+    /// Remove the file of the buffer, along with the directory of the
+    /// session's buffers once it is empty.
+    pub fn remove(&self) {
+        let _ = fs::remove_file(&self.path);
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+/// Evaluate the scratch buffer of the session if it has been saved since it
+/// was last evaluated, reporting its diagnostics. This is checked before
+/// each prompt, rather than in the background, so that the output doesn't
+/// interfere with the line editor whilst the user is typing.
+pub fn evaluate_changes(session: &mut Session) {
+    let Some(scratch) = session.scratch.as_mut() else {
+        return;
+    };
+
+    if !scratch.take_change() {
+        return;
+    }
+
+    let (name, path) = (scratch.name.clone(), scratch.path.clone());
+    println!("Scratch buffer `{name}` changed, evaluating it");
+
+    let result = session.run_file(&path);
+    println!("Evaluated `{name}` with {} errors and {} warnings", result.errors, result.warnings);
+}
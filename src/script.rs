@@ -0,0 +1,443 @@
+//! Running scripts of inputs with `:source`. A script is read in the same way
+//! as the inputs at the prompt, so heredocs and `\` continuations can be used
+//! in scripts too. Scripts can also contain conditional blocks, so that a
+//! single script can drive sessions across platforms and build
+//! configurations, e.g.
+//!
+//! ```text
+//! :if windows
+//! sep := "\\";
+//! :else
+//! sep := "/";
+//! :end
+//! ```
+//!
+//! The supported conditions are `windows`, `unix`, `linux`, `macos`,
+//! `debug`, `release`, `feature(name)` and `not(condition)`. Features are
+//! enabled by listing them in the `HASHI_FEATURES` environment variable,
+//! separated by commas.
+//!
+//! Scripts can assert that an input fails by preceding it with
+//! `:expect-error`, optionally followed by the error code and a quoted
+//! substring of the message that the input is expected to fail with, which
+//! may appear anywhere in the message:
+//!
+//! ```text
+//! :expect-error 0001 "cannot find"
+//! x := undefined_name;
+//! ```
+//!
+//! The script is stopped if the input doesn't fail as expected, and the
+//! unmet expectation counts as a failed assertion, so that the session exits
+//! with a failure status.
+
+use std::{
+    env, fmt, fs,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+};
+
+use hash_pipeline::interface::CompilerInterface;
+use rustyline::error::ReadlineError;
+
+use crate::{
+    diagnostic::Diagnostic,
+    error::{InteractiveError, InteractiveResult},
+    input::LineSource,
+    session::Session,
+};
+
+/// The environment variable which lists the enabled features.
+pub const FEATURES_ENV_VAR: &str = "HASHI_FEATURES";
+
+/// The lines of a script that is being run.
+pub struct Script {
+    /// The path of the script.
+    path: PathBuf,
+
+    /// The lines of the script.
+    lines: Vec<String>,
+
+    /// The number of lines that have been read.
+    position: usize,
+}
+
+impl Script {
+    /// Read the script at the given path.
+    pub fn open(path: &Path) -> InteractiveResult<Self> {
+        let contents = fs::read_to_string(path).map_err(|err| InteractiveError::io(path, err))?;
+        let lines = contents.lines().map(str::to_string).collect();
+
+        Ok(Self { path: path.to_path_buf(), lines, position: 0 })
+    }
+
+    /// Create an error at the given line of the script.
+    fn error(&self, line: usize, message: impl Into<String>) -> InteractiveError {
+        InteractiveError::InvalidScript {
+            path: self.path.display().to_string(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl LineSource for Script {
+    fn readline(&mut self, _: &str) -> Result<String, ReadlineError> {
+        let line = self.lines.get(self.position).cloned().ok_or(ReadlineError::Eof)?;
+        self.position += 1;
+        Ok(line)
+    }
+}
+
+/// A condition of an `:if` block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// Running on Windows.
+    Windows,
+
+    /// Running on a unix platform.
+    Unix,
+
+    /// Running on Linux.
+    Linux,
+
+    /// Running on macOS.
+    MacOs,
+
+    /// Running a debug build of the REPL.
+    Debug,
+
+    /// Running a release build of the REPL.
+    Release,
+
+    /// The named feature is enabled.
+    Feature(String),
+
+    /// The inner condition doesn't hold.
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    /// Parse a condition, returning `None` if it isn't understood.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+
+        if let Some(inner) = argument(text, "not") {
+            return Some(Condition::Not(Box::new(Condition::parse(inner)?)));
+        }
+
+        if let Some(name) = argument(text, "feature") {
+            let name = name.trim();
+            return (!name.is_empty()).then(|| Condition::Feature(name.to_string()));
+        }
+
+        match text {
+            "windows" => Some(Condition::Windows),
+            "unix" => Some(Condition::Unix),
+            "linux" => Some(Condition::Linux),
+            "macos" => Some(Condition::MacOs),
+            "debug" => Some(Condition::Debug),
+            "release" => Some(Condition::Release),
+            _ => None,
+        }
+    }
+
+    /// Check whether the condition holds for the current session.
+    pub fn holds(&self) -> bool {
+        match self {
+            Condition::Windows => cfg!(windows),
+            Condition::Unix => cfg!(unix),
+            Condition::Linux => cfg!(target_os = "linux"),
+            Condition::MacOs => cfg!(target_os = "macos"),
+            Condition::Debug => cfg!(debug_assertions),
+            Condition::Release => !cfg!(debug_assertions),
+            Condition::Feature(name) => env::var(FEATURES_ENV_VAR)
+                .is_ok_and(|features| features.split(',').any(|feature| feature.trim() == name)),
+            Condition::Not(inner) => !inner.holds(),
+        }
+    }
+}
+
+/// Get the argument of a condition of the form `name(argument)`.
+fn argument<'t>(text: &'t str, name: &str) -> Option<&'t str> {
+    text.strip_prefix(name)?.trim_start().strip_prefix('(')?.strip_suffix(')')
+}
+
+/// An expectation that the next input of a script fails.
+#[derive(Debug, Clone, Default)]
+pub struct Expectation {
+    /// The error code that the input is expected to fail with.
+    pub code: Option<String>,
+
+    /// A substring of the message that the input is expected to fail with.
+    pub message: Option<String>,
+}
+
+impl Expectation {
+    /// Parse the arguments of an `:expect-error`, which are an optional error
+    /// code followed by an optional quoted message. Returns `None` if the
+    /// arguments aren't understood.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (code, message) = match text.find(char::is_whitespace) {
+            _ if text.starts_with('"') => (None, text),
+            Some(index) => (Some(&text[..index]), text[index..].trim_start()),
+            None if text.is_empty() => (None, ""),
+            None => (Some(text), ""),
+        };
+
+        let message = match message {
+            "" => None,
+            message => {
+                let unquoted = message.strip_prefix('"')?.strip_suffix('"')?;
+                Some(unquoted.to_string())
+            }
+        };
+
+        Some(Self { code: code.map(str::to_string), message })
+    }
+
+    /// Check whether one of the given errors satisfies the expectation. The
+    /// code of the error must be the expected code, and its message must
+    /// contain the expected message anywhere within it, rather than being
+    /// the whole message or starting with it.
+    pub fn is_met_by(&self, errors: &[Diagnostic]) -> bool {
+        errors.iter().any(|error| {
+            self.code.as_ref().is_none_or(|code| error.code.as_ref() == Some(code))
+                && self.message.as_ref().is_none_or(|message| error.message.contains(message))
+        })
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an error")?;
+
+        if let Some(code) = &self.code {
+            write!(f, " with code `{code}`")?;
+        }
+
+        if let Some(message) = &self.message {
+            write!(f, " containing \"{message}\"")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An `:if` block that is open in a script.
+struct Block {
+    /// The line of the script that the block starts on.
+    line: usize,
+
+    /// Whether the block that encloses this one is being run.
+    enclosing: bool,
+
+    /// Whether the condition of the block holds.
+    holds: bool,
+
+    /// Whether the `:else` branch of the block has been reached.
+    in_else: bool,
+}
+
+impl Block {
+    /// Whether the inputs in the current branch of the block are run.
+    fn is_active(&self) -> bool {
+        self.enclosing && self.holds != self.in_else
+    }
+}
+
+/// Run each of the inputs of the script at the given path in the session.
+/// The script is stopped at the first malformed conditional block, and
+/// breaks at an input that quits the session. A script which is already
+/// being run, i.e. which sources itself directly or through other scripts,
+/// isn't run again.
+pub fn run_script(session: &mut Session, path: &Path) -> InteractiveResult<ControlFlow<()>> {
+    let script = Script::open(path)?;
+    let canonical = path.canonicalize().map_err(|err| InteractiveError::io(path, err))?;
+
+    if session.sourcing.contains(&canonical) {
+        let chain: Vec<_> = session
+            .sourcing
+            .iter()
+            .chain([&canonical])
+            .map(|path| format!("`{}`", path.display()))
+            .collect();
+
+        return Err(InteractiveError::SourceCycle {
+            path: path.display().to_string(),
+            chain: chain.join(" -> "),
+        });
+    }
+
+    session.sourcing.push(canonical);
+    let result = run_inputs(session, script);
+    session.sourcing.pop();
+    result
+}
+
+/// Run each of the inputs of the script.
+fn run_inputs(session: &mut Session, mut script: Script) -> InteractiveResult<ControlFlow<()>> {
+    let mut blocks: Vec<Block> = vec![];
+    let mut expectation: Option<(usize, Expectation)> = None;
+
+    loop {
+        let line = script.position + 1;
+        let input = match script.read_input() {
+            Ok(input) => input,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(InteractiveError::Internal(err.to_string())),
+        };
+
+        let active = blocks.last().is_none_or(Block::is_active);
+        let trimmed = input.trim();
+
+        if let Some(condition) = trimmed.strip_prefix(":if ") {
+            let condition = Condition::parse(condition).ok_or_else(|| {
+                script.error(line, format!("unknown condition `{}`", condition.trim()))
+            })?;
+
+            blocks.push(Block {
+                line,
+                enclosing: active,
+                holds: condition.holds(),
+                in_else: false,
+            });
+        } else if trimmed == ":else" {
+            match blocks.last_mut() {
+                Some(block) if !block.in_else => block.in_else = true,
+                Some(_) => return Err(script.error(line, "`:else` is repeated in the block")),
+                None => return Err(script.error(line, "`:else` is outside of an `:if` block")),
+            }
+        } else if trimmed == ":end" {
+            if blocks.pop().is_none() {
+                return Err(script.error(line, "`:end` is outside of an `:if` block"));
+            }
+        } else if !active {
+            // Inputs in branches that aren't taken are skipped entirely.
+        } else if let Some(arguments) = trimmed
+            .strip_prefix(":expect-error")
+            .filter(|arguments| arguments.is_empty() || arguments.starts_with(char::is_whitespace))
+        {
+            let parsed = Expectation::parse(arguments)
+                .ok_or_else(|| script.error(line, "expected an error code and a quoted message"))?;
+
+            expectation = Some((line, parsed));
+        } else {
+            session.log_input(&input);
+            if crate::execute(session, &input).is_break() {
+                return Ok(ControlFlow::Break(()));
+            }
+
+            if let Some((line, expected)) = expectation.take() {
+                let errors: Vec<_> = session
+                    .compiler
+                    .diagnostics()
+                    .iter()
+                    .filter(|report| report.is_error())
+                    .map(Diagnostic::from_report)
+                    .collect();
+
+                if !expected.is_met_by(&errors) {
+                    session.failed_assertions += 1;
+
+                    let found = match errors.as_slice() {
+                        [] => "it succeeded".to_string(),
+                        errors => {
+                            let messages: Vec<_> = errors
+                                .iter()
+                                .map(|error| match &error.code {
+                                    Some(code) => format!("[{code}] {}", error.message),
+                                    None => error.message.clone(),
+                                })
+                                .collect();
+
+                            format!("it failed with: {}", messages.join("; "))
+                        }
+                    };
+
+                    return Err(script.error(line, format!("expected {expected}, but {found}")));
+                }
+            }
+        }
+    }
+
+    if let Some((line, _)) = expectation {
+        return Err(script.error(line, "`:expect-error` is not followed by an input"));
+    }
+
+    match blocks.last() {
+        Some(block) => Err(script.error(block.line, "`:if` block is not closed with `:end`")),
+        None => Ok(ControlFlow::Continue(())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::{run_script, Condition, Expectation};
+    use crate::{config::Config, diagnostic::Diagnostic, options::Options, session::Session};
+
+    #[test]
+    fn unmet_expectation_fails_the_session() {
+        let path = env::temp_dir().join(format!("hashi-expect-error-{}.hash", process::id()));
+        fs::write(&path, ":expect-error\nx := 1;\n").unwrap();
+
+        let mut session = Session::new(Config::default(), Options::default());
+        let result = run_script(&mut session, &path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(session.exit_code(), 1);
+    }
+
+    #[test]
+    fn parses_conditions() {
+        assert_eq!(Condition::parse(" linux "), Some(Condition::Linux));
+        assert_eq!(Condition::parse("feature( gc )"), Some(Condition::Feature("gc".to_string())));
+        assert_eq!(
+            Condition::parse("not (not(release))"),
+            Some(Condition::Not(Box::new(Condition::Not(Box::new(Condition::Release)))))
+        );
+
+        for other in ["", "bsd", "feature()", "not(bsd)", "not linux", "feature(gc"] {
+            assert_eq!(Condition::parse(other), None, "{other}");
+        }
+    }
+
+    #[test]
+    fn negates_conditions() {
+        assert_eq!(Condition::parse("not(windows)").unwrap().holds(), !cfg!(windows));
+        assert_eq!(Condition::parse("debug").unwrap().holds(), cfg!(debug_assertions));
+    }
+
+    #[test]
+    fn parses_expectations() {
+        let parse = |text| Expectation::parse(text).map(|e| (e.code, e.message));
+        let some = |text: &str| Some(text.to_string());
+
+        assert_eq!(parse(""), Some((None, None)));
+        assert_eq!(parse("0012"), Some((some("0012"), None)));
+        assert_eq!(parse("\"not found\""), Some((None, some("not found"))));
+        assert_eq!(parse(" 0012  \"x y\" "), Some((some("0012"), some("x y"))));
+        assert_eq!(parse("0012 unquoted"), None);
+        assert_eq!(parse("\"unterminated"), None);
+    }
+
+    #[test]
+    fn matches_errors_by_code_and_message() {
+        let errors = [Diagnostic {
+            code: Some("0012".to_string()),
+            message: "the name `x` was not found".to_string(),
+            spans: vec![],
+        }];
+
+        let met = |text| Expectation::parse(text).unwrap().is_met_by(&errors);
+        assert!(met(""));
+        assert!(met("0012 \"not found\""));
+        assert!(!met("0013"));
+        assert!(!met("\"not defined\""));
+        assert!(!Expectation::default().is_met_by(&[]));
+    }
+}
@@ -0,0 +1,564 @@
+//! The state of an interactive session, this holds the compiler that inputs
+//! are run with, along with any state of the REPL that persists between
+//! inputs.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use hash_driver::{driver::Driver, Compiler, CompilerBuilder};
+use hash_pipeline::{
+    interface::CompilerInterface,
+    settings::{CompilerSettings, CompilerStageKind},
+};
+use hash_reporting::report::Report;
+
+use crate::{
+    ansi::{incomplete_escape, sanitise, strip_ansi},
+    capture::{capture_stdout, stream_stdout, without_stdin},
+    config::{modified_time, Config},
+    diagnostic::Diagnostic,
+    dump::DumpDir,
+    error::{InteractiveError, InteractiveResult},
+    events::{Event, EventLog},
+    flamegraph::FlameGraph,
+    journal::Journal,
+    loader::{LoadQueue, LoadedModule, ModuleResult},
+    memo::QueryCache,
+    options::{Options, Prelude},
+    scratch::Scratch,
+    signals,
+    sink::{Channel, OutputSink, TerminalSink},
+    sources::Sources,
+    standby::Standby,
+    stats::{stats_path, Stats},
+    status::SessionStatus,
+    suggest::{Fix, ModuleIndex},
+    timeline::Timeline,
+};
+
+/// An input that failed to evaluate, and the errors it produced.
+#[derive(Debug, Clone)]
+pub struct LastError {
+    /// The input that failed.
+    pub input: String,
+
+    /// The errors that were emitted when evaluating the input.
+    pub errors: Vec<Diagnostic>,
+}
+
+/// An interactive session.
+pub struct Session {
+    /// The compiler that is used to run the inputs of the session.
+    pub compiler: Driver<Compiler>,
+
+    /// The configuration of the session.
+    pub config: Config,
+
+    /// The sources that are known to the session.
+    pub sources: Sources,
+
+    /// The options of the REPL.
+    pub options: Options,
+
+    /// The flamegraph recorder, if the session is currently being profiled.
+    pub flamegraph: Option<FlameGraph>,
+
+    /// The directory that the artifacts of each input are written to, if
+    /// any.
+    pub dump_dir: Option<DumpDir>,
+
+    /// The last input that failed to evaluate, if any.
+    pub last_error: Option<LastError>,
+
+    /// The fix that was suggested for the last input that failed, if any.
+    pub pending_fix: Option<Fix>,
+
+    /// The names that the modules in the current directory declare, which
+    /// imports are suggested from.
+    pub module_index: ModuleIndex,
+
+    /// The modules that remain from a `:load` which was interrupted.
+    pub pending_load: Option<LoadQueue>,
+
+    /// The modules that the session has attempted to load, in the order that
+    /// they were loaded.
+    pub loaded: Vec<LoadedModule>,
+
+    /// The number of assertions that have failed in the session, including
+    /// the errors that scripts expected but which didn't occur.
+    pub failed_assertions: usize,
+
+    /// The names of the commands that the policy has denied, including those
+    /// that were run by pipelines and other commands.
+    pub denied: Vec<&'static str>,
+
+    /// The scripts that are being run with `:source`, outermost first, so
+    /// that a script which sources itself is caught.
+    pub sourcing: Vec<PathBuf>,
+
+    /// The journal that inputs are recorded in, if any.
+    pub journal: Option<Journal>,
+
+    /// Whether the configuration was reloaded since it was last applied to
+    /// the line editor.
+    pub config_changed: bool,
+
+    /// Whether evaluated programs can read from the standard input, this
+    /// can't be changed with `:set` since it is a safety measure.
+    pub program_stdin: bool,
+
+    /// The usage statistics of the session.
+    pub stats: Stats,
+
+    /// The log that the events of the session are written to, if any.
+    pub event_log: Option<EventLog>,
+
+    /// The sink that the output of the session is written to.
+    pub sink: Box<dyn OutputSink>,
+
+    /// The inputs that have been entered at the prompt.
+    pub timeline: Timeline,
+
+    /// The full output of the last evaluation, if it was truncated.
+    pub truncated_output: Option<String>,
+
+    /// The memoised results of queries such as `:t`.
+    pub queries: QueryCache,
+
+    /// The compiler that is being built in the background for the next reset,
+    /// if any.
+    pub standby: Option<Standby>,
+
+    /// The output of the previous stage of the pipeline that is being run, if
+    /// any.
+    pub piped: Option<String>,
+
+    /// The scratch buffer of the session, which is evaluated whenever it is
+    /// saved.
+    pub scratch: Option<Scratch>,
+
+    /// The live status of the session.
+    pub status: Arc<SessionStatus>,
+
+    /// Whether the session is running in a dumb terminal, in which case no
+    /// escape sequences or cursor movements should be emitted.
+    pub dumb: bool,
+}
+
+/// Create a compiler for an interactive session which starts with the given
+/// prelude. Custom preludes are loaded separately by the session.
+pub fn build_compiler(prelude: &Prelude) -> Driver<Compiler> {
+    let mut settings = CompilerSettings::new();
+
+    // Configure the settings to only run up to the typechecking stage, and
+    // consequently to evaluate the TIR, as this is what the interpreter
+    // currently supports.
+    settings.set_stage(CompilerStageKind::Analysis);
+    settings.semantic_settings.eval_tir = true;
+    settings.skip_prelude = !matches!(prelude, Prelude::Default);
+
+    CompilerBuilder::build_with_settings(settings)
+}
+
+/// Run an input in the compiler. Unless `program_stdin` is set, the standard
+/// input is connected to the null device whilst the input runs, so that a
+/// program which reads it sees the end of the input rather than taking over
+/// the terminal.
+pub fn run_input(compiler: &mut Driver<Compiler>, program_stdin: bool, input: String) {
+    match program_stdin {
+        true => compiler.run_interactive(input),
+        false => without_stdin(|| compiler.run_interactive(input)),
+    }
+}
+
+impl Session {
+    /// Create a new session with the given configuration and options.
+    pub fn new(config: Config, options: Options) -> Self {
+        let mut session = Self {
+            compiler: build_compiler(&options.prelude),
+            config,
+            sources: Sources::default(),
+            options,
+            flamegraph: None,
+            dump_dir: None,
+            last_error: None,
+            pending_fix: None,
+            module_index: ModuleIndex::default(),
+            pending_load: None,
+            loaded: vec![],
+            failed_assertions: 0,
+            denied: vec![],
+            sourcing: vec![],
+            journal: None,
+            config_changed: false,
+            program_stdin: true,
+            event_log: None,
+            stats: Stats::new(),
+            sink: Box::new(TerminalSink),
+            timeline: Timeline::default(),
+            truncated_output: None,
+            queries: QueryCache::default(),
+            standby: None,
+            piped: None,
+            scratch: None,
+            status: Arc::new(SessionStatus::new()),
+            dumb: false,
+        };
+
+        session.load_prelude();
+        session
+    }
+
+    /// Reset the session to a fresh compiler, discarding all of the
+    /// definitions that have been made in the session. The standby compiler
+    /// is used if there is one, and another one is started for the next
+    /// reset.
+    pub fn reset(&mut self) {
+        let prelude = &self.options.prelude;
+        self.compiler = self
+            .standby
+            .take()
+            .and_then(|standby| standby.take(prelude))
+            .unwrap_or_else(|| build_compiler(prelude));
+
+        self.prepare_standby();
+        self.queries.advance();
+        self.sources = Sources::default();
+        self.last_error = None;
+        self.pending_fix = None;
+        self.pending_load = None;
+        self.loaded.clear();
+        self.load_prelude();
+    }
+
+    /// Start building a standby compiler in the background, which the next
+    /// `:reset` swaps to.
+    pub fn prepare_standby(&mut self) {
+        self.standby = Some(Standby::spawn(&self.options.prelude));
+    }
+
+    /// Load all of the modules in the queue, stopping between modules if the
+    /// user interrupts the load, in which case the remaining modules are kept
+    /// so that the load can be continued.
+    pub fn load(&mut self, mut queue: LoadQueue) {
+        let finished = signals::interruptible(|| {
+            while let Some(path) = queue.pending.front().cloned() {
+                if signals::take_interrupt() {
+                    return false;
+                }
+
+                println!("[{}/{}] Loading `{}`", queue.position(), queue.total, path.display());
+
+                let result = self.load_file(&path);
+                queue.results.push(result);
+                queue.pending.pop_front();
+            }
+
+            true
+        });
+
+        if finished {
+            queue.print_summary();
+            self.pending_load = None;
+        } else {
+            println!(
+                "Loading interrupted with {} modules remaining, use `:load --continue` to resume",
+                queue.pending.len()
+            );
+            self.pending_load = Some(queue);
+        }
+    }
+
+    /// Load the modules that have changed since they were loaded into the
+    /// session again. Each module is parsed before it is loaded, and modules
+    /// that fail to parse are skipped so that their previous definitions are
+    /// kept.
+    ///
+    /// ##Note: modules that parse but fail to type check also keep their
+    /// previous definitions, since a failing input isn't bound into the
+    /// interactive scope.
+    pub fn reload(&mut self) {
+        let stale: Vec<_> =
+            self.loaded.iter().filter(|module| module.is_stale()).map(|m| m.path.clone()).collect();
+
+        if stale.is_empty() {
+            let failed = self.loaded.iter().filter(|module| module.errors > 0).count();
+            match failed {
+                0 => println!("No loaded modules have changed"),
+                failed => println!(
+                    "No loaded modules have changed, {failed} of them failed when they were last \
+                     loaded"
+                ),
+            }
+
+            return;
+        }
+
+        let mut modules = vec![];
+
+        for path in stale {
+            match self.check_file(&path) {
+                Ok(true) => modules.push(path),
+                Ok(false) => {
+                    println!("Skipped `{}`, keeping its previous definitions", path.display())
+                }
+                Err(err) => eprintln!("{}", Report::from(err)),
+            }
+        }
+
+        if !modules.is_empty() {
+            self.load(LoadQueue::from_modules(modules));
+        }
+    }
+
+    /// Check whether a file parses, printing its diagnostics if it doesn't.
+    fn check_file(&mut self, path: &Path) -> InteractiveResult<bool> {
+        let contents = fs::read_to_string(path).map_err(|err| InteractiveError::io(path, err))?;
+
+        let settings = self.compiler.settings_mut();
+        settings.ast_settings_mut().dump = false;
+        settings.set_stage(CompilerStageKind::Parse);
+
+        self.compiler.diagnostics_mut().clear();
+        self.compiler.run_interactive(contents);
+        self.compiler.settings_mut().set_stage(CompilerStageKind::Analysis);
+
+        let failed = self.compiler.diagnostics().iter().any(|report| report.is_error());
+        self.compiler.diagnostics_mut().clear();
+        Ok(!failed)
+    }
+
+    /// Load a single file into the session.
+    fn load_file(&mut self, path: &Path) -> ModuleResult {
+        let result = self.run_file(path);
+
+        // Modules which fail are remembered too, so that they are loaded
+        // again by `:reload` once they have been fixed.
+        self.loaded.retain(|module| module.path != path);
+        self.loaded.push(LoadedModule::new(path, result.errors));
+
+        result
+    }
+
+    /// Run the contents of a file in the session, without recording it as a
+    /// loaded module.
+    pub fn run_file(&mut self, path: &Path) -> ModuleResult {
+        self.queries.advance();
+
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                eprintln!("{}", Report::from(InteractiveError::io(path, err)));
+                return ModuleResult { path: path.to_path_buf(), errors: 1, warnings: 0 };
+            }
+        };
+
+        let settings = self.compiler.settings_mut();
+        settings.ast_settings_mut().dump = false;
+        settings.set_stage(CompilerStageKind::Analysis);
+
+        self.compiler.diagnostics_mut().clear();
+        self.status.begin(&format!(":load {}", path.display()));
+        self.run_to_sink(contents.clone());
+
+        let diagnostics = self.compiler.diagnostics();
+        let errors = diagnostics.iter().filter(|report| report.is_error()).count();
+        let warnings = diagnostics.len() - errors;
+
+        self.status.end(errors > 0);
+        self.sources.add_file(path, &contents);
+
+        ModuleResult { path: path.to_path_buf(), errors, warnings }
+    }
+
+    /// Read the configuration file again, replacing the configuration of the
+    /// session. If the configuration is invalid, then the previous one is
+    /// kept, and isn't reported again until the file changes once more.
+    pub fn reload_config(&mut self) -> InteractiveResult<()> {
+        match Config::load(self.config.path.as_deref()) {
+            Ok(config) => {
+                self.config = config;
+                self.config_changed = true;
+                Ok(())
+            }
+            Err(err) => {
+                // The file that failed is remembered, including a default
+                // configuration file which has been created since the
+                // session started, so that it is only read again once it
+                // changes.
+                let path = self.config.path.clone().or_else(Config::default_path);
+                self.config.modified = path.as_deref().and_then(modified_time);
+                self.config.path = path;
+                Err(err)
+            }
+        }
+    }
+
+    /// Evaluate an expression on behalf of a command, returning what it
+    /// printed. If the expression fails, its diagnostics are printed.
+    pub fn evaluate(&mut self, expr: &str) -> InteractiveResult<String> {
+        self.queries.advance();
+
+        let settings = self.compiler.settings_mut();
+        settings.ast_settings_mut().dump = false;
+        settings.set_stage(CompilerStageKind::Analysis);
+
+        self.compiler.diagnostics_mut().clear();
+        self.status.begin(expr);
+
+        let (compiler, program_stdin) = (&mut self.compiler, self.program_stdin);
+        let ((), output) = capture_stdout(|| run_input(compiler, program_stdin, expr.to_string()))
+            .map_err(|err| InteractiveError::Internal(err.to_string()))?;
+
+        let failed = self.compiler.diagnostics().iter().any(|report| report.is_error());
+        self.status.end(failed);
+
+        if failed {
+            let output = sanitise(&output, self.options.program_escapes);
+            self.sink.write(Channel::Program, &output);
+            return Err(InteractiveError::EvaluationFailed(expr.to_string()));
+        }
+
+        Ok(strip_ansi(&output).trim().to_string())
+    }
+
+    /// Run an input in the session, streaming what it prints to the sink once
+    /// it has been sanitised according to `program.escapes`.
+    pub fn run_to_sink(&mut self, input: String) {
+        let (compiler, sink) = (&mut self.compiler, &mut self.sink);
+        let (program_stdin, escapes) = (self.program_stdin, self.options.program_escapes);
+        let mut pending = String::new();
+
+        let streamed = stream_stdout(
+            || run_input(compiler, program_stdin, input),
+            |chunk, terminal| {
+                // An escape sequence which is split between chunks is held
+                // back until the rest of it arrives.
+                pending.push_str(chunk);
+                let index = incomplete_escape(&pending).unwrap_or(pending.len());
+                sink.write_to(Channel::Program, &sanitise(&pending[..index], escapes), terminal);
+                pending.drain(..index);
+            },
+        );
+
+        if let Err(err) = streamed {
+            eprintln!("failed to capture output: {err}");
+        }
+
+        self.sink.write(Channel::Program, &sanitise(&pending, escapes));
+    }
+
+    /// Load the custom prelude of the session, if one is specified.
+    fn load_prelude(&mut self) {
+        let Prelude::File(path) = self.options.prelude.clone() else {
+            return;
+        };
+
+        if let Err(err) = self.config.policy.check_path(&path) {
+            return eprintln!("{}", Report::from(err));
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => {
+                self.run_to_sink(contents.clone());
+                self.sources.add_file(&path, &contents);
+            }
+            Err(err) => eprintln!("{}", Report::from(InteractiveError::io(&path, err))),
+        }
+    }
+
+    /// Stop recording the flamegraph of the session (if any) and write
+    /// it to its file.
+    pub fn stop_flamegraph(&mut self) {
+        if let Some(flamegraph) = self.flamegraph.take() {
+            match flamegraph.write() {
+                Ok(()) => println!("Wrote flamegraph to `{}`", flamegraph.path().display()),
+                Err(err) => {
+                    eprintln!("{}", Report::from(InteractiveError::io(flamegraph.path(), err)))
+                }
+            }
+        }
+    }
+
+    /// Record an input in the journal of the session before it is evaluated.
+    pub fn record_input(&mut self, input: &str) {
+        if let Some(journal) = &mut self.journal {
+            if let Err(err) = journal.record(input) {
+                eprintln!("failed to write the input to the journal: {err}");
+            }
+        }
+    }
+
+    /// Record an input that was entered at the top level, i.e. at the prompt,
+    /// in a script or by a client, in the event log of the session. Inputs
+    /// that commands run on behalf of another input aren't logged, so that
+    /// the log can be replayed.
+    pub fn log_input(&mut self, input: &str) {
+        if let Some(log) = &mut self.event_log {
+            log.record(Event::Input(input));
+        }
+    }
+
+    /// Get the usage statistics of all of the sessions that have been
+    /// recorded, including this one.
+    pub fn all_stats(&self) -> InteractiveResult<Stats> {
+        let path = stats_path().ok_or_else(|| {
+            InteractiveError::Internal("couldn't find the data directory".to_string())
+        })?;
+
+        let mut stats = Stats::load(&path).map_err(|message| InteractiveError::InvalidData {
+            path: path.display().to_string(),
+            message,
+        })?;
+        stats.merge(&self.stats);
+        Ok(stats)
+    }
+
+    /// Add the statistics of the session to those of previous sessions, if
+    /// they are enabled.
+    fn save_stats(&mut self) {
+        if !self.config.stats {
+            return;
+        }
+
+        let Some(path) = stats_path() else {
+            return;
+        };
+
+        // The statistics are only saved once, even if the session is finished
+        // more than once.
+        let result = self
+            .all_stats()
+            .and_then(|stats| stats.save(&path).map_err(|err| InteractiveError::io(&path, err)));
+        self.stats = Stats::default();
+
+        if let Err(err) = result {
+            eprintln!("{}", Report::from(err));
+        }
+    }
+
+    /// Get the code that the process should exit with when the session ends,
+    /// which is non-zero if any assertions failed.
+    pub fn exit_code(&self) -> i32 {
+        i32::from(self.failed_assertions > 0)
+    }
+
+    /// Finish the session, this should be called before the interactive mode
+    /// exits so that any state that is being recorded is written out.
+    pub fn finish(&mut self) {
+        self.stop_flamegraph();
+        self.save_stats();
+
+        if let Some(scratch) = self.scratch.take() {
+            scratch.remove();
+        }
+
+        // The session ended cleanly, so there is nothing to recover.
+        if let Some(journal) = self.journal.take() {
+            let _ = journal.remove();
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Handling of signals. When the interactive mode receives `SIGTERM`,
+//! `SIGHUP` or `SIGINT`, it waits for any in-flight evaluation to finish,
+//! finishes the session so that any recorded state is written out, restores
+//! the terminal and then exits. A second signal exits straight away, e.g. if
+//! the evaluation doesn't finish. On `SIGUSR1`, the status of the session is
+//! dumped to standard error (or to the file given by `--status-file`), which
+//! can be used to diagnose a REPL that appears to be hung.
+//!
+//! If the REPL is running an interruptible operation (see [`interruptible`])
+//! then `SIGINT` asks the operation to stop at the next opportunity instead.
+//! Otherwise, if the prompt is interactive and an input is being evaluated,
+//! then `SIGINT` kills the evaluation by restarting the REPL in place of the
+//! process with `--recover`, since the compiler can't be stopped part way
+//! through an evaluation. The journal of the session is kept, so the inputs
+//! before the interrupted one can be replayed to return to where the session
+//! was. `SIGINT` only shuts the session down (exiting with 130) when no input
+//! is being evaluated, or when the input isn't a terminal.
+//!
+//! The signals are blocked on all threads and are instead received by a
+//! dedicated thread using `sigwait`, which means that the handling code is
+//! not restricted to async-signal-safe operations.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::{session::Session, status::SessionStatus};
+
+/// Whether an interruptible operation is currently running.
+static INTERRUPTIBLE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the running interruptible operation has been asked to stop.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Run an operation during which `SIGINT` requests the operation to stop,
+/// rather than terminating the process. The operation should check
+/// [`take_interrupt`] at points where it can safely stop.
+pub fn interruptible<T>(operation: impl FnOnce() -> T) -> T {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    INTERRUPTIBLE.store(true, Ordering::SeqCst);
+    let result = operation();
+    INTERRUPTIBLE.store(false, Ordering::SeqCst);
+    result
+}
+
+/// Check whether the running interruptible operation has been asked to
+/// stop, clearing the request.
+pub fn take_interrupt() -> bool {
+    INTERRUPTED.swap(false, Ordering::SeqCst)
+}
+
+/// Block the signals that are handled by the interactive mode on the current
+/// thread. This must be called before any other threads are spawned so that
+/// they inherit the signal mask, and the signals are only delivered to the
+/// thread that is started with [`spawn_handler`].
+#[cfg(unix)]
+pub fn block() {
+    imp::save_terminal();
+    imp::save_streams();
+
+    // ##Safety: the signal set is initialised before it is used.
+    unsafe {
+        let set = imp::handled_signals();
+        libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut());
+    }
+}
+
+/// Start the thread that handles the blocked signals for the given session,
+/// status dumps are written to `status_file` if it is specified.
+#[cfg(unix)]
+pub fn spawn_handler(
+    session: Arc<Mutex<Session>>,
+    status: Arc<SessionStatus>,
+    status_file: Option<PathBuf>,
+) {
+    use std::io::{self, IsTerminal};
+
+    std::thread::spawn(move || {
+        let signal = loop {
+            match imp::wait() {
+                libc::SIGUSR1 => dump_status(&status, status_file.as_ref()),
+                libc::SIGINT if INTERRUPTIBLE.load(Ordering::SeqCst) => {
+                    INTERRUPTED.store(true, Ordering::SeqCst);
+                }
+                libc::SIGINT if io::stdin().is_terminal() && is_evaluating(&session) => {
+                    imp::restore_streams();
+                    imp::restore_terminal();
+
+                    println!();
+                    eprintln!("Interrupted the evaluation, restarting the session...");
+                    let err = imp::restart();
+                    eprintln!("failed to restart the session: {err}");
+                    std::process::exit(130);
+                }
+                signal => break signal,
+            }
+        };
+
+        // Being interrupted exits with the conventional status of a process
+        // that was terminated by `SIGINT`.
+        let code = (signal == libc::SIGINT).then_some(130);
+
+        imp::restore_terminal();
+
+        println!();
+        eprintln!("Received {}, shutting down...", imp::signal_name(signal));
+
+        // Wait for any in-flight evaluation to finish before the session
+        // is finished, a second signal will abort the wait.
+        std::thread::spawn(move || {
+            while imp::wait() == libc::SIGUSR1 {}
+            eprintln!("Shutdown interrupted, exiting immediately");
+            std::process::exit(code.unwrap_or(1));
+        });
+
+        let mut session = session.lock().unwrap_or_else(|err| err.into_inner());
+        session.finish();
+        crate::goodbye(code.unwrap_or_else(|| session.exit_code()));
+    });
+}
+
+/// Check whether an input is being evaluated, i.e. whether the main loop
+/// holds the lock on the session.
+#[cfg(unix)]
+fn is_evaluating(session: &Mutex<Session>) -> bool {
+    use std::sync::TryLockError;
+
+    matches!(session.try_lock(), Err(TryLockError::WouldBlock))
+}
+
+/// Signals are not handled on non-unix platforms.
+#[cfg(not(unix))]
+pub fn block() {}
+
+/// Write the status of the session to the given file, or to standard error.
+#[cfg(unix)]
+fn dump_status(status: &SessionStatus, file: Option<&PathBuf>) {
+    use std::{fs::OpenOptions, io::Write};
+
+    let Some(path) = file else {
+        eprintln!("{status}");
+        return;
+    };
+
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{status}"));
+
+    if let Err(err) = result {
+        eprintln!("failed to write status to `{}`: {err}", path.display());
+    }
+}
+
+/// Signals are not handled on non-unix platforms.
+#[cfg(not(unix))]
+pub fn spawn_handler(_: Arc<Mutex<Session>>, _: Arc<SessionStatus>, _: Option<PathBuf>) {}
+
+#[cfg(unix)]
+mod imp {
+    use std::{
+        env, io, mem::MaybeUninit, os::unix::process::CommandExt, process::Command, sync::OnceLock,
+    };
+
+    /// The state of the terminal when the interactive mode was started.
+    struct Terminal(libc::termios);
+
+    // ##Safety: the saved terminal state is plain data.
+    unsafe impl Send for Terminal {}
+    unsafe impl Sync for Terminal {}
+
+    static TERMINAL: OnceLock<Option<Terminal>> = OnceLock::new();
+
+    /// Duplicates of the standard streams from when the interactive mode was
+    /// started, which are closed if the process is replaced.
+    static STREAMS: OnceLock<[libc::c_int; 3]> = OnceLock::new();
+
+    /// The standard streams, in the order of their file descriptors.
+    const STANDARD_STREAMS: [libc::c_int; 3] =
+        [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO];
+
+    /// Get the set of signals that are handled.
+    pub(super) unsafe fn handled_signals() -> libc::sigset_t {
+        let mut set = MaybeUninit::uninit();
+        libc::sigemptyset(set.as_mut_ptr());
+        libc::sigaddset(set.as_mut_ptr(), libc::SIGTERM);
+        libc::sigaddset(set.as_mut_ptr(), libc::SIGHUP);
+        libc::sigaddset(set.as_mut_ptr(), libc::SIGUSR1);
+        libc::sigaddset(set.as_mut_ptr(), libc::SIGINT);
+        set.assume_init()
+    }
+
+    /// Wait for one of the handled signals to be received.
+    pub(super) fn wait() -> libc::c_int {
+        let mut signal = 0;
+
+        // ##Safety: the signal set is initialised, and the signals are blocked.
+        unsafe {
+            let set = handled_signals();
+            libc::sigwait(&set, &mut signal);
+        }
+
+        signal
+    }
+
+    /// Get the name of a handled signal.
+    pub(super) fn signal_name(signal: libc::c_int) -> &'static str {
+        match signal {
+            libc::SIGHUP => "SIGHUP",
+            libc::SIGINT => "SIGINT",
+            _ => "SIGTERM",
+        }
+    }
+
+    /// Restart the interactive mode in place of the current process, with the
+    /// same arguments along with `--recover`. This only returns if the
+    /// process couldn't be replaced.
+    pub(super) fn restart() -> io::Error {
+        let args: Vec<_> = env::args_os().skip(1).filter(|arg| arg != "--recover").collect();
+
+        match env::current_exe() {
+            Ok(exe) => Command::new(exe).args(args).arg("--recover").exec(),
+            Err(err) => err,
+        }
+    }
+
+    /// Save the standard streams, so that they can be restored if they are
+    /// redirected (e.g. to capture the output of an evaluation) when the
+    /// process is replaced.
+    pub(super) fn save_streams() {
+        STREAMS.get_or_init(|| {
+            // ##Safety: duplicating a file descriptor has no other effects.
+            STANDARD_STREAMS.map(|fd| unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) })
+        });
+    }
+
+    /// Point the standard streams back at those that were saved.
+    pub(super) fn restore_streams() {
+        let Some(saved) = STREAMS.get() else {
+            return;
+        };
+
+        for (saved, fd) in saved.iter().zip(STANDARD_STREAMS) {
+            if *saved >= 0 {
+                // ##Safety: the saved file descriptors are never closed.
+                unsafe { libc::dup2(*saved, fd) };
+            }
+        }
+    }
+
+    /// Save the state of the terminal so that it can be restored if the line
+    /// editor is interrupted whilst the terminal is in raw mode.
+    pub(super) fn save_terminal() {
+        TERMINAL.get_or_init(|| {
+            let mut termios = MaybeUninit::uninit();
+
+            // ##Safety: `termios` is only read if `tcgetattr` succeeds.
+            unsafe {
+                (libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) == 0)
+                    .then(|| Terminal(termios.assume_init()))
+            }
+        });
+    }
+
+    /// Restore the saved terminal state.
+    pub(super) fn restore_terminal() {
+        if let Some(Some(Terminal(termios))) = TERMINAL.get() {
+            // ##Safety: the saved state was produced by `tcgetattr`.
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, termios);
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! The sinks that the output of the session is written to. The output of the
+//! evaluated program is kept separate from the output of the REPL itself
+//! (e.g. timings and warnings), so that frontends which embed the session
+//! can present them differently, or so that the program output can be
+//! written to a file with `--program-output`.
+//!
+//! @@Future: route the rest of the messages of the REPL through the sink,
+//! they are currently printed directly.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+/// The kind of output that is written to a sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Output of the evaluated program, including the values of inputs.
+    Program,
+
+    /// Output of the REPL itself.
+    Repl,
+}
+
+/// A destination for the output of the session.
+pub trait OutputSink: Send {
+    /// Write some output to the sink, anything that is shown on the terminal
+    /// is written to `terminal`. This is the original standard output while
+    /// the output of the program is being streamed.
+    fn write_to(&mut self, channel: Channel, text: &str, terminal: &mut dyn Write);
+
+    /// Write some output to the sink.
+    fn write(&mut self, channel: Channel, text: &str) {
+        self.write_to(channel, text, &mut io::stdout());
+    }
+
+    /// Whether the output of the program is shown on the terminal, in which
+    /// case it is truncated to the `print.limit`.
+    fn shows_program(&self) -> bool {
+        true
+    }
+}
+
+/// Writes all output to the terminal.
+#[derive(Debug, Default)]
+pub struct TerminalSink;
+
+impl OutputSink for TerminalSink {
+    fn write_to(&mut self, _: Channel, text: &str, terminal: &mut dyn Write) {
+        // Partial lines are flushed, since the program might be prompting.
+        let _ = terminal.write_all(text.as_bytes()).and_then(|_| terminal.flush());
+    }
+}
+
+/// Writes the output of the program to a file, and the output of the REPL
+/// to the terminal.
+#[derive(Debug)]
+pub struct FileSink {
+    file: File,
+}
+
+impl FileSink {
+    /// Create the sink, truncating the file at the given path.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self { file: File::create(path)? })
+    }
+}
+
+impl OutputSink for FileSink {
+    fn write_to(&mut self, channel: Channel, text: &str, terminal: &mut dyn Write) {
+        match channel {
+            Channel::Program => {
+                if let Err(err) = self.file.write_all(text.as_bytes()) {
+                    eprintln!("failed to write the program output: {err}");
+                }
+            }
+            Channel::Repl => {
+                let _ = terminal.write_all(text.as_bytes()).and_then(|_| terminal.flush());
+            }
+        }
+    }
+
+    fn shows_program(&self) -> bool {
+        false
+    }
+}
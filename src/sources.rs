@@ -0,0 +1,145 @@
+//! The sources that are known to the session, these are the inputs that
+//! have been entered interactively along with any files that have been
+//! loaded into the session, including a custom prelude.
+//!
+//! @@Future: include the standard prelude once the compiler exposes the
+//! sources of its workspace to the frontend.
+
+use std::path::Path;
+
+use regex::Regex;
+
+/// A single source of the session.
+#[derive(Debug, Clone)]
+pub struct Source {
+    /// The name of the source, which is used when referring to locations
+    /// within it.
+    pub name: String,
+
+    /// The contents of the source.
+    pub contents: String,
+}
+
+/// A run of lines of a source around the lines which matched a search
+/// pattern.
+#[derive(Debug)]
+pub struct SourceMatch<'s> {
+    /// The source the match is in.
+    pub source: &'s Source,
+
+    /// The lines of the match, with their (1-based) line numbers and whether
+    /// they matched rather than being context around a match.
+    pub lines: Vec<MatchedLine<'s>>,
+}
+
+/// A line of the text that was searched.
+#[derive(Debug, Clone, Copy)]
+pub struct MatchedLine<'t> {
+    /// The (1-based) line number of the line.
+    pub line: usize,
+
+    /// The contents of the line.
+    pub text: &'t str,
+
+    /// Whether the line matched the pattern, rather than being context.
+    pub matched: bool,
+}
+
+/// Find the lines of `text` which match the given `pattern`, along with
+/// `context` lines either side of each match. Matches whose context
+/// overlaps are merged into one group of lines.
+pub fn grep_lines<'t>(text: &'t str, pattern: &Regex, context: usize) -> Vec<Vec<MatchedLine<'t>>> {
+    let lines: Vec<_> = text.lines().collect();
+    let mut groups: Vec<Vec<MatchedLine<'t>>> = vec![];
+    let mut end = 0;
+
+    for (index, line) in lines.iter().enumerate() {
+        if !pattern.is_match(line) {
+            continue;
+        }
+
+        let start = index.saturating_sub(context);
+        if groups.is_empty() || start > end {
+            groups.push(vec![]);
+        }
+
+        let group = groups.last_mut().unwrap();
+        let last = (index + context + 1).min(lines.len());
+
+        for (line, &text) in lines.iter().enumerate().take(last).skip(start.max(end)) {
+            group.push(MatchedLine { line: line + 1, text, matched: false });
+        }
+
+        for matched in group.iter_mut().filter(|matched| matched.line == index + 1) {
+            matched.matched = true;
+        }
+
+        end = last;
+    }
+
+    groups
+}
+
+/// All of the sources of a session.
+#[derive(Debug, Default)]
+pub struct Sources {
+    sources: Vec<Source>,
+
+    /// The number of interactive inputs that have been added.
+    inputs: usize,
+}
+
+impl Sources {
+    /// Add an input that was entered interactively.
+    pub fn add_input(&mut self, contents: &str) {
+        self.inputs += 1;
+        self.sources.push(Source {
+            name: format!("<interactive:{}>", self.inputs),
+            contents: contents.to_string(),
+        });
+    }
+
+    /// Add the contents of a file, replacing any previous version of the file.
+    pub fn add_file(&mut self, path: &Path, contents: &str) {
+        let name = path.display().to_string();
+        self.sources.retain(|source| source.name != name);
+        self.sources.push(Source { name, contents: contents.to_string() });
+    }
+
+    /// Replace the contents of the source with the given name.
+    pub fn replace(&mut self, name: &str, contents: &str) {
+        if let Some(source) = self.sources.iter_mut().find(|source| source.name == name) {
+            source.contents = contents.to_string();
+        }
+    }
+
+    /// Get the source with the given name, or the last interactive input if
+    /// no name is given.
+    pub fn get(&self, name: Option<&str>) -> Option<&Source> {
+        match name {
+            Some(name) => self.sources.iter().rev().find(|source| source.name == name),
+            None => {
+                self.sources.iter().rev().find(|source| source.name.starts_with("<interactive:"))
+            }
+        }
+    }
+
+    /// Iterate over all of the sources.
+    pub fn iter(&self) -> impl Iterator<Item = &Source> {
+        self.sources.iter()
+    }
+
+    /// Find all of the lines in the sources that match the given `pattern`,
+    /// along with `context` lines either side of each match.
+    pub fn grep<'s>(
+        &'s self,
+        pattern: &'s Regex,
+        context: usize,
+    ) -> impl Iterator<Item = SourceMatch<'s>> + 's {
+        self.iter().flat_map(move |source| {
+            grep_lines(&source.contents, pattern, context)
+                .into_iter()
+                .map(move |lines| SourceMatch { source, lines })
+        })
+    }
+}
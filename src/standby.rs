@@ -0,0 +1,50 @@
+//! A standby compiler, which is built in the background so that `:reset`
+//! can swap to it rather than waiting for a new compiler to be built.
+
+use std::thread::{self, JoinHandle};
+
+use hash_driver::{driver::Driver, Compiler};
+use hash_pipeline::interface::CompilerInterface;
+
+use crate::{options::Prelude, session::build_compiler};
+
+/// A compiler that is being built in the background.
+#[derive(Debug)]
+pub struct Standby {
+    /// The prelude that the compiler is built with.
+    prelude: Prelude,
+
+    /// The thread that is building the compiler.
+    compiler: JoinHandle<Driver<Compiler>>,
+}
+
+impl Standby {
+    /// Start building a compiler with the given prelude.
+    pub fn spawn(prelude: &Prelude) -> Self {
+        let compiler = thread::spawn({
+            let prelude = prelude.clone();
+            move || {
+                let mut compiler = build_compiler(&prelude);
+
+                // Running an empty input makes the compiler load the prelude,
+                // so that the first input after the swap doesn't pay for it.
+                compiler.run_interactive(String::new());
+                compiler.diagnostics_mut().clear();
+                compiler
+            }
+        });
+
+        Self { prelude: prelude.clone(), compiler }
+    }
+
+    /// Take the compiler, waiting for it to be built if it isn't ready yet.
+    /// Returns `None` if it was built with a different prelude, or if
+    /// building it failed.
+    pub fn take(self, prelude: &Prelude) -> Option<Driver<Compiler>> {
+        if self.prelude != *prelude {
+            return None;
+        }
+
+        self.compiler.join().ok()
+    }
+}
@@ -0,0 +1,164 @@
+//! Usage statistics of the REPL, i.e. the commands that are used, the error
+//! codes that are hit and the time that is spent evaluating inputs. The
+//! statistics of the current session are always available with `:stats`.
+//! Recording them across sessions is opt-in, by enabling them in the
+//! configuration:
+//!
+//! ```toml
+//! [stats]
+//! enabled = true
+//! ```
+//!
+//! in which case they are accumulated in `hashi/stats.toml` within the
+//! user's data directory, and shown with `:stats all`. The statistics never
+//! leave the machine.
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use toml_edit::{Document, Item};
+
+/// Get the path of the file that the statistics are accumulated in.
+pub fn stats_path() -> Option<PathBuf> {
+    dirs_next::data_dir().map(|dir| dir.join("hashi").join("stats.toml"))
+}
+
+/// The usage statistics of one or more sessions.
+#[derive(Debug, Default, Clone)]
+pub struct Stats {
+    /// The number of sessions.
+    pub sessions: u64,
+
+    /// The number of times that each command was used.
+    pub commands: BTreeMap<String, u64>,
+
+    /// The number of times that each error code was hit.
+    pub errors: BTreeMap<String, u64>,
+
+    /// The number of inputs that were evaluated.
+    pub evaluations: u64,
+
+    /// The total time that was spent evaluating inputs.
+    pub evaluation_time: Duration,
+}
+
+impl Stats {
+    /// Create the statistics of a new session.
+    pub fn new() -> Self {
+        Self { sessions: 1, ..Self::default() }
+    }
+
+    /// Record that a command was used.
+    pub fn record_command(&mut self, name: &str) {
+        *self.commands.entry(name.to_string()).or_default() += 1;
+    }
+
+    /// Record that an error with the given code was hit.
+    pub fn record_error(&mut self, code: &str) {
+        *self.errors.entry(code.to_string()).or_default() += 1;
+    }
+
+    /// Record that an input was evaluated in the given time.
+    pub fn record_evaluation(&mut self, elapsed: Duration) {
+        self.evaluations += 1;
+        self.evaluation_time += elapsed;
+    }
+
+    /// Add the statistics of other sessions to these.
+    pub fn merge(&mut self, other: &Stats) {
+        self.sessions += other.sessions;
+        self.evaluations += other.evaluations;
+        self.evaluation_time += other.evaluation_time;
+
+        for (name, count) in &other.commands {
+            *self.commands.entry(name.clone()).or_default() += count;
+        }
+
+        for (code, count) in &other.errors {
+            *self.errors.entry(code.clone()).or_default() += count;
+        }
+    }
+
+    /// Load the accumulated statistics from the given file, a missing file
+    /// has no statistics.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let document = contents.parse::<Document>().map_err(|err| err.to_string())?;
+        let count = |item: Option<&Item>| item.and_then(Item::as_integer).unwrap_or(0) as u64;
+
+        let counts = |key: &str| -> BTreeMap<String, u64> {
+            document
+                .get(key)
+                .and_then(Item::as_table)
+                .map(|table| table.iter().map(|(k, v)| (k.to_string(), count(Some(v)))).collect())
+                .unwrap_or_default()
+        };
+
+        Ok(Self {
+            sessions: count(document.get("sessions")),
+            commands: counts("commands"),
+            errors: counts("errors"),
+            evaluations: count(document.get("evaluations")),
+            evaluation_time: Duration::from_millis(count(document.get("evaluation-ms"))),
+        })
+    }
+
+    /// Write the statistics to the given file.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut contents = format!(
+            "sessions = {}\nevaluations = {}\nevaluation-ms = {}\n",
+            self.sessions,
+            self.evaluations,
+            self.evaluation_time.as_millis()
+        );
+
+        for (key, counts) in [("commands", &self.commands), ("errors", &self.errors)] {
+            contents.push_str(&format!("\n[{key}]\n"));
+            for (name, count) in counts {
+                contents.push_str(&format!("\"{name}\" = {count}\n"));
+            }
+        }
+
+        fs::write(path, contents)
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let average = match self.evaluations {
+            0 => Duration::ZERO,
+            n => self.evaluation_time / n as u32,
+        };
+
+        writeln!(f, "sessions:    {}", self.sessions)?;
+        writeln!(f, "evaluations: {} (average {average:.2?})", self.evaluations)?;
+
+        for (title, counts) in [("commands", &self.commands), ("errors", &self.errors)] {
+            let mut counts: Vec<_> = counts.iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+            writeln!(f, "{title}:")?;
+            if counts.is_empty() {
+                writeln!(f, "  (none)")?;
+            }
+
+            for (name, count) in counts {
+                writeln!(f, "  {name:<16} {count}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
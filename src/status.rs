@@ -0,0 +1,88 @@
+//! The live status of the session, this is shared with the signal handler
+//! so that it can be inspected while an evaluation is in progress.
+
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The status of a session.
+#[derive(Debug)]
+pub struct SessionStatus {
+    inner: Mutex<StatusInner>,
+}
+
+#[derive(Debug)]
+struct StatusInner {
+    /// When the session was started.
+    started: Instant,
+
+    /// The input that is currently being evaluated, and when its evaluation
+    /// began.
+    current: Option<(String, Instant)>,
+
+    /// The number of inputs that have been evaluated.
+    evaluated: usize,
+
+    /// The number of inputs that failed to evaluate.
+    failed: usize,
+
+    /// The total time that has been spent evaluating inputs.
+    evaluation_time: Duration,
+}
+
+impl SessionStatus {
+    /// Create the status for a session that starts now.
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(StatusInner {
+                started: Instant::now(),
+                current: None,
+                evaluated: 0,
+                failed: 0,
+                evaluation_time: Duration::ZERO,
+            }),
+        }
+    }
+
+    /// Record that the evaluation of the given input has begun.
+    pub fn begin(&self, input: &str) {
+        self.inner.lock().unwrap().current = Some((input.to_string(), Instant::now()));
+    }
+
+    /// Record that the evaluation of the current input has completed.
+    pub fn end(&self, failed: bool) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if let Some((_, start)) = inner.current.take() {
+            inner.evaluation_time += start.elapsed();
+        }
+
+        inner.evaluated += 1;
+        inner.failed += usize::from(failed);
+    }
+}
+
+impl fmt::Display for SessionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.lock().unwrap();
+
+        writeln!(f, "hashi session status")?;
+        writeln!(f, "  uptime:          {:.2?}", inner.started.elapsed())?;
+        writeln!(f, "  inputs:          {} ({} failed)", inner.evaluated, inner.failed)?;
+        writeln!(f, "  evaluation time: {:.2?}", inner.evaluation_time)?;
+
+        match &inner.current {
+            Some((input, start)) => {
+                writeln!(f, "  status:          evaluating for {:.2?}", start.elapsed())?;
+                writeln!(f, "  current input:")?;
+                for line in input.lines() {
+                    writeln!(f, "    {line}")?;
+                }
+                Ok(())
+            }
+            None => writeln!(f, "  status:          idle"),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Suggestions for fixing inputs that fail because they refer to names which
+//! are not in scope, but which are declared by a module that could be
+//! imported.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs,
+    path::Path,
+    time::SystemTime,
+};
+
+use crate::{
+    config::modified_time, definition::top_level_names, diagnostic::Diagnostic,
+    loader::SOURCE_EXTENSION,
+};
+
+/// A suggestion to import a name from a module.
+#[derive(Debug, Clone)]
+pub struct ImportSuggestion {
+    /// The module that declares the name, as it is written in an import.
+    pub module: String,
+
+    /// The name that should be imported.
+    pub name: String,
+}
+
+impl ImportSuggestion {
+    /// Create the declaration which imports the name into scope.
+    pub fn to_import(&self) -> String {
+        format!("{{ {} }} := import(\"{}\");", self.name, self.module)
+    }
+}
+
+/// A fix for an input that failed, which can be applied with `:fix`.
+#[derive(Debug, Clone)]
+pub struct Fix {
+    /// The imports that should be added.
+    pub imports: Vec<ImportSuggestion>,
+
+    /// The input that failed, which is retried after the imports are added.
+    pub input: String,
+}
+
+/// The error code that the compiler emits for names which aren't in scope.
+const UNRESOLVED_SYMBOL: &str = "0002";
+
+/// The top-level names that are declared by the Hash sources in the current
+/// directory, which suggestions are made from. A module is only read again
+/// once it has changed.
+#[derive(Debug, Default)]
+pub struct ModuleIndex {
+    /// The names that each module declares, along with the time that the
+    /// module was modified when it was read.
+    modules: BTreeMap<String, (Option<SystemTime>, HashSet<String>)>,
+}
+
+impl ModuleIndex {
+    /// Suggest imports for the names which the given errors report aren't in
+    /// scope.
+    pub fn suggest_imports(&mut self, errors: &[Diagnostic]) -> Vec<ImportSuggestion> {
+        let names: BTreeSet<_> = errors.iter().filter_map(unresolved_name).collect();
+
+        if names.is_empty() {
+            return vec![];
+        }
+
+        self.refresh(Path::new("."));
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let (module, _) =
+                    self.modules.iter().find(|(_, (_, declared))| declared.contains(name))?;
+                Some(ImportSuggestion { module: module.clone(), name: name.to_string() })
+            })
+            .collect()
+    }
+
+    /// Bring the index up to date with the modules in the given directory,
+    /// reading the ones which are new or have changed since they were read.
+    fn refresh(&mut self, dir: &Path) {
+        let paths: Vec<_> = fs::read_dir(dir)
+            .map(|entries| entries.filter_map(|entry| Some(entry.ok()?.path())).collect())
+            .unwrap_or_default();
+
+        let mut modules = BTreeMap::new();
+
+        for path in paths {
+            if path.extension().is_none_or(|ext| ext != SOURCE_EXTENSION) {
+                continue;
+            }
+
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            let modified = modified_time(&path);
+            let module = match self.modules.remove(name) {
+                Some(module) if modified.is_some() && module.0 == modified => module,
+                _ => match fs::read_to_string(&path) {
+                    Ok(contents) => (modified, top_level_names(&contents).into_iter().collect()),
+                    Err(_) => continue,
+                },
+            };
+
+            modules.insert(name.to_string(), module);
+        }
+
+        self.modules = modules;
+    }
+}
+
+/// Get the name that the error reports isn't in scope, which is the first
+/// identifier that is quoted with backticks in its message. Other errors
+/// which quote names, e.g. for mismatched types, aren't suggested for.
+fn unresolved_name(error: &Diagnostic) -> Option<&str> {
+    if error.code.as_deref() != Some(UNRESOLVED_SYMBOL) {
+        return None;
+    }
+
+    error.message.split('`').nth(1).filter(|name| {
+        let mut chars = name.chars();
+        chars.next().is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+            && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+    })
+}
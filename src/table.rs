@@ -0,0 +1,86 @@
+//! Rendering lists of records as aligned tables, e.g.
+//!
+//! ```text
+//! name  | age
+//! ------+----
+//! "Ann" | 31
+//! "Bob" | 4
+//! ```
+//!
+//! Only lists whose elements are all records with the same fields, in the
+//! same order, are rendered as tables.
+
+use std::borrow::Cow;
+
+use crate::{ansi::strip_ansi, value::Value};
+
+/// Render the value as a table, if it is a list of records with identical
+/// fields.
+pub fn render_table(value: &Value) -> Option<String> {
+    let Value::Group { open: '[', items: rows, .. } = value else {
+        return None;
+    };
+
+    let mut headers: Option<Vec<&str>> = None;
+    let mut cells = vec![];
+
+    for row in rows {
+        let Value::Group { items: fields, .. } = &row.value else {
+            return None;
+        };
+
+        let names = fields.iter().map(|field| field.name.as_deref()).collect::<Option<Vec<_>>>()?;
+        if names.is_empty() || headers.as_ref().is_some_and(|headers| *headers != names) {
+            return None;
+        }
+
+        headers = Some(names);
+        cells.push(fields.iter().map(|field| field.value.to_string()).collect::<Vec<_>>());
+    }
+
+    let headers = headers?;
+    let widths: Vec<_> = headers
+        .iter()
+        .enumerate()
+        .map(|(column, header)| {
+            cells.iter().map(|row| row[column].chars().count()).fold(header.len(), usize::max)
+        })
+        .collect();
+
+    let format_row = |row: &[&str]| {
+        let padded: Vec<_> =
+            row.iter().zip(&widths).map(|(cell, width)| format!("{cell:width$}")).collect();
+        padded.join(" | ").trim_end().to_string()
+    };
+
+    let mut table = format_row(&headers);
+    let rule: Vec<_> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    table.push('\n');
+    table.push_str(&rule.join("-+-"));
+
+    for row in &cells {
+        let row: Vec<_> = row.iter().map(String::as_str).collect();
+        table.push('\n');
+        table.push_str(&format_row(&row));
+    }
+
+    Some(table)
+}
+
+/// Render the value that is printed on the last line of the output as a
+/// table, if it can be. The rest of the output is kept as it is.
+pub fn tabulate(output: &str) -> Cow<'_, str> {
+    let trimmed = output.trim_end_matches('\n');
+    let (before, last) = match trimmed.rsplit_once('\n') {
+        Some((before, last)) => (Some(before), last),
+        None => (None, trimmed),
+    };
+
+    match render_table(&Value::parse(&strip_ansi(last))) {
+        Some(table) => match before {
+            Some(before) => Cow::Owned(format!("{before}\n{table}\n")),
+            None => Cow::Owned(format!("{table}\n")),
+        },
+        None => Cow::Borrowed(output),
+    }
+}
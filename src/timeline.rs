@@ -0,0 +1,122 @@
+//! The timeline of the inputs of a session, which is shown by `:timeline` so
+//! that long sessions can be navigated. Inputs can be run again by their
+//! number with `!n`.
+
+use std::{fmt::Write, time::Duration};
+
+use crate::outline::Outline;
+
+/// The length after which inputs are truncated in the timeline.
+const MAX_INPUT_LEN: usize = 40;
+
+/// The outcome of an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Error,
+    Interrupted,
+}
+
+impl Status {
+    /// The name of the status.
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Error => "error",
+            Status::Interrupted => "interrupted",
+        }
+    }
+
+    /// The icon of the status, or its name if the terminal is dumb.
+    fn icon(self, dumb: bool) -> &'static str {
+        match (self, dumb) {
+            (Status::Ok, false) => "✓",
+            (Status::Error, false) => "✗",
+            (Status::Interrupted, false) => "⏸",
+            (_, true) => self.name(),
+        }
+    }
+}
+
+/// An input of the session.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// The text of the input.
+    pub input: String,
+
+    /// The outcome of the input.
+    pub status: Status,
+
+    /// How long the input took to run.
+    pub duration: Duration,
+
+    /// The names that the input defined.
+    pub defined: Vec<String>,
+}
+
+/// The inputs of the session, in the order that they were run.
+#[derive(Debug, Default)]
+pub struct Timeline {
+    entries: Vec<Entry>,
+}
+
+impl Timeline {
+    /// Record an input, the names that it defined are only recorded if it
+    /// ran successfully.
+    pub fn record(&mut self, input: &str, status: Status, duration: Duration) {
+        let defined = match status {
+            Status::Ok if !input.trim_start().starts_with(':') => {
+                Outline::new(input).0.into_iter().map(|item| item.name).collect()
+            }
+            _ => vec![],
+        };
+
+        self.entries.push(Entry { input: input.to_string(), status, duration, defined });
+    }
+
+    /// Get the input with the given (1-based) number.
+    pub fn get(&self, number: usize) -> Option<&Entry> {
+        self.entries.get(number.checked_sub(1)?)
+    }
+
+    /// Render the timeline, only including the inputs that failed if
+    /// `errors` is set.
+    pub fn render(&self, errors: bool, dumb: bool) -> String {
+        let mut rendered = String::new();
+        let entries = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !errors || entry.status == Status::Error);
+
+        for (index, entry) in entries {
+            let first_line = entry.input.lines().next().unwrap_or_default().trim();
+            let mut input: String = first_line.chars().take(MAX_INPUT_LEN).collect();
+            if input.len() < first_line.len() || entry.input.trim().lines().nth(1).is_some() {
+                input.push('…');
+            }
+
+            let _ = write!(
+                rendered,
+                "{:>4} {:<2} {:>9.2?}  {input:<width$}",
+                index + 1,
+                entry.status.icon(dumb),
+                entry.duration,
+                width = MAX_INPUT_LEN + 1
+            );
+
+            if !entry.defined.is_empty() {
+                let _ = write!(rendered, "  defines {}", entry.defined.join(", "));
+            }
+
+            rendered.push('\n');
+        }
+
+        rendered
+    }
+}
+
+/// Parse a reference to an input of the timeline, i.e. `!n`.
+pub fn parse_reference(input: &str) -> Option<usize> {
+    input.trim().strip_prefix('!')?.parse().ok()
+}
@@ -0,0 +1,110 @@
+//! Limiting the amount of output that an evaluation prints, so that printing
+//! a huge value by accident doesn't freeze the terminal. The output that is
+//! cut off is kept, so that it can be shown with `:show-all` or written to a
+//! file with `:save-output <file>`. Only the first [`MAX_KEPT`] bytes are
+//! kept, so that a program which prints forever doesn't exhaust the memory.
+
+use crate::ansi::incomplete_escape;
+
+/// The number of bytes of the output of an evaluation that are kept.
+pub const MAX_KEPT: usize = 16 * 1024 * 1024;
+
+/// The output of an evaluation that is kept, up to [`MAX_KEPT`] bytes.
+#[derive(Debug, Default)]
+pub struct Kept {
+    /// The output that was kept.
+    text: String,
+
+    /// The number of bytes of output that were dropped.
+    dropped: usize,
+}
+
+impl Kept {
+    /// Keep as much of the next chunk of output as fits.
+    pub fn push(&mut self, text: &str) {
+        let mut len = text.len().min(MAX_KEPT.saturating_sub(self.text.len()));
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        self.text.push_str(&text[..len]);
+        self.dropped += text.len() - len;
+    }
+
+    /// Get the output that was kept, followed by a note if some of it was
+    /// dropped.
+    pub fn into_string(mut self) -> String {
+        if self.dropped > 0 {
+            self.text.push_str(&format!(
+                "\n... {} more bytes of output weren't kept, only the first {MAX_KEPT} bytes are\n",
+                self.dropped
+            ));
+        }
+
+        self.text
+    }
+}
+
+/// Tracks how much of the output of an evaluation has been written.
+#[derive(Debug)]
+pub struct Truncator {
+    /// The number of bytes that may be written, if there is a limit.
+    limit: Option<usize>,
+
+    /// The number of bytes that have been written.
+    written: usize,
+
+    /// The number of bytes of output in total.
+    total: usize,
+}
+
+impl Truncator {
+    /// Create a truncator with the given limit.
+    pub fn new(limit: Option<usize>) -> Self {
+        Self { limit, written: 0, total: 0 }
+    }
+
+    /// Get the part of the next chunk of output that can be written within
+    /// the limit.
+    pub fn admit<'t>(&mut self, text: &'t str) -> &'t str {
+        // Nothing more is written once the output has been cut off.
+        let truncated = self.is_truncated();
+        self.total += text.len();
+
+        let Some(limit) = self.limit else {
+            self.written += text.len();
+            return text;
+        };
+
+        if truncated {
+            return "";
+        }
+
+        let mut len = text.len().min(limit.saturating_sub(self.written));
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        // An escape sequence that is cut off would swallow the text after it.
+        if len < text.len() {
+            len = incomplete_escape(&text[..len]).unwrap_or(len);
+        }
+
+        self.written += len;
+        &text[..len]
+    }
+
+    /// Whether any of the output was cut off.
+    pub fn is_truncated(&self) -> bool {
+        self.total > self.written
+    }
+
+    /// The note that is printed after output that was cut off.
+    pub fn note(&self) -> String {
+        format!(
+            "\n... output truncated after {} of {} bytes, use `:show-all` to show all of it or \
+             `:save-output <file>` to save it\n",
+            self.written, self.total
+        )
+    }
+}
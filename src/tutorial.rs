@@ -0,0 +1,308 @@
+//! The interactive tutorial, which is started with `hashi tutorial <path>`.
+//! The path is either a lesson file, or a directory of lesson files which
+//! are taken in order of their names. A lesson consists of prose, which is
+//! shown to the user, and exercises that are answered at the prompt, e.g.
+//!
+//! ```text
+//! # Functions
+//! Functions are defined by binding a lambda to a name.
+//!
+//! :exercise
+//! Define `double`, which doubles an integer.
+//! :hint Multiply the argument by 2.
+//! :type double : (x: i32) -> i32
+//! :check double(2) == 4
+//! :expect double(-3) => -6
+//! :end
+//! ```
+//!
+//! The checks of an exercise are hidden, and are run in the session after
+//! each input. The exercise is complete once all of its checks pass, and the
+//! exercises that have been completed are remembered across sessions.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rustyline::error::ReadlineError;
+
+use crate::{
+    check::Check,
+    error::{InteractiveError, InteractiveResult},
+    input::LineSource,
+    session::Session,
+};
+
+/// The extension of lesson files.
+pub const LESSON_EXTENSION: &str = "lesson";
+
+/// A lesson of the tutorial.
+#[derive(Debug)]
+pub struct Lesson {
+    /// The name that the progress of the lesson is recorded under.
+    name: String,
+
+    /// The title of the lesson.
+    title: String,
+
+    /// The exercises of the lesson.
+    exercises: Vec<Exercise>,
+
+    /// The prose after the last exercise.
+    outro: String,
+}
+
+/// An exercise of a lesson.
+#[derive(Debug, Default)]
+struct Exercise {
+    /// The prose that comes before the exercise.
+    intro: String,
+
+    /// The task that the user is set.
+    prompt: String,
+
+    /// The hints that are given when the user asks for them.
+    hints: Vec<String>,
+
+    /// The hidden checks that the answer must pass.
+    checks: Vec<Check>,
+}
+
+impl Lesson {
+    /// Read the lesson at the given path.
+    pub fn open(path: &Path) -> InteractiveResult<Self> {
+        let contents = fs::read_to_string(path).map_err(|err| InteractiveError::io(path, err))?;
+        let error = |line: usize, message: String| InteractiveError::InvalidScript {
+            path: path.display().to_string(),
+            line,
+            message,
+        };
+
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let mut lines = contents.lines().enumerate().peekable();
+
+        let title = match lines.next_if(|(_, line)| line.starts_with("# ")) {
+            Some((_, line)) => line[2..].trim().to_string(),
+            None => name.clone(),
+        };
+
+        let mut exercises = vec![];
+        let mut prose = vec![];
+        let mut current: Option<Exercise> = None;
+
+        for (index, line) in lines {
+            let number = index + 1;
+
+            match (&mut current, line.trim()) {
+                (None, ":exercise") => {
+                    let intro = prose.join("\n").trim().to_string();
+                    current = Some(Exercise { intro, ..Exercise::default() });
+                    prose.clear();
+                }
+                (None, _) => prose.push(line),
+                (Some(exercise), ":end") => {
+                    if exercise.checks.is_empty() {
+                        return Err(error(number, "exercise has no checks".to_string()));
+                    }
+
+                    exercises.extend(current.take());
+                }
+                (Some(exercise), trimmed) => {
+                    if let Some(hint) = trimmed.strip_prefix(":hint ") {
+                        exercise.hints.push(hint.trim().to_string());
+                    } else if let Some(check) = Check::parse(trimmed) {
+                        exercise.checks.push(check.map_err(|message| error(number, message))?);
+                    } else if trimmed.starts_with(':') {
+                        return Err(error(number, format!("unknown line `{trimmed}` in exercise")));
+                    } else {
+                        exercise.prompt = format!("{}\n{line}", exercise.prompt).trim().to_string();
+                    }
+                }
+            }
+        }
+
+        if current.is_some() {
+            return Err(error(contents.lines().count(), "unterminated exercise".to_string()));
+        }
+
+        Ok(Self { name, title, exercises, outro: prose.join("\n").trim().to_string() })
+    }
+}
+
+/// Find the lessons at the given path, which is either a single lesson or a
+/// directory of lessons.
+fn find_lessons(path: &Path) -> InteractiveResult<Vec<Lesson>> {
+    if !path.is_dir() {
+        return Ok(vec![Lesson::open(path)?]);
+    }
+
+    let entries = fs::read_dir(path).map_err(|err| InteractiveError::io(path, err))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == LESSON_EXTENSION))
+        .collect();
+
+    if paths.is_empty() {
+        return Err(InteractiveError::NoSources(path.display().to_string()));
+    }
+
+    paths.sort();
+    paths.iter().map(|path| Lesson::open(path)).collect()
+}
+
+/// The exercises that the user has completed, which are recorded as
+/// `<lesson>/<exercise>` on each line of the progress file.
+struct Progress {
+    /// The file that the progress is stored in, if any.
+    path: Option<PathBuf>,
+
+    /// The exercises that have been completed.
+    completed: BTreeSet<String>,
+}
+
+impl Progress {
+    /// Read the progress of the user.
+    fn load() -> Self {
+        let path = dirs_next::data_dir().map(|dir| dir.join("hashi").join("tutorial-progress"));
+        let completed = path
+            .as_deref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| contents.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        Self { path, completed }
+    }
+
+    /// Check whether an exercise has been completed.
+    fn is_completed(&self, lesson: &Lesson, exercise: usize) -> bool {
+        self.completed.contains(&format!("{}/{exercise}", lesson.name))
+    }
+
+    /// Record that an exercise was completed.
+    fn complete(&mut self, lesson: &Lesson, exercise: usize) {
+        self.completed.insert(format!("{}/{exercise}", lesson.name));
+
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let contents: String = self.completed.iter().map(|entry| format!("{entry}\n")).collect();
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, contents));
+
+        if let Err(err) = result {
+            eprintln!("failed to save the progress of the tutorial: {err}");
+        }
+    }
+}
+
+/// Run the tutorial at the given path, reading the answers of the user from
+/// the reader. Inputs are run in the session as usual, so the user can try
+/// things out at any point, and `:hint` and `:skip` are also available.
+pub fn run_tutorial(
+    session: &mut Session,
+    reader: &mut impl LineSource,
+    path: &Path,
+) -> InteractiveResult<()> {
+    let lessons = find_lessons(path)?;
+    let mut progress = Progress::load();
+
+    for lesson in &lessons {
+        println!("\n# {}\n", lesson.title);
+
+        for (index, exercise) in lesson.exercises.iter().enumerate() {
+            let number = index + 1;
+            if progress.is_completed(lesson, number) {
+                println!("(exercise {number} is already complete)");
+                continue;
+            }
+
+            if !exercise.intro.is_empty() {
+                println!("{}\n", exercise.intro);
+            }
+
+            println!("Exercise {number}/{}: {}", lesson.exercises.len(), exercise.prompt);
+
+            // Skipped exercises aren't completed, so they are offered again
+            // the next time the tutorial is run.
+            match run_exercise(session, reader, exercise)? {
+                Outcome::Solved => progress.complete(lesson, number),
+                Outcome::Skipped => {}
+                Outcome::Quit => {
+                    println!("Goodbye! Your progress has been saved.");
+                    return Ok(());
+                }
+            }
+        }
+
+        if !lesson.outro.is_empty() {
+            println!("{}", lesson.outro);
+        }
+    }
+
+    println!("\nYou have completed the tutorial!");
+    Ok(())
+}
+
+/// How an exercise ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// The user answered the exercise.
+    Solved,
+
+    /// The user moved on to the next exercise with `:skip`.
+    Skipped,
+
+    /// The user left the tutorial.
+    Quit,
+}
+
+/// Read inputs until the exercise is solved or skipped, or the user leaves
+/// the tutorial.
+fn run_exercise(
+    session: &mut Session,
+    reader: &mut impl LineSource,
+    exercise: &Exercise,
+) -> InteractiveResult<Outcome> {
+    let mut hints = exercise.hints.iter();
+
+    loop {
+        let input = match reader.read_input() {
+            Ok(input) => input,
+            Err(ReadlineError::Eof | ReadlineError::Interrupted) => return Ok(Outcome::Quit),
+            Err(err) => return Err(InteractiveError::Internal(err.to_string())),
+        };
+
+        match input.trim() {
+            ":q" => return Ok(Outcome::Quit),
+            ":skip" => return Ok(Outcome::Skipped),
+            ":hint" => match hints.next() {
+                Some(hint) => println!("hint: {hint}"),
+                None => println!("There are no more hints for this exercise"),
+            },
+            trimmed => {
+                session.log_input(&input);
+                if crate::execute(session, &input).is_break() {
+                    return Ok(Outcome::Quit);
+                }
+
+                // Commands don't answer the exercise, other than `:load` which
+                // might have loaded the answer from a file.
+                if trimmed.starts_with(':') && !trimmed.starts_with(":load") {
+                    continue;
+                }
+
+                if exercise.checks.iter().all(|check| check.passes(session)) {
+                    println!("Correct!\n");
+                    return Ok(Outcome::Solved);
+                }
+
+                println!("Not quite, try again (`:hint` for a hint, `:skip` to move on)");
+            }
+        }
+    }
+}
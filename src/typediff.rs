@@ -0,0 +1,372 @@
+//! Comparing the types of two expressions structurally, so that the parts of
+//! two long signatures which disagree can be pointed out.
+
+use std::fmt::{self, Write};
+
+/// The colour of the parts of the left type which disagree.
+const REMOVED: &str = "\x1b[31m";
+
+/// The colour of the parts of the right type which disagree.
+const ADDED: &str = "\x1b[32m";
+
+const RESET: &str = "\x1b[0m";
+
+/// The structure of a type, as it is printed by the compiler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    /// A type without any structure that is compared, e.g. `i32` or `&str`.
+    Atom(String),
+
+    /// A type applied to arguments, e.g. `Map<str, i32>`.
+    Apply { name: String, args: Vec<Type> },
+
+    /// A tuple type, whose fields may be named, e.g. `(x: f64, y: f64)`.
+    Tuple(Vec<Field>),
+
+    /// A function type, e.g. `(x: i32) -> i32`.
+    Function { params: Vec<Field>, ret: Box<Type> },
+}
+
+/// A field of a tuple, or a parameter of a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// The name of the field, if it has one.
+    pub name: Option<String>,
+
+    /// The type of the field.
+    pub ty: Type,
+}
+
+impl Type {
+    /// Parse a type from the way it is printed. Types which can't be parsed
+    /// are kept as an atom, so that they are compared as a whole.
+    pub fn parse(text: &str) -> Self {
+        let mut parser = Parser { text: text.trim(), position: 0 };
+
+        match parser.parse_type() {
+            Some(ty) if parser.rest().trim().is_empty() => ty,
+            _ => Type::Atom(text.trim().to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&render(self, "", &[], ""))
+    }
+}
+
+/// A parser of printed types.
+struct Parser<'t> {
+    text: &'t str,
+    position: usize,
+}
+
+impl<'t> Parser<'t> {
+    /// Get the text that hasn't been parsed yet.
+    fn rest(&self) -> &'t str {
+        &self.text[self.position..]
+    }
+
+    /// Skip any whitespace, and then consume `token` if it is next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.position = self.text.len() - self.rest().trim_start().len();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.position += token.len();
+        }
+
+        found
+    }
+
+    fn parse_type(&mut self) -> Option<Type> {
+        if self.eat("(") {
+            let fields = self.parse_fields()?;
+            return match self.eat("->") {
+                true => Some(Type::Function { params: fields, ret: Box::new(self.parse_type()?) }),
+                false => Some(Type::Tuple(fields)),
+            };
+        }
+
+        // Atoms extend up to the next delimiter, keeping any brackets within
+        // them (e.g. `[i32]`) balanced.
+        let rest = self.rest().trim_start();
+        self.position = self.text.len() - rest.len();
+
+        let mut depth = 0usize;
+        let len = rest
+            .char_indices()
+            .find(|&(index, ch)| match ch {
+                '[' | '{' => {
+                    depth += 1;
+                    false
+                }
+                ']' | '}' if depth > 0 => {
+                    depth -= 1;
+                    false
+                }
+                ',' | '(' | ')' | '<' | '>' | ']' | '}' => depth == 0,
+                '-' => depth == 0 && rest[index..].starts_with("->"),
+                _ => false,
+            })
+            .map_or(rest.len(), |(index, _)| index);
+
+        let name = rest[..len].trim().to_string();
+        self.position += len;
+
+        if name.is_empty() {
+            return None;
+        }
+
+        if !self.eat("<") {
+            return Some(Type::Atom(name));
+        }
+
+        let mut args = vec![self.parse_type()?];
+        while self.eat(",") {
+            args.push(self.parse_type()?);
+        }
+
+        self.eat(">").then_some(Type::Apply { name, args })
+    }
+
+    /// Parse the fields of a tuple or the parameters of a function, after
+    /// the opening bracket.
+    fn parse_fields(&mut self) -> Option<Vec<Field>> {
+        let mut fields = vec![];
+
+        while !self.eat(")") {
+            if !fields.is_empty() && !self.eat(",") {
+                return None;
+            }
+
+            // A trailing comma, as in `(i32,)`.
+            if self.eat(")") {
+                break;
+            }
+
+            let rest = self.rest().trim_start();
+            let name = rest
+                .split_once(':')
+                .filter(|(name, after)| {
+                    !after.starts_with(':')
+                        && !name.is_empty()
+                        && name.chars().all(|ch| ch.is_alphanumeric() || ch == '_')
+                })
+                .map(|(name, _)| name.to_string());
+
+            if let Some(name) = &name {
+                self.eat(name);
+                self.eat(":");
+            }
+
+            fields.push(Field { name, ty: self.parse_type()? });
+        }
+
+        Some(fields)
+    }
+}
+
+/// A part of two types which disagree.
+#[derive(Debug, Clone)]
+pub struct Disagreement {
+    /// The path to the part within the types, e.g. `.x` or `->`.
+    pub path: String,
+
+    /// The part of the left type.
+    pub left: Type,
+
+    /// The part of the right type.
+    pub right: Type,
+}
+
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "type" } else { &self.path };
+        writeln!(f, "{path}:")?;
+        writeln!(f, "  - {}", self.left)?;
+        writeln!(f, "  + {}", self.right)
+    }
+}
+
+/// Compute the parts of two types which disagree.
+pub fn compare(left: &Type, right: &Type) -> Vec<Disagreement> {
+    let mut disagreements = vec![];
+    compare_at(String::new(), left, right, &mut disagreements);
+    disagreements
+}
+
+fn compare_at(path: String, left: &Type, right: &Type, disagreements: &mut Vec<Disagreement>) {
+    if left == right {
+        return;
+    }
+
+    let same_names = |l: &[Field], r: &[Field]| {
+        l.len() == r.len() && l.iter().zip(r).all(|(l, r)| l.name == r.name)
+    };
+
+    match (left, right) {
+        (Type::Tuple(l), Type::Tuple(r)) if same_names(l, r) => {
+            compare_fields(&path, l, r, disagreements);
+        }
+        (
+            Type::Function { params: l, ret: left_ret },
+            Type::Function { params: r, ret: right_ret },
+        ) if same_names(l, r) => {
+            compare_fields(&path, l, r, disagreements);
+            compare_at(format!("{path}->"), left_ret, right_ret, disagreements);
+        }
+        (Type::Apply { name: l_name, args: l }, Type::Apply { name: r_name, args: r })
+            if l_name == r_name && l.len() == r.len() =>
+        {
+            for (index, (l, r)) in l.iter().zip(r).enumerate() {
+                compare_at(format!("{path}<{index}>"), l, r, disagreements);
+            }
+        }
+        _ => disagreements.push(Disagreement { path, left: left.clone(), right: right.clone() }),
+    }
+}
+
+fn compare_fields(path: &str, l: &[Field], r: &[Field], disagreements: &mut Vec<Disagreement>) {
+    for (index, (l, r)) in l.iter().zip(r).enumerate() {
+        let path = match &l.name {
+            Some(name) => format!("{path}.{name}"),
+            None => format!("{path}.{index}"),
+        };
+
+        compare_at(path, &l.ty, &r.ty, disagreements);
+    }
+}
+
+/// Render a type, highlighting the parts at the given paths with `colour`.
+pub fn render(ty: &Type, path: &str, highlighted: &[&str], colour: &str) -> String {
+    let inner = match ty {
+        Type::Atom(name) => name.clone(),
+        Type::Apply { name, args } => {
+            let args: Vec<_> = args
+                .iter()
+                .enumerate()
+                .map(|(index, arg)| render(arg, &format!("{path}<{index}>"), highlighted, colour))
+                .collect();
+            format!("{name}<{}>", args.join(", "))
+        }
+        Type::Tuple(fields) => format!("({})", render_fields(fields, path, highlighted, colour)),
+        Type::Function { params, ret } => format!(
+            "({}) -> {}",
+            render_fields(params, path, highlighted, colour),
+            render(ret, &format!("{path}->"), highlighted, colour)
+        ),
+    };
+
+    match highlighted.contains(&path) {
+        true => format!("{colour}{inner}{RESET}"),
+        false => inner,
+    }
+}
+
+fn render_fields(fields: &[Field], path: &str, highlighted: &[&str], colour: &str) -> String {
+    let mut rendered = String::new();
+
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            rendered.push_str(", ");
+        }
+
+        let path = match &field.name {
+            Some(name) => {
+                let _ = write!(rendered, "{name}: ");
+                format!("{path}.{name}")
+            }
+            None => format!("{path}.{index}"),
+        };
+
+        rendered.push_str(&render(&field.ty, &path, highlighted, colour));
+    }
+
+    rendered
+}
+
+/// Render the two types that were compared, highlighting the parts of each
+/// that disagree.
+pub fn render_pair(left: &Type, right: &Type, disagreements: &[Disagreement]) -> (String, String) {
+    let paths: Vec<_> = disagreements.iter().map(|d| d.path.as_str()).collect();
+    (render(left, "", &paths, REMOVED), render(right, "", &paths, ADDED))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare, render_pair, Field, Type};
+
+    fn atom(name: &str) -> Type {
+        Type::Atom(name.to_string())
+    }
+
+    fn field(name: Option<&str>, ty: Type) -> Field {
+        Field { name: name.map(str::to_string), ty }
+    }
+
+    #[test]
+    fn parses_types() {
+        assert_eq!(
+            Type::parse(" Map<str, (x: i32, [u8])> "),
+            Type::Apply {
+                name: "Map".to_string(),
+                args: vec![
+                    atom("str"),
+                    Type::Tuple(vec![field(Some("x"), atom("i32")), field(None, atom("[u8]"))])
+                ],
+            }
+        );
+        assert_eq!(
+            Type::parse("(f: (i32) -> bool) -> i32"),
+            Type::Function {
+                params: vec![field(
+                    Some("f"),
+                    Type::Function {
+                        params: vec![field(None, atom("i32"))],
+                        ret: atom("bool").into()
+                    }
+                )],
+                ret: atom("i32").into(),
+            }
+        );
+        assert_eq!(Type::parse("(i32,)"), Type::Tuple(vec![field(None, atom("i32"))]));
+    }
+
+    #[test]
+    fn keeps_unparsable_types_whole() {
+        for text in ["Map<i32", "(i32", "(x: i32,, y: i32)", "i32 >"] {
+            assert_eq!(Type::parse(text), atom(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn prints_parsed_types() {
+        for text in ["Map<str, (x: i32, [u8])>", "(f: (i32) -> bool) -> (i32, i32)", "&str"] {
+            assert_eq!(Type::parse(text).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn finds_the_parts_that_disagree() {
+        let left = Type::parse("(x: i32, y: Map<str, i32>) -> bool");
+        let right = Type::parse("(x: i32, y: Map<str, u8>) -> char");
+        let disagreements = compare(&left, &right);
+
+        let paths: Vec<_> = disagreements.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, [".y<1>", "->"]);
+
+        let (left, right) = render_pair(&left, &right, &disagreements);
+        assert_eq!(left, "(x: i32, y: Map<str, \x1b[31mi32\x1b[0m>) -> \x1b[31mbool\x1b[0m");
+        assert_eq!(right, "(x: i32, y: Map<str, \x1b[32mu8\x1b[0m>) -> \x1b[32mchar\x1b[0m");
+    }
+
+    #[test]
+    fn compares_mismatched_shapes_whole() {
+        let left = Type::parse("(x: i32)");
+        let disagreements = compare(&left, &Type::parse("(y: i32)"));
+        assert_eq!(disagreements.len(), 1);
+        assert_eq!(disagreements[0].path, "");
+        assert!(compare(&left, &left).is_empty());
+    }
+}
@@ -0,0 +1,279 @@
+//! A structural view of the values that are printed by the compiler, so that
+//! values can be compared by the REPL. Printed values are parsed loosely,
+//! anything which isn't a tuple, list, block or constructor application is
+//! kept as an atom of text.
+
+use std::fmt;
+
+/// A printed value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// A value without any structure, e.g. a number or a string.
+    Atom(String),
+
+    /// A value made up of other values, e.g. `Foo(x = 1, y = 2)` or
+    /// `[1, 2, 3]`.
+    Group {
+        /// The name of the constructor of the value, if it has one.
+        name: Option<String>,
+
+        /// The bracket that opens the group.
+        open: char,
+
+        /// The members of the group.
+        items: Vec<Field>,
+    },
+}
+
+/// A member of a [`Value::Group`], which is optionally named.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// The name of the field, if it has one.
+    pub name: Option<String>,
+
+    /// The value of the field.
+    pub value: Value,
+}
+
+/// Get the bracket which closes the given opening bracket.
+fn closing(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    }
+}
+
+impl Value {
+    /// Parse a printed value.
+    pub fn parse(text: &str) -> Self {
+        let mut parser = Parser { chars: text.trim().chars().collect(), position: 0 };
+        let value = parser.value();
+
+        // If the value isn't understood in its entirety, then it is treated
+        // as a single atom.
+        match parser.position == parser.chars.len() {
+            true => value,
+            false => Value::Atom(text.trim().to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Atom(text) => write!(f, "{text}"),
+            Value::Group { name, open, items } => {
+                if let Some(name) = name {
+                    write!(f, "{name}")?;
+                }
+
+                write!(f, "{open}")?;
+                for (index, field) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    if let Some(name) = &field.name {
+                        write!(f, "{name} = ")?;
+                    }
+
+                    write!(f, "{}", field.value)?;
+                }
+                write!(f, "{}", closing(*open))
+            }
+        }
+    }
+}
+
+/// A parser for printed values.
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Parse a single value.
+    fn value(&mut self) -> Value {
+        self.skip_whitespace();
+
+        let text = self.atom();
+        let name = (!text.is_empty()).then_some(text);
+
+        match self.peek() {
+            Some(open @ ('(' | '[' | '{')) => {
+                self.position += 1;
+                let items = self.items(closing(open));
+                Value::Group { name, open, items }
+            }
+            _ => Value::Atom(name.unwrap_or_default()),
+        }
+    }
+
+    /// Parse the members of a group up to the closing bracket.
+    fn items(&mut self, close: char) -> Vec<Field> {
+        let mut items = vec![];
+
+        loop {
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(ch) if ch == close => {
+                    self.position += 1;
+                    break;
+                }
+                Some(',') => self.position += 1,
+                Some(_) => items.push(self.field()),
+                None => break,
+            }
+        }
+
+        items
+    }
+
+    /// Parse a member of a group, which is either `name = value`,
+    /// `name: value` or just `value`.
+    fn field(&mut self) -> Field {
+        let start = self.position;
+        let text = self.atom();
+
+        self.skip_whitespace();
+        let is_named = !text.is_empty()
+            && text.chars().all(|ch| ch.is_alphanumeric() || ch == '_')
+            && matches!(self.peek(), Some('=' | ':'));
+
+        if is_named {
+            self.position += 1;
+            return Field { name: Some(text), value: self.value() };
+        }
+
+        self.position = start;
+        Field { name: None, value: self.value() }
+    }
+
+    /// Read the text of an atom, up to the next delimiter. Quoted strings
+    /// and characters are read in their entirety.
+    fn atom(&mut self) -> String {
+        let mut text = String::new();
+        let mut quote = None;
+
+        while let Some(ch) = self.peek() {
+            match quote {
+                Some(q) if ch == q => quote = None,
+                Some(_) if ch == '\\' => {
+                    text.push(ch);
+                    self.position += 1;
+                    let Some(escaped) = self.peek() else { break };
+                    text.push(escaped);
+                    self.position += 1;
+                    continue;
+                }
+                Some(_) => {}
+                None if ch == '"' || ch == '\'' => quote = Some(ch),
+                // Paths such as `Option::Some` are kept together.
+                None if ch == ':' && self.chars.get(self.position + 1) == Some(&':') => {
+                    text.push_str("::");
+                    self.position += 2;
+                    continue;
+                }
+                None if matches!(ch, ',' | '(' | ')' | '[' | ']' | '{' | '}' | '=' | ':') => break,
+                None => {}
+            }
+
+            text.push(ch);
+            self.position += 1;
+        }
+
+        text.trim().to_string()
+    }
+}
+
+/// A difference between two values, at the given path within the values.
+#[derive(Debug, Clone)]
+pub struct Difference {
+    /// The path to the differing part of the values, e.g. `.point.x`.
+    pub path: String,
+
+    /// The part of the left value, or `None` if it is missing.
+    pub left: Option<Value>,
+
+    /// The part of the right value, or `None` if it is missing.
+    pub right: Option<Value>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "value" } else { &self.path };
+        writeln!(f, "{path}:")?;
+
+        for (sign, value) in [('-', &self.left), ('+', &self.right)] {
+            match value {
+                Some(value) => writeln!(f, "  {sign} {value}")?,
+                None => writeln!(f, "  {sign} <missing>")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute the structural differences between two values.
+pub fn diff(left: &Value, right: &Value) -> Vec<Difference> {
+    let mut differences = vec![];
+    diff_at(String::new(), left, right, &mut differences);
+    differences
+}
+
+fn diff_at(path: String, left: &Value, right: &Value, differences: &mut Vec<Difference>) {
+    if left == right {
+        return;
+    }
+
+    let (
+        Value::Group { name: left_name, open: left_open, items: left_items },
+        Value::Group { name: right_name, open: right_open, items: right_items },
+    ) = (left, right)
+    else {
+        differences.push(Difference { path, left: Some(left.clone()), right: Some(right.clone()) });
+        return;
+    };
+
+    let same_shape = left_name == right_name
+        && left_open == right_open
+        && left_items.iter().zip(right_items).all(|(l, r)| l.name == r.name);
+
+    if !same_shape {
+        differences.push(Difference { path, left: Some(left.clone()), right: Some(right.clone()) });
+        return;
+    }
+
+    for index in 0..left_items.len().max(right_items.len()) {
+        let (l, r) = (left_items.get(index), right_items.get(index));
+        let field = l.or(r).and_then(|field| field.name.as_ref());
+
+        let path = match (field, left_open) {
+            (Some(name), _) => format!("{path}.{name}"),
+            (None, '[') => format!("{path}[{index}]"),
+            (None, _) => format!("{path}.{index}"),
+        };
+
+        match (l, r) {
+            (Some(l), Some(r)) => diff_at(path, &l.value, &r.value, differences),
+            (l, r) => differences.push(Difference {
+                path,
+                left: l.map(|field| field.value.clone()),
+                right: r.map(|field| field.value.clone()),
+            }),
+        }
+    }
+}